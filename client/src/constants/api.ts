@@ -4,13 +4,36 @@ const apiBase = `${apiDomain}/api`;
 export const API = {
   LOGIN: `${apiBase}/login`,
   REGISTER: `${apiBase}/register`,
+  PASSWORD_RESET: `${apiBase}/password_reset`,
+  PASSWORD_RESET_CONFIRM: `${apiBase}/password_reset/confirm`,
+  VERIFY_EMAIL: `${apiBase}/verify_email`,
+  RESEND_VERIFICATION: `${apiBase}/verify_email/resend`,
+  REFRESH: `${apiBase}/refresh`,
+  LOGOUT: `${apiBase}/logout`,
+  SESSIONS: `${apiBase}/sessions`,
+  SESSION: (sessionId: string) => `${apiBase}/session/${sessionId}`,
+  API_TOKENS: `${apiBase}/api_tokens`,
+  API_TOKEN: (tokenId: string) => `${apiBase}/api_token/${tokenId}`,
   CREATE_USER: `${apiBase}/create_user`,
   GET_USER: `${apiBase}/user`,
   GET_USER_LINKS: `${apiBase}/user/links`,
   CREATE_LINK: `${apiBase}/link`,
   UPDATE_LINK: `${apiBase}/link`,
+  REORDER_LINKS: `${apiBase}/links/order`,
   DELETE_LINK: (linkId: string) => `${apiBase}/link/${linkId}`,
+  LINK_ALIAS: (linkId: string) => `${apiBase}/link/${linkId}/alias`,
+  GO: (path: string) => `${apiBase}/go/${path}`,
+  ALIASES: `${apiBase}/aliases`,
+  CHECK_ALIAS: (alias: string) => `${apiBase}/aliases/${encodeURIComponent(alias)}`,
   SUGGEST: (query: string) => `${apiBase}/suggest/${query}`,
+  SEARCH_ENGINES: `${apiBase}/search_engines`,
+  SEARCH_ENGINE: (engineId: string) => `${apiBase}/search_engine/${engineId}`,
+  RESOLVE_SEARCH: (query: string) =>
+    `${apiBase}/search_engines/resolve?q=${encodeURIComponent(query)}`,
+  OPENSEARCH: (token: string) => `${apiBase}/opensearch.xml?token=${token}`,
+  BANGS: `${apiBase}/bangs`,
+  SEARCH_HISTORY: `${apiBase}/search_history`,
+  SEARCH_HISTORY_ENTRY: (entryId: string) => `${apiBase}/search_history/${entryId}`,
   SEARCH_ICONS: (query: string) => `${apiBase}/icons/search/${encodeURIComponent(query)}`,
   FEEDBACK: `${apiBase}/feedback`,
   CREATE_SETTINGS: `${apiBase}/settings`,
@@ -19,3 +42,7 @@ export const API = {
   GET_USER_DATA: `${apiBase}/user_data`,
   STAGING_LOGIN: `${apiBase}/staging_login`,
 } as const;
+
+// Server-relative paths (e.g. stored icons) need the API domain when the client is served separately
+export const resolveApiUrl = (path: string) =>
+  path.startsWith("/api/") ? `${apiDomain}${path}` : path;
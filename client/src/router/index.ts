@@ -29,6 +29,12 @@ const router = createRouter({
       component: () => import("../views/Home.vue"),
       meta: { requiresAuth: true },
     },
+    {
+      path: "/go/:path(.*)*",
+      name: "go",
+      component: () => import("../views/GoLink.vue"),
+      meta: { requiresAuth: true },
+    },
     {
       path: "/staging-login",
       name: "stagingLogin",
@@ -63,7 +69,8 @@ router.beforeEach(async (to, from, next) => {
 
   // Handle auth-required routes
   if (to.meta.requiresAuth && !isAuthenticated) {
-    next("/login");
+    // Go links pick up where they left off once the user logs in
+    next(to.name === "go" ? { path: "/login", query: { redirect: to.fullPath } } : "/login");
     return;
   }
 
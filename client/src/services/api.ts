@@ -1,6 +1,7 @@
 // src/services/api.ts
+import authService from "@/services/auth";
 import { useUserStore } from "@/stores/user";
-import axios, { type AxiosInstance } from "axios";
+import axios, { type AxiosInstance, type InternalAxiosRequestConfig } from "axios";
 
 const apiDomain = import.meta.env.VITE_API_BASE_URL || "http://localhost:3000";
 const api: AxiosInstance = axios.create({
@@ -20,20 +21,41 @@ api.interceptors.request.use(
   },
 );
 
+// Requests failing at the same time share one refresh, a refresh token only
+// works once
+let refreshing: Promise<string | null> | null = null;
+const refreshOnce = () => {
+  if (!refreshing) {
+    refreshing = authService.refresh().finally(() => {
+      refreshing = null;
+    });
+  }
+  return refreshing;
+};
+
 // Response interceptor for token refresh and error handling
 api.interceptors.response.use(
-  (response) => {
-    // Check for new token in response headers (auto-refresh from backend)
-    const newToken = response.headers["x-new-auth-token"];
-    if (newToken) {
-      localStorage.setItem("token", newToken);
+  (response) => response,
+  async (error) => {
+    const request = error.config as
+      | (InternalAxiosRequestConfig & { _retried?: boolean })
+      | undefined;
+    if (error.response?.status === 401 && request && !request._retried) {
+      // Access tokens are short-lived, try once more with a fresh one
+      request._retried = true;
+      const token = await refreshOnce();
+      if (token) {
+        request.headers.set("Authorization", `Bearer ${token}`);
+        if (request.headers.has("X-User-Authorization")) {
+          request.headers.set("X-User-Authorization", token);
+        }
+        return api(request);
+      }
     }
-    return response;
-  },
-  (error) => {
     if (error.response?.status === 401) {
-      // Token expired or invalid - clear and redirect
+      // Session expired or revoked - clear and redirect
       localStorage.removeItem("token");
+      localStorage.removeItem("refresh_token");
       const userStore = useUserStore();
       userStore.clearUser();
       window.location.href = "/";
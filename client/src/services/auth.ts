@@ -1,6 +1,6 @@
 import { API } from "@/constants/api";
 import { useUserStore } from "@/stores/user";
-import type { AuthResponse } from "@/types/User";
+import type { AuthResponse, RefreshResponse } from "@/types/User";
 import axios from "axios";
 
 const authApi = axios.create({
@@ -25,10 +25,50 @@ export const authService = {
     return response.data;
   },
 
-  logout(): void {
+  async requestPasswordReset(email: string): Promise<void> {
+    await authApi.post(API.PASSWORD_RESET, { email });
+  },
+
+  async resetPassword(token: string, password: string): Promise<void> {
+    await authApi.post(API.PASSWORD_RESET_CONFIRM, { token, password });
+  },
+
+  async verifyEmail(token: string): Promise<void> {
+    await authApi.post(API.VERIFY_EMAIL, { token });
+  },
+
+  async resendVerification(email: string): Promise<void> {
+    await authApi.post(API.RESEND_VERIFICATION, { email });
+  },
+
+  async logout(): Promise<void> {
+    const token = localStorage.getItem("token");
     localStorage.removeItem("token");
+    localStorage.removeItem("refresh_token");
     const userStore = useUserStore();
     userStore.clearUser();
+
+    // Revoke the session on the server too, best effort
+    if (token) {
+      await authApi
+        .post(API.LOGOUT, null, { headers: { Authorization: `Bearer ${token}` } })
+        .catch(() => undefined);
+    }
+  },
+
+  // Trade the refresh token for a new access token, null when the session is gone
+  async refresh(): Promise<string | null> {
+    const refreshToken = localStorage.getItem("refresh_token");
+    if (!refreshToken) return null;
+    try {
+      const response = await authApi.post<RefreshResponse>(API.REFRESH, {
+        refresh_token: refreshToken,
+      });
+      this.setToken(response.data.token, response.data.refresh_token);
+      return response.data.token;
+    } catch {
+      return null;
+    }
   },
 
   getToken(): string | null {
@@ -39,8 +79,11 @@ export const authService = {
     return !!localStorage.getItem("token");
   },
 
-  setToken(token: string): void {
+  setToken(token: string, refreshToken?: string): void {
     localStorage.setItem("token", token);
+    if (refreshToken) {
+      localStorage.setItem("refresh_token", refreshToken);
+    }
   },
 };
 
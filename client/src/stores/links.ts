@@ -180,6 +180,28 @@ export const useLinksStore = defineStore("links", {
       return true;
     },
 
+    // Reserve a go link alias for a link, or release it when empty.
+    // Returns why the alias couldn't be saved, null when it was.
+    async setLinkAlias(link: Link, alias: string): Promise<string | null> {
+      try {
+        if (alias.trim()) {
+          const response = await api.put<Link>(API.LINK_ALIAS(link.id), { alias });
+          link.alias = response.data.alias;
+        } else {
+          await api.delete(API.LINK_ALIAS(link.id));
+          link.alias = null;
+        }
+        this.links = this.links.map((l) => (l.id === link.id ? link : l));
+        cache.set(CacheKeys.LINKS, this.links);
+        return null;
+      } catch (error) {
+        const status = (error as AxiosError).response?.status;
+        if (status === 409) return "Another link already uses this alias";
+        if (status === 400) return "Use letters, digits, -, _ and . only";
+        return "Failed to save the alias";
+      }
+    },
+
     isLink(obj: Link): obj is Link {
       return (
         typeof obj === "object" &&
@@ -237,11 +259,16 @@ export const useLinksStore = defineStore("links", {
       // Update cache
       cache.set(CacheKeys.LINKS, this.links);
 
-      // Persist to server - update each link's order_index
+      // Persist to server in one request, the server applies it atomically
       try {
-        for (const link of columnLinks) {
-          await this.updateLink(link);
-        }
+        await api.patch(API.REORDER_LINKS, {
+          links: columnLinks.map((link) => ({
+            link_id: link.id,
+            column_id: link.column_id ?? null,
+            column_type: link.column_id ? null : link.column_type,
+            position: link.order_index,
+          })),
+        });
       } catch (error) {
         console.error("Error persisting link order:", error);
       }
@@ -1,23 +1,103 @@
+import { API } from "@/constants/api";
+import api from "@/services/api";
+import type { NewSearchEngine, ResolvedSearch, SearchEngine } from "@/types/SearchEngine";
 import { CacheKeys, cache } from "@/utils/cache";
 import { defineStore } from "pinia";
 
+// Used until the user's engines have loaded
+const FALLBACK_ENGINE: SearchEngine = {
+  id: "",
+  name: "Google",
+  url: "https://www.google.com/search?q=%s",
+  icon: "icons/google.svg",
+  is_default: true,
+  order_index: 0,
+};
+
+// A word starting with "!" at either end of the query, resolved by the server
+const BANG_PATTERN = /^!\S|\s!\S+$/;
+
 export const useSearchEngineStore = defineStore("searchEngine", {
   state: () => {
-    const cachedEngine = cache.get<string>(CacheKeys.SEARCH_ENGINE);
+    const cachedEngines = cache.get<SearchEngine[]>(CacheKeys.SEARCH_ENGINES) ?? [];
     return {
-      searchEngines: [
-        { name: "Brave", url: "https://search.brave.com/search?q=" },
-        { name: "Perplexity", url: "https://www.perplexity.ai/search?q=" },
-        { name: "Google", url: "https://www.google.com/search?q=" },
-        { name: "Bing", url: "https://www.bing.com/search?q=" },
-      ],
-      selectedEngine: cachedEngine || "https://www.google.com/search?q=",
+      searchEngines: cachedEngines,
+      selectedEngine: cachedEngines.find((engine) => engine.is_default)?.id ?? "",
     };
   },
+  getters: {
+    currentEngine(state): SearchEngine {
+      return (
+        state.searchEngines.find((engine) => engine.id === state.selectedEngine) ??
+        FALLBACK_ENGINE
+      );
+    },
+  },
   actions: {
-    setSearchEngine(url: string) {
-      this.selectedEngine = url;
-      cache.set(CacheKeys.SEARCH_ENGINE, url);
+    async fetchSearchEngines() {
+      try {
+        const response = await api.get<SearchEngine[]>(API.SEARCH_ENGINES);
+        this.setEngines(response.data);
+      } catch (error) {
+        console.error("Failed to fetch search engines:", error);
+      }
+    },
+
+    setEngines(engines: SearchEngine[]) {
+      this.searchEngines = engines;
+      this.selectedEngine = engines.find((engine) => engine.is_default)?.id ?? "";
+      cache.set(CacheKeys.SEARCH_ENGINES, engines);
+    },
+
+    // Makes the engine the default on every device
+    async setSearchEngine(id: string) {
+      const engine = this.searchEngines.find((engine) => engine.id === id);
+      if (!engine) return;
+      this.selectedEngine = id;
+      await this.updateSearchEngine(id, { ...engine, is_default: true });
+    },
+
+    async createSearchEngine(engine: NewSearchEngine) {
+      await api.post(API.SEARCH_ENGINES, engine);
+      await this.fetchSearchEngines();
+    },
+
+    async updateSearchEngine(id: string, engine: NewSearchEngine) {
+      try {
+        await api.put(API.SEARCH_ENGINE(id), {
+          ...engine,
+          keyword: engine.keyword ?? undefined,
+          icon: engine.icon ?? undefined,
+        });
+        await this.fetchSearchEngines();
+      } catch (error) {
+        console.error("Failed to update search engine:", error);
+        throw error;
+      }
+    },
+
+    async deleteSearchEngine(id: string) {
+      await api.delete(API.SEARCH_ENGINE(id));
+      await this.fetchSearchEngines();
+    },
+
+    searchUrl(query: string) {
+      return this.currentEngine.url.replace("%s", encodeURIComponent(query));
+    },
+
+    hasBang(query: string) {
+      return BANG_PATTERN.test(query.trim());
+    },
+
+    // Where the server sends a query with a bang, null when it can't be reached
+    async resolve(query: string): Promise<string | null> {
+      try {
+        const response = await api.get<ResolvedSearch>(API.RESOLVE_SEARCH(query));
+        return response.data.url;
+      } catch (error) {
+        console.error("Failed to resolve search:", error);
+        return null;
+      }
     },
   },
 });
@@ -0,0 +1,68 @@
+import { API } from "@/constants/api";
+import api from "@/services/api";
+import type { SearchHistory } from "@/types/SearchHistory";
+import { CacheKeys, cache } from "@/utils/cache";
+import { defineStore } from "pinia";
+import { useUserSettingsStore } from "./settings";
+
+const MAX_HISTORY_ENTRIES = 500;
+
+const normalize = (query: string) => query.trim().split(/\s+/).join(" ").toLowerCase();
+
+export const useSearchHistoryStore = defineStore("searchHistory", {
+  state: () => {
+    const cached = cache.get<SearchHistory[]>(CacheKeys.SEARCH_HISTORY);
+    return {
+      // Best frecency first, as the server ranks them
+      entries: Array.isArray(cached) ? cached : ([] as SearchHistory[]),
+    };
+  },
+  actions: {
+    async fetchHistory() {
+      try {
+        const response = await api.get<SearchHistory[]>(API.SEARCH_HISTORY, {
+          params: { limit: MAX_HISTORY_ENTRIES },
+        });
+        this.entries = response.data;
+        cache.set(CacheKeys.SEARCH_HISTORY, this.entries);
+      } catch (error) {
+        console.error("Failed to fetch search history:", error);
+      }
+    },
+
+    // Nothing is recorded, here or on the server, while history is off
+    async record(query: string) {
+      if (!query.trim() || !useUserSettingsStore().settings.search_history) return;
+
+      const existing = this.entries.find((entry) => normalize(entry.query) === normalize(query));
+      if (existing) {
+        existing.query = query.trim();
+        existing.searches += 1;
+        existing.last_searched_at = new Date().toISOString();
+      }
+
+      try {
+        const response = await api.post<SearchHistory>(API.SEARCH_HISTORY, { query });
+        if (response.status === 201 && !existing) {
+          this.entries.unshift(response.data);
+          this.entries = this.entries.slice(0, MAX_HISTORY_ENTRIES);
+        }
+      } catch (error) {
+        console.error("Failed to record search:", error);
+      }
+      cache.set(CacheKeys.SEARCH_HISTORY, this.entries);
+    },
+
+    async remove(id: string) {
+      await api.delete(API.SEARCH_HISTORY_ENTRY(id));
+      this.entries = this.entries.filter((entry) => entry.id !== id);
+      cache.set(CacheKeys.SEARCH_HISTORY, this.entries);
+    },
+
+    async clear() {
+      await api.delete(API.SEARCH_HISTORY);
+      this.entries = [];
+      cache.clear(CacheKeys.SEARCH_HISTORY);
+    },
+  },
+});
@@ -1,6 +1,6 @@
 import { API } from "@/constants/api";
 import api from "@/services/api";
-import type { UserSettings } from "@/types/UserSettings";
+import type { ToggleSetting, UserSettings } from "@/types/UserSettings";
 import { CacheKeys, cache } from "@/utils/cache";
 import { defineStore } from "pinia";
 import { useUserStore } from "./user";
@@ -18,7 +18,7 @@ export const useUserSettingsStore = defineStore("userSettings", {
     } as UserSettings,
   }),
   actions: {
-    async updateSetting(key: keyof UserSettings, value: boolean) {
+    async updateSetting(key: ToggleSetting, value: boolean) {
       console.log("Updating setting:", key, value);
       this.settings[key] = value;
       console.log("Settings:", this.settings);
@@ -35,6 +35,33 @@ export const useUserSettingsStore = defineStore("userSettings", {
       }
     },
 
+    async updateLocale(locale: string, region: string) {
+      this.settings.locale = locale.trim() || undefined;
+      this.settings.region = region.trim() || undefined;
+      try {
+        const userStore = useUserStore();
+        if (!userStore.userId) return;
+        await api.put(API.UPDATE_SETTINGS, this.settings);
+        cache.set(CacheKeys.SETTINGS, this.settings);
+      } catch (error) {
+        console.error("Failed to update locale:", error);
+        throw error;
+      }
+    },
+
+    async updateHistoryRetention(days: number | undefined) {
+      this.settings.search_history_days = days;
+      try {
+        const userStore = useUserStore();
+        if (!userStore.userId) return;
+        await api.put(API.UPDATE_SETTINGS, this.settings);
+        cache.set(CacheKeys.SETTINGS, this.settings);
+      } catch (error) {
+        console.error("Failed to update search history retention:", error);
+        throw error;
+      }
+    },
+
     async fetchSettings() {
       // Only fetch if not already populated by user store
       if (Object.values(this.settings).every((val) => val === false)) {
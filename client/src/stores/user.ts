@@ -5,6 +5,7 @@ import type { AuthUser, UserDataResponse, UserState } from "@/types/User";
 import { CacheKeys, cache } from "@/utils/cache";
 import { defineStore } from "pinia";
 import { useLinksStore } from "./links";
+import { useSearchEngineStore } from "./searchEngine";
 import { useUserSettingsStore } from "./settings";
 
 export const useUserStore = defineStore("user", {
@@ -81,11 +82,6 @@ export const useUserStore = defineStore("user", {
         if (data.user) {
           this.setEmail(data.user.email);
           this.setUserId(data.user.id);
-
-          // Store the auth token
-          if (data.user.auth_token) {
-            this.setAuthToken(data.user.auth_token);
-          }
         }
 
         if (data.links) {
@@ -102,6 +98,8 @@ export const useUserStore = defineStore("user", {
           cache.set(CacheKeys.SETTINGS, settings);
         }
 
+        await useSearchEngineStore().fetchSearchEngines();
+
         // Update user cache with latest state
         cache.set(CacheKeys.USER, this.$state);
         return true;
@@ -154,8 +152,10 @@ export const useUserStore = defineStore("user", {
       this.auth_token = token;
     },
 
+    // Access tokens are short-lived, the one in localStorage is kept fresh by
+    // the api interceptor
     getAuthToken(): string | null {
-      return this.auth_token;
+      return localStorage.getItem("token") || this.auth_token;
     },
 
     /**
@@ -9,6 +9,21 @@ export type Link = {
   created_at: string;
   description: string | null;
   column_type: string;
+  column_id?: string | null;
+  alias?: string | null;
+  tags?: string[];
+};
+
+// A link with a go link alias, as the user sees it
+export type GoLink = {
+  alias: string;
+  link_id: string;
+  title: string;
+  url: string;
+  owner_type: string;
+  owner_id: string;
+  // false when another link with the same alias wins
+  active: boolean;
 };
 
 export type CreateLinkRequest = {
@@ -20,6 +35,8 @@ export type CreateLinkRequest = {
   owner_type: string;
   owner_id: string;
   column_type: string;
+  column_id?: string | null;
+  tags?: string[];
 };
 
 export type UpdateLinkRequest = {
@@ -29,5 +46,7 @@ export type UpdateLinkRequest = {
   title: string | null;
   icon: string | null;
   column_type: string | null;
+  column_id?: string | null;
   order_index?: number;
+  tags?: string[];
 };
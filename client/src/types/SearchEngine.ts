@@ -1,5 +1,24 @@
 export type SearchEngine = {
-  icon: string;
+  id: string;
   name: string;
+  // Search URL, %s is replaced by the query
   url: string;
+  // Bang without the "!", `!keyword terms` searches this engine
+  keyword?: string | null;
+  icon?: string | null;
+  is_default: boolean;
+  order_index: number;
+};
+
+export type NewSearchEngine = {
+  name: string;
+  url: string;
+  keyword?: string;
+  icon?: string;
+  is_default?: boolean;
+};
+
+export type ResolvedSearch = {
+  url: string;
+  kind: "url" | "search";
 };
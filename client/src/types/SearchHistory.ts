@@ -1,5 +1,10 @@
 export type SearchHistory = {
+  id: string;
   query: string;
-  timestamp: number;
-  freq: number;
+  // How many times it was searched
+  searches: number;
+  last_searched_at: string;
+  created_at: string;
+  // Frecency, searches weighted by how recent the last one was
+  score: number;
 };
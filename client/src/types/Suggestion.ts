@@ -1,5 +1,9 @@
 export type Suggestions = {
   query: string;
+  // Provider it came from: history, links, brave or a suggestion endpoint's host
+  source: string;
+  // Set for suggestions that open a link instead of searching
+  url?: string;
 };
 
 export type SuggestionsResponse = {
@@ -5,6 +5,7 @@ export type User = {
   id: string;
   email: string;
   createdAt: string;
+  email_verified_at?: string | null;
   auth_token?: string;
 };
 
@@ -26,11 +27,28 @@ export type AuthUser = {
 };
 
 // Response from login/register endpoints
+// token is missing when the server wants the email confirmed first
 export type AuthResponse = {
-  token: string;
+  token?: string;
+  refresh_token?: string;
   user: User;
 };
 
+export type RefreshResponse = {
+  token: string;
+  refresh_token: string;
+};
+
+// A signed-in browser or device
+export type Session = {
+  id: string;
+  user_agent: string | null;
+  created_at: string;
+  last_used_at: string;
+  expires_at: string;
+  current: boolean;
+};
+
 export type UserDataResponse = {
   user: User;
   settings: settings_blob;
@@ -6,8 +6,18 @@ export type UserSettings = {
   linear_api: boolean;
   new_tabs: boolean;
   metadata: boolean;
+  // Language and country for search suggestions, like "en-GB" and "GB"
+  locale?: string;
+  region?: string;
+  // Days searches are kept for, the server's default when unset
+  search_history_days?: number;
 };
 
+export type ToggleSetting = Exclude<
+  keyof UserSettings,
+  "locale" | "region" | "search_history_days"
+>;
+
 export const UserSettingsLabels = [
   {
     label: "Enable Search History",
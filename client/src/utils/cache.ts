@@ -5,7 +5,7 @@ export const CacheKeys = {
   USER: `${CACHE_PREFIX}${CACHE_VERSION}user`,
   LINKS: `${CACHE_PREFIX}${CACHE_VERSION}links`,
   SETTINGS: `${CACHE_PREFIX}${CACHE_VERSION}settings`,
-  SEARCH_ENGINE: `${CACHE_PREFIX}${CACHE_VERSION}search_engine`,
+  SEARCH_ENGINES: `${CACHE_PREFIX}${CACHE_VERSION}search_engines`,
   STAGING_LOGGED_IN: `${CACHE_PREFIX}${CACHE_VERSION}staging_logged_in`,
   SEARCH_HISTORY: `${CACHE_PREFIX}${CACHE_VERSION}search_history`,
 } as const;
@@ -38,26 +38,6 @@ export const cache = {
     }
   },
 
-  // search history uses it's own timestamp
-  get_search_history: (key: string): string | null => {
-    try {
-      const item = localStorage.getItem(key);
-      return item; // Return the raw string value directly
-    } catch (error) {
-      console.error("Cache read failed:", error);
-      return null;
-    }
-  },
-
-  // search history is stored unqiuely
-  set_search_history: (key: string, data: string): void => {
-    try {
-      localStorage.setItem(key, data);
-    } catch (error) {
-      console.error("Cache write failed:", error);
-    }
-  },
-
   clear: (key: string): void => {
     try {
       localStorage.removeItem(key);
@@ -0,0 +1,12 @@
+-- Link health tracking for dead-link detection
+-- One row per link, overwritten on every check
+
+CREATE TABLE IF NOT EXISTS link_health (
+    link_id TEXT PRIMARY KEY REFERENCES links(id) ON DELETE CASCADE,
+    status_code INTEGER,
+    final_url TEXT,
+    error TEXT,
+    checked_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_link_health_checked_at ON link_health(checked_at);
@@ -0,0 +1,11 @@
+-- Content-addressed favicon store
+-- Links reference icons by URL (/api/icons/<sha256>) instead of inlining data: URIs.
+-- Existing data: URIs are moved here on startup by Database::migrate_inline_icons,
+-- since SQLite can't decode base64 or hash in plain SQL.
+
+CREATE TABLE IF NOT EXISTS icons (
+    hash TEXT PRIMARY KEY,
+    content_type TEXT NOT NULL,
+    data BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
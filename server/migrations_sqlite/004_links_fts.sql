@@ -0,0 +1,32 @@
+-- Full-text search over links
+-- The FTS table keeps its own copy of the text keyed by link_id, rather than
+-- using external content on links.rowid, since links has a TEXT primary key
+-- and its implicit rowids aren't guaranteed to survive a VACUUM.
+
+CREATE VIRTUAL TABLE IF NOT EXISTS links_fts USING fts5(
+    link_id UNINDEXED,
+    title,
+    url,
+    description,
+    column_type,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER IF NOT EXISTS links_fts_insert AFTER INSERT ON links BEGIN
+    INSERT INTO links_fts (link_id, title, url, description, column_type)
+    VALUES (new.id, new.title, new.url, COALESCE(new.description, ''), new.column_type);
+END;
+
+CREATE TRIGGER IF NOT EXISTS links_fts_delete AFTER DELETE ON links BEGIN
+    DELETE FROM links_fts WHERE link_id = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS links_fts_update AFTER UPDATE OF id, title, url, description, column_type ON links BEGIN
+    DELETE FROM links_fts WHERE link_id = old.id;
+    INSERT INTO links_fts (link_id, title, url, description, column_type)
+    VALUES (new.id, new.title, new.url, COALESCE(new.description, ''), new.column_type);
+END;
+
+-- Index the links that already exist
+INSERT INTO links_fts (link_id, title, url, description, column_type)
+SELECT id, title, url, COALESCE(description, ''), column_type FROM links;
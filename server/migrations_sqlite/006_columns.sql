@@ -0,0 +1,44 @@
+-- Columns become real rows, so they can be empty, renamed and reordered
+-- without touching every link. links.column_type stays as the column name
+-- for older clients and is kept in sync by the server.
+
+CREATE TABLE IF NOT EXISTS columns (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    color TEXT,
+    collapsed INTEGER NOT NULL DEFAULT 0,
+    owner_id TEXT NOT NULL,
+    owner_type TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (owner_id, owner_type, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_columns_owner ON columns(owner_id, owner_type);
+
+ALTER TABLE links ADD COLUMN column_id TEXT REFERENCES columns(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_links_column_id ON links(column_id);
+
+-- One column per distinct column_type, ordered the way the client used to
+-- derive them: by the first link added to each
+INSERT INTO columns (id, name, position, owner_id, owner_type, created_at)
+SELECT
+    lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)),2) || '-' || substr('89ab',abs(random()) % 4 + 1, 1) || substr(hex(randomblob(2)),2) || '-' || hex(randomblob(6))),
+    column_type,
+    ROW_NUMBER() OVER (PARTITION BY owner_id, owner_type ORDER BY first_link) - 1,
+    owner_id,
+    owner_type,
+    datetime('now')
+FROM (
+    SELECT owner_id, owner_type, column_type, MIN(rowid) AS first_link
+    FROM links
+    GROUP BY owner_id, owner_type, column_type
+);
+
+UPDATE links SET column_id = (
+    SELECT c.id FROM columns c
+    WHERE c.owner_id = links.owner_id
+    AND c.owner_type = links.owner_type
+    AND c.name = links.column_type
+);
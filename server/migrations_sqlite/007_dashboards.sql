@@ -0,0 +1,37 @@
+-- Users can keep several dashboards, each with its own columns (and through
+-- them, links) plus settings that override the user's own.
+-- A user's columns are owned by a dashboard (owner_type 'dashboard') rather
+-- than the user, so column names only have to be unique per dashboard.
+-- Links stay owned by the user.
+
+CREATE TABLE IF NOT EXISTS dashboards (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    settings_overrides TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (user_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_dashboards_user_id ON dashboards(user_id);
+
+ALTER TABLE users ADD COLUMN active_dashboard_id TEXT REFERENCES dashboards(id) ON DELETE SET NULL;
+
+-- Every existing user starts with the dashboard they already had
+INSERT INTO dashboards (id, user_id, name, created_at)
+SELECT
+    lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)),2) || '-' || substr('89ab',abs(random()) % 4 + 1, 1) || substr(hex(randomblob(2)),2) || '-' || hex(randomblob(6))),
+    id,
+    'Home',
+    datetime('now')
+FROM users;
+
+UPDATE users SET active_dashboard_id = (
+    SELECT d.id FROM dashboards d WHERE d.user_id = users.id
+);
+
+UPDATE columns SET
+    owner_type = 'dashboard',
+    owner_id = (SELECT d.id FROM dashboards d WHERE d.user_id = columns.owner_id)
+WHERE owner_type = 'user'
+AND EXISTS (SELECT 1 FROM dashboards d WHERE d.user_id = columns.owner_id);
@@ -0,0 +1,16 @@
+-- Pending invitations to teams and organizations. Only a hash of the
+-- token from the invite link is stored.
+CREATE TABLE IF NOT EXISTS invitations (
+    id TEXT PRIMARY KEY,
+    email TEXT NOT NULL COLLATE NOCASE,
+    entity_id TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    role TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    invited_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (entity_id, entity_type, email)
+);
+
+CREATE INDEX IF NOT EXISTS idx_invitations_entity ON invitations(entity_id, entity_type);
@@ -0,0 +1,18 @@
+-- Email verification and password reset. Accounts that exist before this
+-- migration count as verified, so turning verification on later doesn't
+-- lock anyone out.
+ALTER TABLE users ADD COLUMN email_verified_at TEXT;
+UPDATE users SET email_verified_at = created_at;
+
+-- Single-use tokens sent by email. Only a hash is stored and a token is
+-- deleted when it's redeemed.
+CREATE TABLE IF NOT EXISTS email_tokens (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_tokens_user ON email_tokens(user_id, purpose);
@@ -0,0 +1,25 @@
+-- Sign-in sessions. Access tokens are short-lived JWTs, the session lives on
+-- through a refresh token that's replaced every time it's used. Only hashes
+-- of refresh tokens are stored.
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    refresh_token_hash TEXT NOT NULL UNIQUE,
+    -- the token this one replaced, seeing it again means it was stolen
+    previous_refresh_hash TEXT,
+    user_agent TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    last_used_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    revoked_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_previous_refresh ON sessions(previous_refresh_hash);
+
+-- Access token ids (jti) and session ids that must no longer be accepted.
+-- Rows only matter until every access token they cover has expired.
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    id TEXT PRIMARY KEY,
+    expires_at INTEGER NOT NULL
+);
@@ -0,0 +1,13 @@
+-- Audit trail of password logins, also what brute-force backoff and lockout
+-- are worked out from. Emails are stored lowercased and may not belong to
+-- any account.
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id TEXT PRIMARY KEY,
+    email TEXT NOT NULL,
+    ip TEXT,
+    outcome TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_attempts_email ON login_attempts(email, created_at);
+CREATE INDEX IF NOT EXISTS idx_login_attempts_ip ON login_attempts(ip, created_at);
@@ -0,0 +1,2 @@
+-- Feedback is limited by the rate limit layer now
+DROP TABLE IF EXISTS feedback_timestamps;
@@ -0,0 +1,12 @@
+-- Searches made through Omega Tab, one row per distinct query and user.
+-- Queries are matched on normalized_query (lowercased, spaces collapsed).
+CREATE TABLE IF NOT EXISTS search_history (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    query TEXT NOT NULL,
+    normalized_query TEXT NOT NULL,
+    searches INTEGER NOT NULL DEFAULT 1,
+    last_searched_at TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE(user_id, normalized_query)
+);
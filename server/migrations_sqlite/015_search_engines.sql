@@ -0,0 +1,70 @@
+-- Built-in bangs, `!keyword terms` searches `url` with %s replaced by the terms.
+-- Keywords are stored lowercase without the "!".
+CREATE TABLE IF NOT EXISTS bangs (
+    keyword TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    url TEXT NOT NULL,
+    icon TEXT
+);
+
+INSERT OR IGNORE INTO bangs (keyword, name, url, icon) VALUES
+    ('g', 'Google', 'https://www.google.com/search?q=%s', 'icons/google.svg'),
+    ('b', 'Bing', 'https://www.bing.com/search?q=%s', 'icons/bing.svg'),
+    ('p', 'Perplexity', 'https://www.perplexity.ai/search?q=%s', 'icons/perplexity.svg'),
+    ('brave', 'Brave', 'https://search.brave.com/search?q=%s', 'icons/brave.svg'),
+    ('ddg', 'DuckDuckGo', 'https://duckduckgo.com/?q=%s', NULL),
+    ('gi', 'Google Images', 'https://www.google.com/search?tbm=isch&q=%s', 'icons/google.svg'),
+    ('maps', 'Google Maps', 'https://www.google.com/maps/search/%s', NULL),
+    ('w', 'Wikipedia', 'https://en.wikipedia.org/wiki/Special:Search?search=%s', NULL),
+    ('gh', 'GitHub', 'https://github.com/search?q=%s', NULL),
+    ('so', 'Stack Overflow', 'https://stackoverflow.com/search?q=%s', NULL),
+    ('mdn', 'MDN Web Docs', 'https://developer.mozilla.org/en-US/search?q=%s', NULL),
+    ('rs', 'Docs.rs', 'https://docs.rs/releases/search?query=%s', NULL),
+    ('crates', 'crates.io', 'https://crates.io/search?q=%s', NULL),
+    ('npm', 'npm', 'https://www.npmjs.com/search?q=%s', NULL),
+    ('yt', 'YouTube', 'https://www.youtube.com/results?search_query=%s', NULL),
+    ('r', 'Reddit', 'https://www.reddit.com/search/?q=%s', NULL),
+    ('hn', 'Hacker News', 'https://hn.algolia.com/?q=%s', NULL),
+    ('a', 'Amazon', 'https://www.amazon.com/s?k=%s', NULL),
+    ('wa', 'Wolfram Alpha', 'https://www.wolframalpha.com/input?i=%s', NULL);
+
+-- Each user's search engines. A keyword makes the engine a bang of its own,
+-- taking precedence over the built-in one. At most one default per user.
+CREATE TABLE IF NOT EXISTS search_engines (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    url TEXT NOT NULL,
+    keyword TEXT,
+    icon TEXT,
+    is_default INTEGER NOT NULL DEFAULT 0,
+    order_index INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    UNIQUE(user_id, keyword)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_search_engines_default
+    ON search_engines(user_id) WHERE is_default = 1;
+
+-- Everyone starts with the engines the search bar used to hard-code,
+-- Google as the default
+CREATE TRIGGER IF NOT EXISTS search_engines_defaults AFTER INSERT ON users BEGIN
+    INSERT INTO search_engines (id, user_id, name, url, keyword, icon, is_default, order_index, created_at)
+    SELECT
+        lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)),2) || '-' || substr('89ab',abs(random()) % 4 + 1, 1) || substr(hex(randomblob(2)),2) || '-' || hex(randomblob(6))),
+        new.id, name, url, keyword, icon,
+        keyword = 'g',
+        CASE keyword WHEN 'g' THEN 0 WHEN 'b' THEN 1 WHEN 'p' THEN 2 ELSE 3 END,
+        strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')
+    FROM bangs WHERE keyword IN ('g', 'b', 'p', 'brave');
+END;
+
+INSERT INTO search_engines (id, user_id, name, url, keyword, icon, is_default, order_index, created_at)
+SELECT
+    lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)),2) || '-' || substr('89ab',abs(random()) % 4 + 1, 1) || substr(hex(randomblob(2)),2) || '-' || hex(randomblob(6))),
+    users.id, bangs.name, bangs.url, bangs.keyword, bangs.icon,
+    bangs.keyword = 'g',
+    CASE bangs.keyword WHEN 'g' THEN 0 WHEN 'b' THEN 1 WHEN 'p' THEN 2 ELSE 3 END,
+    strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')
+FROM users CROSS JOIN bangs
+WHERE bangs.keyword IN ('g', 'b', 'p', 'brave');
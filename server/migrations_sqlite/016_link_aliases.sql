@@ -0,0 +1,7 @@
+-- Go-link aliases, /go/<alias> redirects to the link. An alias is unique
+-- per owner, the same one can point somewhere else for a team or an
+-- organization and the user's own wins.
+ALTER TABLE links ADD COLUMN alias TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_links_alias
+    ON links(owner_type, owner_id, alias) WHERE alias IS NOT NULL;
@@ -0,0 +1,49 @@
+-- The search_engine setting, a URL template with %s for the query, is
+-- replaced by search_engines. It becomes the user's default engine, added
+-- when none of their engines has that URL. Blobs that aren't JSON are left
+-- alone, CASE keeps json_extract from ever seeing them.
+CREATE TEMP VIEW legacy_search_engines AS
+SELECT user_id, url, substr(url, instr(url, '://') + 3) AS rest
+FROM (
+    SELECT user_id,
+        CASE WHEN json_valid(settings_blob)
+            THEN json_extract(settings_blob, '$.search_engine') END AS url
+    FROM user_settings
+)
+WHERE url LIKE 'http://_%' OR url LIKE 'https://_%';
+
+INSERT INTO search_engines (id, user_id, name, url, keyword, icon, is_default, order_index, created_at)
+SELECT
+    lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)),2) || '-' || substr('89ab',abs(random()) % 4 + 1, 1) || substr(hex(randomblob(2)),2) || '-' || hex(randomblob(6))),
+    user_id,
+    -- Named after the host, www. dropped
+    replace(
+        CASE WHEN instr(rest, '/') > 0 THEN substr(rest, 1, instr(rest, '/') - 1) ELSE rest END,
+        'www.', ''
+    ),
+    url, NULL, NULL, 0,
+    (SELECT COALESCE(MAX(order_index) + 1, 0) FROM search_engines e WHERE e.user_id = legacy.user_id),
+    strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')
+FROM legacy_search_engines legacy
+WHERE NOT EXISTS (
+    SELECT 1 FROM search_engines e WHERE e.user_id = legacy.user_id AND e.url = legacy.url
+);
+
+UPDATE search_engines SET is_default = 0
+WHERE is_default = 1 AND user_id IN (SELECT user_id FROM legacy_search_engines);
+
+UPDATE search_engines SET is_default = 1
+WHERE id IN (
+    SELECT (
+        SELECT e.id FROM search_engines e
+        WHERE e.user_id = legacy.user_id AND e.url = legacy.url
+        ORDER BY e.order_index LIMIT 1
+    )
+    FROM legacy_search_engines legacy
+);
+
+DROP VIEW legacy_search_engines;
+
+UPDATE user_settings SET settings_blob = json_remove(settings_blob, '$.search_engine')
+WHERE CASE WHEN json_valid(settings_blob)
+    THEN json_type(settings_blob, '$.search_engine') IS NOT NULL ELSE 0 END;
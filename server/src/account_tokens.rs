@@ -0,0 +1,146 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::invitations::{app_url, escape_html};
+
+/// What an emailed token lets its holder do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Purpose {
+    PasswordReset,
+    VerifyEmail,
+}
+
+impl Purpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Purpose::PasswordReset => "password_reset",
+            Purpose::VerifyEmail => "verify_email",
+        }
+    }
+
+    /// Reset links can take over an account, so they're short-lived
+    pub fn ttl(&self) -> Duration {
+        match self {
+            Purpose::PasswordReset => Duration::hours(1),
+            Purpose::VerifyEmail => Duration::days(2),
+        }
+    }
+
+    pub fn expires_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + self.ttl()
+    }
+
+    /// The page the link in the email opens, the client picks the token up
+    /// from the query
+    pub fn url(&self, token: &str) -> String {
+        let param = match self {
+            Purpose::PasswordReset => "reset",
+            Purpose::VerifyEmail => "verify",
+        };
+        format!("{}/?{}={}", app_url(), param, token)
+    }
+
+    pub fn email_subject(&self) -> &'static str {
+        match self {
+            Purpose::PasswordReset => "Reset your Omega Tab password",
+            Purpose::VerifyEmail => "Confirm your Omega Tab email address",
+        }
+    }
+
+    pub fn email_body(&self, url: &str) -> String {
+        let (intro, action) = match self {
+            Purpose::PasswordReset => (
+                "Someone asked to reset the password of your Omega Tab account. \
+                 If that wasn't you, ignore this email.",
+                "Choose a new password",
+            ),
+            Purpose::VerifyEmail => (
+                "Thanks for signing up for Omega Tab.",
+                "Confirm your email address",
+            ),
+        };
+        let ttl = self.ttl();
+        let expires_in = if ttl.num_hours() == 1 {
+            "1 hour".to_string()
+        } else if ttl.num_hours() < 24 {
+            format!("{} hours", ttl.num_hours())
+        } else {
+            format!("{} days", ttl.num_days())
+        };
+        format!(
+            "<p>{}</p><p><a href=\"{}\">{}</a></p><p>The link expires in {}.</p>",
+            intro,
+            escape_html(url),
+            action,
+            expires_in
+        )
+    }
+}
+
+/// How strictly email addresses are verified, from EMAIL_VERIFICATION
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    /// No verification emails, the default
+    Off,
+    /// New accounts get a verification email but can sign in without it
+    Optional,
+    /// Unverified accounts can't sign in with their password
+    Required,
+}
+
+impl Verification {
+    pub fn parse(value: Option<&str>) -> Verification {
+        match value.map(|value| value.trim().to_lowercase()).as_deref() {
+            Some("optional") => Verification::Optional,
+            Some("required") => Verification::Required,
+            Some("off") | Some("") | None => Verification::Off,
+            Some(other) => {
+                tracing::warn!(
+                    "Unknown EMAIL_VERIFICATION {:?}, verification is off",
+                    other
+                );
+                Verification::Off
+            }
+        }
+    }
+
+    pub fn from_env() -> Verification {
+        Verification::parse(std::env::var("EMAIL_VERIFICATION").ok().as_deref())
+    }
+
+    pub fn sends_email(&self) -> bool {
+        *self != Verification::Off
+    }
+
+    pub fn is_required(&self) -> bool {
+        *self == Verification::Required
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_tokens() {
+        let now = Utc::now();
+        assert_eq!(
+            Purpose::PasswordReset.expires_at(now),
+            now + Duration::hours(1)
+        );
+        assert!(Purpose::VerifyEmail.url("abc").ends_with("/?verify=abc"));
+        assert!(
+            Purpose::PasswordReset
+                .email_body("http://x/?reset=t")
+                .contains("expires in 1 hour.")
+        );
+
+        assert_eq!(Verification::parse(None), Verification::Off);
+        assert_eq!(
+            Verification::parse(Some(" Required ")),
+            Verification::Required
+        );
+        assert_eq!(Verification::parse(Some("maybe")), Verification::Off);
+        assert!(Verification::Optional.sends_email());
+        assert!(!Verification::Optional.is_required());
+    }
+}
@@ -0,0 +1,143 @@
+use axum::http::Method;
+
+/// Personal access tokens start with this so the auth middleware can tell
+/// them from JWTs, and so they're easy to spot when leaked
+pub const TOKEN_PREFIX: &str = "omt_";
+
+/// Longest token name we accept, in characters
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// Longest lifetime a token can be created with
+pub const MAX_EXPIRES_IN_DAYS: i64 = 365;
+
+/// Tokens per user, they're meant for a handful of scripts and extensions
+pub const MAX_TOKENS_PER_USER: usize = 50;
+
+/// What a personal access token may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scope {
+    LinksRead,
+    LinksWrite,
+    SettingsRead,
+    /// Searching from the browser's address bar
+    Search,
+}
+
+impl Scope {
+    pub fn parse(scope: &str) -> Option<Scope> {
+        match scope.trim() {
+            "links:read" => Some(Scope::LinksRead),
+            "links:write" => Some(Scope::LinksWrite),
+            "settings:read" => Some(Scope::SettingsRead),
+            "search" => Some(Scope::Search),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::LinksRead => "links:read",
+            Scope::LinksWrite => "links:write",
+            Scope::SettingsRead => "settings:read",
+            Scope::Search => "search",
+        }
+    }
+}
+
+/// Parse requested scopes, sorted and without duplicates. None if any is
+/// unknown or there are none at all.
+pub fn parse_scopes(scopes: &[String]) -> Option<Vec<Scope>> {
+    let mut parsed = scopes
+        .iter()
+        .map(|scope| Scope::parse(scope))
+        .collect::<Option<Vec<_>>>()?;
+    parsed.sort();
+    parsed.dedup();
+    (!parsed.is_empty()).then_some(parsed)
+}
+
+/// Scopes as stored in the database
+pub fn join_scopes(scopes: &[Scope]) -> String {
+    scopes
+        .iter()
+        .map(Scope::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Scopes read back from the database, skipping anything unknown
+pub fn split_scopes(scopes: &str) -> Vec<Scope> {
+    scopes.split_whitespace().filter_map(Scope::parse).collect()
+}
+
+/// The scope a request needs when it's made with a personal access token.
+/// Paths are relative to /api. Anything not listed here, like account,
+/// session and token management, needs a signed-in session.
+pub fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let scope = match (method.as_str(), segments.as_slice()) {
+        ("GET", ["user", "links"]) | ("GET", ["links", "search"]) => Scope::LinksRead,
+        ("GET", ["columns"]) | ("GET", ["tags"]) => Scope::LinksRead,
+        ("GET", ["go", ..]) | ("GET", ["aliases", ..]) => Scope::LinksRead,
+        ("PUT" | "DELETE", ["link", _, "alias"]) => Scope::LinksWrite,
+        ("POST" | "PUT", ["link"]) | ("DELETE", ["link", _]) => Scope::LinksWrite,
+        ("PATCH", ["links", "order"]) => Scope::LinksWrite,
+        ("GET", ["settings"]) => Scope::SettingsRead,
+        ("GET", ["search"]) | ("GET", ["search", "suggest"]) => Scope::Search,
+        ("GET", ["search_engines", "resolve"]) => Scope::Search,
+        _ => return None,
+    };
+    Some(scope)
+}
+
+/// Trim a token name, None if it's empty or too long
+pub fn normalize_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes() {
+        let requested = ["settings:read", "links:read", "links:read"].map(String::from);
+        let scopes = parse_scopes(&requested).unwrap();
+        assert_eq!(scopes, vec![Scope::LinksRead, Scope::SettingsRead]);
+        assert_eq!(split_scopes(&join_scopes(&scopes)), scopes);
+        assert_eq!(parse_scopes(&["links:admin".to_string()]), None);
+        assert_eq!(parse_scopes(&[]), None);
+    }
+
+    #[test]
+    fn test_required_scope() {
+        assert_eq!(
+            required_scope(&Method::GET, "/user/links"),
+            Some(Scope::LinksRead)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/link"),
+            Some(Scope::LinksWrite)
+        );
+        assert_eq!(
+            required_scope(&Method::DELETE, "/link/abc"),
+            Some(Scope::LinksWrite)
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/settings"),
+            Some(Scope::SettingsRead)
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/go/docs/a/b"),
+            Some(Scope::LinksRead)
+        );
+        // tokens can't change settings or manage themselves
+        assert_eq!(required_scope(&Method::PUT, "/settings"), None);
+        assert_eq!(required_scope(&Method::POST, "/api_tokens"), None);
+        assert_eq!(required_scope(&Method::DELETE, "/link/abc/extra"), None);
+    }
+}
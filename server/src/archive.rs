@@ -0,0 +1,136 @@
+use crate::database::{Column, Dashboard, Link, Subscription, UserData};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Bump whenever the archive layout changes in a way older readers can't handle
+/// v2: stored icons are bundled since links only reference them by URL
+/// v3: columns are exported with their order, color and collapsed state
+/// v4: dashboards are exported, columns belong to a dashboard
+pub const ARCHIVE_VERSION: u32 = 4;
+
+/// Portable snapshot of everything a user owns
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Archive {
+    pub version: u32,
+    pub exported_at: String,
+    pub user: ArchiveUser,
+    #[serde(default)]
+    pub subscription: Option<Subscription>,
+    /// The parsed settings blob, kept as JSON so the archive stays readable
+    #[serde(default)]
+    pub settings: Option<Value>,
+    #[serde(default)]
+    pub dashboards: Vec<Dashboard>,
+    #[serde(default)]
+    pub columns: Vec<Column>,
+    #[serde(default)]
+    pub links: Vec<Link>,
+    #[serde(default)]
+    pub icons: Vec<ArchiveIcon>,
+}
+
+/// An icon from the icon store, base64 encoded
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveIcon {
+    pub hash: String,
+    pub content_type: String,
+    pub data: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveUser {
+    pub id: String,
+    pub email: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreMode {
+    /// Keep what's already there, add links and settings that are missing
+    #[default]
+    Merge,
+    /// Drop the user's links and settings and take the archive as-is
+    Replace,
+}
+
+impl Archive {
+    pub fn from_user_data(data: UserData) -> Self {
+        Self {
+            version: ARCHIVE_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            user: ArchiveUser {
+                id: data.user.id,
+                email: data.user.email,
+                created_at: data.user.created_at,
+            },
+            subscription: data.subscription,
+            settings: data
+                .settings
+                .and_then(|s| serde_json::from_str(&s.settings_blob).ok()),
+            dashboards: data.dashboards,
+            columns: data.columns,
+            links: data.links,
+            icons: Vec::new(),
+        }
+    }
+}
+
+/// Where each column sits, as (dashboard name, column name) keyed by column id.
+/// Columns from archives before dashboards have no dashboard name.
+pub fn column_locations(
+    dashboards: &[Dashboard],
+    columns: &[Column],
+) -> HashMap<String, (String, String)> {
+    columns
+        .iter()
+        .map(|column| {
+            let dashboard = dashboards
+                .iter()
+                .find(|d| column.owner_type == "dashboard" && d.id == column.owner_id)
+                .map(|d| d.name.clone())
+                .unwrap_or_default();
+            (column.id.clone(), (dashboard, column.name.clone()))
+        })
+        .collect()
+}
+
+/// Merge archived settings into the current ones, current values win
+pub fn merge_settings(current: Option<Value>, archived: Option<Value>) -> Option<Value> {
+    match (current, archived) {
+        (Some(Value::Object(mut current)), Some(Value::Object(archived))) => {
+            for (key, value) in archived {
+                current.entry(key).or_insert(value);
+            }
+            Some(Value::Object(current))
+        }
+        (Some(current), _) => Some(current),
+        (None, archived) => archived,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    // merging keeps local values and only fills in what's missing
+    fn test_merge_settings() {
+        let merged = merge_settings(
+            Some(json!({"autosuggest": true, "metadata": false})),
+            Some(json!({"autosuggest": false, "new_tabs": true})),
+        );
+
+        assert_eq!(
+            merged,
+            Some(json!({"autosuggest": true, "metadata": false, "new_tabs": true}))
+        );
+        assert_eq!(
+            merge_settings(None, Some(json!({"a": 1}))),
+            Some(json!({"a": 1}))
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use axum::http::StatusCode;
+
+use crate::database::Database;
+use crate::middleware::UserContext;
+use crate::teams::{self, Role};
+
+/// What a request wants to do with a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// Manage members, rename or delete
+    Admin,
+}
+
+impl Access {
+    /// Role a team or organization member needs for this access
+    pub fn required_role(&self) -> Role {
+        match self {
+            Access::Read => Role::Viewer,
+            Access::Write => Role::Editor,
+            Access::Admin => Role::Admin,
+        }
+    }
+}
+
+/// Something access is checked against
+#[derive(Debug, Clone, Copy)]
+pub enum Resource<'a> {
+    Link(&'a str),
+    Column(&'a str),
+    /// A user's settings, by user id
+    Settings(&'a str),
+    /// Everything an owner has, e.g. to add a link to a team
+    Owner {
+        owner_type: &'a str,
+        owner_id: &'a str,
+    },
+}
+
+/// Who a resource ultimately belongs to. Dashboards are resolved to their
+/// user, so this is always a user, team or organization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Owner {
+    pub owner_type: String,
+    pub owner_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denied {
+    /// No relationship to the resource at all. Reported as 404 so ids of
+    /// other people's things can't be probed.
+    NotFound,
+    /// A member whose role isn't strong enough
+    Forbidden,
+}
+
+impl From<Denied> for StatusCode {
+    fn from(denied: Denied) -> Self {
+        match denied {
+            Denied::NotFound => StatusCode::NOT_FOUND,
+            Denied::Forbidden => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+/// Decide whether `user_id` gets `access` to something `owner` owns. Users
+/// can do anything with their own things and nothing with anyone else's.
+/// Team and organization things depend on the user's role, if any.
+pub fn decide(
+    user_id: &str,
+    owner: &Owner,
+    role: Option<Role>,
+    access: Access,
+) -> Result<(), Denied> {
+    if owner.owner_type == "user" {
+        return if owner.owner_id == user_id {
+            Ok(())
+        } else {
+            Err(Denied::NotFound)
+        };
+    }
+
+    if !teams::is_shared_owner_type(&owner.owner_type) {
+        return Err(Denied::NotFound);
+    }
+
+    match role {
+        None => Err(Denied::NotFound),
+        Some(role) if role < access.required_role() => Err(Denied::Forbidden),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Check that the user can access a resource, returning its owner. Every
+/// handler that touches something by id goes through here.
+pub async fn authorize(
+    database: &Database,
+    user: &UserContext,
+    resource: Resource<'_>,
+    access: Access,
+) -> Result<Owner, StatusCode> {
+    let owner = resolve_owner(database, resource).await?;
+
+    let role = if teams::is_shared_owner_type(&owner.owner_type) {
+        database
+            .get_member_role(&user.user_id, &owner.owner_type, &owner.owner_id)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to fetch role of user {} in {} {}: {:?}",
+                    user.user_id,
+                    owner.owner_type,
+                    owner.owner_id,
+                    e
+                );
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    } else {
+        None
+    };
+
+    decide(&user.user_id, &owner, role, access).map_err(|denied| {
+        tracing::warn!(
+            "User {} denied {:?} access to {:?}: {:?}",
+            user.user_id,
+            access,
+            resource,
+            denied
+        );
+        StatusCode::from(denied)
+    })?;
+
+    Ok(owner)
+}
+
+async fn resolve_owner(database: &Database, resource: Resource<'_>) -> Result<Owner, StatusCode> {
+    let not_found = |e: anyhow::Error| match e.to_string().as_str() {
+        "404" => StatusCode::NOT_FOUND,
+        _ => {
+            tracing::error!("Failed to resolve owner: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+
+    let (owner_type, owner_id) = match resource {
+        Resource::Link(id) => {
+            let link = database.get_link_by_id(id).await.map_err(not_found)?;
+            (link.owner_type, link.owner_id)
+        }
+        Resource::Column(id) => {
+            let column = database.get_column_by_id(id).await.map_err(not_found)?;
+            (column.owner_type, column.owner_id)
+        }
+        Resource::Settings(user_id) => ("user".to_string(), user_id.to_string()),
+        Resource::Owner {
+            owner_type,
+            owner_id,
+        } => (owner_type.to_string(), owner_id.to_string()),
+    };
+
+    if owner_type == "dashboard" {
+        let user_id = database
+            .get_dashboard_owner(&owner_id)
+            .await
+            .map_err(not_found)?;
+        return Ok(Owner {
+            owner_type: "user".to_string(),
+            owner_id: user_id,
+        });
+    }
+
+    Ok(Owner {
+        owner_type,
+        owner_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner(owner_type: &str, owner_id: &str) -> Owner {
+        Owner {
+            owner_type: owner_type.to_string(),
+            owner_id: owner_id.to_string(),
+        }
+    }
+
+    #[test]
+    // users own their things outright, and nobody else sees them
+    fn test_user_owned() {
+        let mine = owner("user", "alice");
+        for access in [Access::Read, Access::Write, Access::Admin] {
+            assert_eq!(decide("alice", &mine, None, access), Ok(()));
+            assert_eq!(decide("bob", &mine, None, access), Err(Denied::NotFound));
+        }
+        // a role somewhere else doesn't open up someone's own links
+        assert_eq!(
+            decide("bob", &mine, Some(Role::Admin), Access::Read),
+            Err(Denied::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_shared_roles() {
+        let team = owner("team", "sre");
+        assert_eq!(
+            decide("bob", &team, None, Access::Read),
+            Err(Denied::NotFound)
+        );
+
+        assert_eq!(
+            decide("bob", &team, Some(Role::Viewer), Access::Read),
+            Ok(())
+        );
+        assert_eq!(
+            decide("bob", &team, Some(Role::Viewer), Access::Write),
+            Err(Denied::Forbidden)
+        );
+
+        assert_eq!(
+            decide("bob", &team, Some(Role::Editor), Access::Write),
+            Ok(())
+        );
+        assert_eq!(
+            decide("bob", &team, Some(Role::Editor), Access::Admin),
+            Err(Denied::Forbidden)
+        );
+
+        let organization = owner("organization", "acme");
+        for access in [Access::Read, Access::Write, Access::Admin] {
+            assert_eq!(
+                decide("bob", &organization, Some(Role::Admin), access),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_owner_type() {
+        assert_eq!(
+            decide("alice", &owner("dashboard", "alice"), None, Access::Read),
+            Err(Denied::NotFound)
+        );
+        assert_eq!(
+            decide("alice", &owner("", ""), Some(Role::Admin), Access::Read),
+            Err(Denied::NotFound)
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::Result;
+use scraper::{ElementRef, Html, Selector};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Supported bookmark export formats
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BookmarkFormat {
+    /// Sniff the format from the content
+    Auto,
+    /// Netscape bookmark HTML, used by every browser's "export bookmarks"
+    Netscape,
+    /// Firefox's JSON backup (bookmarks-YYYY-MM-DD.json)
+    Firefox,
+    /// Chrome's on-disk `Bookmarks` JSON file
+    Chrome,
+}
+
+/// A single bookmark pulled out of an export, in document order
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedBookmark {
+    pub title: String,
+    pub url: String,
+    pub icon: Option<String>,
+    pub description: Option<String>,
+    /// Name of the closest enclosing folder, if any
+    pub folder: Option<String>,
+}
+
+/// What two URLs are compared by when looking for duplicates: the parsed
+/// form (so host casing and a bare origin's slash don't matter) without a
+/// trailing slash on the path
+pub fn dedupe_key(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url) else {
+        return url.to_string();
+    };
+    let path = parsed.path().trim_end_matches('/').to_string();
+    if !path.is_empty() {
+        parsed.set_path(&path);
+    }
+    parsed.to_string()
+}
+
+/// Parse a bookmark export into a flat list of bookmarks
+pub fn parse(content: &str, format: BookmarkFormat) -> Result<Vec<ImportedBookmark>> {
+    let format = match format {
+        BookmarkFormat::Auto => detect_format(content),
+        format => format,
+    };
+
+    tracing::info!("Parsing bookmark export as {:?}", format);
+
+    match format {
+        BookmarkFormat::Netscape => Ok(parse_netscape(content)),
+        BookmarkFormat::Firefox | BookmarkFormat::Chrome => {
+            let root: Value = serde_json::from_str(content)?;
+            let mut bookmarks = Vec::new();
+            if format == BookmarkFormat::Firefox {
+                walk_firefox(&root, None, &mut bookmarks);
+            } else {
+                let roots = root
+                    .get("roots")
+                    .and_then(|r| r.as_object())
+                    .ok_or_else(|| anyhow::anyhow!("Chrome bookmarks file has no roots"))?;
+                for node in roots.values() {
+                    walk_chrome(node, None, &mut bookmarks);
+                }
+            }
+            Ok(bookmarks)
+        }
+        BookmarkFormat::Auto => unreachable!("format is resolved above"),
+    }
+}
+
+fn detect_format(content: &str) -> BookmarkFormat {
+    let trimmed = content.trim_start();
+    if !trimmed.starts_with('{') {
+        return BookmarkFormat::Netscape;
+    }
+
+    match serde_json::from_str::<Value>(trimmed) {
+        Ok(value) if value.get("roots").is_some() => BookmarkFormat::Chrome,
+        _ => BookmarkFormat::Firefox,
+    }
+}
+
+fn parse_netscape(content: &str) -> Vec<ImportedBookmark> {
+    let document = Html::parse_document(content);
+    let link_selector = Selector::parse("a[href]").unwrap();
+
+    document
+        .select(&link_selector)
+        .map(|anchor| {
+            let url = anchor.value().attr("href").unwrap_or_default().trim();
+            let title = anchor.text().collect::<String>().trim().to_string();
+
+            ImportedBookmark {
+                title,
+                url: url.to_string(),
+                icon: anchor
+                    .value()
+                    .attr("icon")
+                    .filter(|i| i.starts_with("data:"))
+                    .map(|i| i.to_string()),
+                description: netscape_description(anchor),
+                folder: netscape_folder(anchor),
+            }
+        })
+        .collect()
+}
+
+/// In Netscape exports a folder is a `<DT><H3>name</H3><DL>...</DL>`, so the
+/// folder of a bookmark is the `<H3>` sitting next to its closest `<DL>`
+fn netscape_folder(anchor: ElementRef) -> Option<String> {
+    let list = anchor
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .find(|e| e.value().name() == "dl")?;
+
+    let heading = list
+        .prev_siblings()
+        .filter_map(ElementRef::wrap)
+        .find(|e| e.value().name() == "h3")
+        .or_else(|| {
+            list.parent()
+                .and_then(ElementRef::wrap)
+                .filter(|p| p.value().name() == "dt")?
+                .children()
+                .filter_map(ElementRef::wrap)
+                .find(|e| e.value().name() == "h3")
+        })?;
+
+    let name = heading.text().collect::<String>().trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Descriptions are written as a `<DD>` following the bookmark's `<DT>`
+fn netscape_description(anchor: ElementRef) -> Option<String> {
+    let term = anchor
+        .parent()
+        .and_then(ElementRef::wrap)
+        .filter(|p| p.value().name() == "dt")?;
+
+    let description = term
+        .next_siblings()
+        .filter_map(ElementRef::wrap)
+        .take_while(|e| e.value().name() != "dt")
+        .find(|e| e.value().name() == "dd")?
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_string();
+
+    (!description.is_empty()).then_some(description)
+}
+
+fn walk_firefox(node: &Value, folder: Option<&str>, bookmarks: &mut Vec<ImportedBookmark>) {
+    let title = node.get("title").and_then(|t| t.as_str()).unwrap_or("");
+
+    match node.get("type").and_then(|t| t.as_str()) {
+        Some("text/x-moz-place") => {
+            let Some(uri) = node.get("uri").and_then(|u| u.as_str()) else {
+                return;
+            };
+            let description = node
+                .get("annos")
+                .and_then(|a| a.as_array())
+                .and_then(|annos| {
+                    annos.iter().find(|a| {
+                        a.get("name").and_then(|n| n.as_str())
+                            == Some("bookmarkProperties/description")
+                    })
+                })
+                .and_then(|a| a.get("value"))
+                .and_then(|v| v.as_str())
+                .map(|d| d.to_string());
+
+            bookmarks.push(ImportedBookmark {
+                title: title.to_string(),
+                url: uri.trim().to_string(),
+                icon: None,
+                description,
+                folder: folder.map(|f| f.to_string()),
+            });
+        }
+        Some("text/x-moz-place-container") => {
+            // The places root has an empty title, keep the parent folder in that case
+            let folder = if title.is_empty() {
+                folder
+            } else {
+                Some(title)
+            };
+            if let Some(children) = node.get("children").and_then(|c| c.as_array()) {
+                for child in children {
+                    walk_firefox(child, folder, bookmarks);
+                }
+            }
+        }
+        // Separators and anything unknown carry no bookmark
+        _ => {}
+    }
+}
+
+fn walk_chrome(node: &Value, folder: Option<&str>, bookmarks: &mut Vec<ImportedBookmark>) {
+    let name = node.get("name").and_then(|n| n.as_str()).unwrap_or("");
+
+    match node.get("type").and_then(|t| t.as_str()) {
+        Some("url") => {
+            let Some(url) = node.get("url").and_then(|u| u.as_str()) else {
+                return;
+            };
+            bookmarks.push(ImportedBookmark {
+                title: name.to_string(),
+                url: url.trim().to_string(),
+                icon: None,
+                description: None,
+                folder: folder.map(|f| f.to_string()),
+            });
+        }
+        Some("folder") => {
+            let folder = if name.is_empty() { folder } else { Some(name) };
+            if let Some(children) = node.get("children").and_then(|c| c.as_array()) {
+                for child in children {
+                    walk_chrome(child, folder, bookmarks);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // folders nest and bookmarks should land in their closest folder
+    fn test_parse_netscape() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1">Bookmarks bar</H3>
+    <DL><p>
+        <DT><A HREF="https://github.com/" ADD_DATE="1">GitHub</A>
+        <DD>Where the code lives
+        <DT><H3>Work &amp; Docs</H3>
+        <DL><p>
+            <DT><A HREF="https://docs.rs/">Docs.rs</A>
+        </DL><p>
+        <DT><A HREF="https://example.com/">Example</A>
+    </DL><p>
+    <DT><A HREF="https://top.example/">Top level</A>
+</DL><p>"#;
+
+        let bookmarks = parse(html, BookmarkFormat::Auto).unwrap();
+        let summary: Vec<(&str, Option<&str>)> = bookmarks
+            .iter()
+            .map(|b| (b.title.as_str(), b.folder.as_deref()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("GitHub", Some("Bookmarks bar")),
+                ("Docs.rs", Some("Work & Docs")),
+                ("Example", Some("Bookmarks bar")),
+                ("Top level", None),
+            ]
+        );
+        assert_eq!(
+            bookmarks[0].description.as_deref(),
+            Some("Where the code lives")
+        );
+    }
+
+    #[test]
+    // containers map to folders, separators are skipped
+    fn test_parse_firefox_json() {
+        let json = r#"{"title":"","type":"text/x-moz-place-container","root":"placesRoot","children":[
+            {"title":"toolbar","type":"text/x-moz-place-container","children":[
+                {"title":"Rust","type":"text/x-moz-place","uri":"https://www.rust-lang.org/"},
+                {"type":"text/x-moz-place-separator"}
+            ]}
+        ]}"#;
+
+        let bookmarks = parse(json, BookmarkFormat::Auto).unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].url, "https://www.rust-lang.org/");
+        assert_eq!(bookmarks[0].folder.as_deref(), Some("toolbar"));
+    }
+
+    #[test]
+    fn test_dedupe_key() {
+        assert_eq!(dedupe_key("https://A.com"), dedupe_key("https://a.com/"));
+        assert_eq!(
+            dedupe_key("https://a.com/docs/"),
+            dedupe_key("https://a.com/docs")
+        );
+        assert_eq!(
+            dedupe_key("https://a.com/docs/?page=2"),
+            "https://a.com/docs?page=2"
+        );
+        assert_ne!(
+            dedupe_key("https://a.com/docs"),
+            dedupe_key("https://a.com/doc")
+        );
+    }
+}
@@ -1,65 +1,36 @@
+use crate::cache::TtlCache;
+use crate::suggestions::normalize_query;
 use anyhow::Result;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
 
-// Rate limiter structure to maintain request timestamps
-struct RateLimiter {
-    requests: Vec<Instant>,
-    max_requests: usize,
-    time_window: Duration,
-}
+const DEFAULT_SUGGEST_URL: &str = "https://api.search.brave.com/res/v1/suggest/search";
 
-impl RateLimiter {
-    fn new(max_requests: usize, time_window_secs: u64) -> Self {
-        Self {
-            requests: Vec::with_capacity(max_requests),
-            max_requests,
-            time_window: Duration::from_secs(time_window_secs),
-        }
-    }
+/// Suggestions change slowly and people retype the same prefixes a lot
+const CACHE_CAPACITY: usize = 2000;
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
 
-    fn can_make_request(&mut self) -> bool {
-        let now = Instant::now();
-        // Remove timestamps older than the time window
-        self.requests
-            .retain(|timestamp| now.duration_since(*timestamp) < self.time_window);
-
-        // Check if we've reached the limit
-        if self.requests.len() < self.max_requests {
-            self.requests.push(now);
-            true
-        } else {
-            false
-        }
-    }
+/// Suggestions are only useful while the user is still typing
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
 
-    fn time_until_next_slot(&self) -> Duration {
-        let now = Instant::now();
-        if self.requests.len() < self.max_requests {
-            return Duration::from_secs(0);
-        }
+/// A fetch shared by everyone asking for the same query at the same time.
+/// Errors are kept as strings so every waiter gets a copy.
+type Fetch = Arc<OnceCell<Result<Vec<Suggestion>, String>>>;
 
-        // Get the oldest request and calculate when it will expire
-        if let Some(oldest) = self.requests.first() {
-            let expiry = *oldest + self.time_window;
-            if expiry > now {
-                expiry - now
-            } else {
-                Duration::from_secs(0)
-            }
-        } else {
-            Duration::from_secs(0)
-        }
-    }
+struct Lookups {
+    cache: TtlCache<Vec<Suggestion>>,
+    in_flight: HashMap<String, Fetch>,
 }
 
 pub struct Brave {
     client: Client,
     url: String,
     api_key: String,
-    rate_limiter: Arc<Mutex<RateLimiter>>,
+    lookups: Mutex<Lookups>,
 }
 
 #[allow(dead_code)]
@@ -77,7 +48,7 @@ pub struct Query {
     pub original: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Suggestion {
     pub query: String,
 }
@@ -85,53 +56,106 @@ pub struct Suggestion {
 impl Brave {
     pub fn new(url: String, api_key: String) -> Result<Self> {
         tracing::info!("Initializing Brave API client");
-        let client = Client::new();
-        // Configure rate limiter for 2 requests per second
-        let rate_limiter = Arc::new(Mutex::new(RateLimiter::new(1, 1)));
+        let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
 
         Ok(Self {
             client,
-            url: url.to_string(),
-            api_key: api_key.to_string(),
-            rate_limiter,
+            url,
+            api_key,
+            lookups: Mutex::new(Lookups {
+                cache: TtlCache::new(CACHE_CAPACITY, CACHE_TTL),
+                in_flight: HashMap::new(),
+            }),
         })
     }
 
-    pub async fn get_suggestions(&self, query: &str) -> Result<SuggestResponse> {
-        tracing::info!("Fetching suggestions for query: {}", query);
-
-        // Check rate limiting
-        let can_proceed = {
-            let mut limiter = self.rate_limiter.lock().unwrap();
-            limiter.can_make_request()
+    /// The client configured by BRAVE_API_KEY and BRAVE_SUGGEST_URL, None
+    /// when there's no API key
+    pub fn from_env() -> Option<Self> {
+        let api_key = std::env::var("BRAVE_API_KEY")
+            .ok()
+            .filter(|key| !key.trim().is_empty());
+        let Some(api_key) = api_key else {
+            tracing::info!("BRAVE_API_KEY is not set, search suggestions are disabled");
+            return None;
         };
+        let url = std::env::var("BRAVE_SUGGEST_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_SUGGEST_URL.to_string());
+
+        Brave::new(url, api_key)
+            .inspect_err(|e| tracing::error!("Error initializing Brave client: {:?}", e))
+            .ok()
+    }
 
-        if !can_proceed {
-            // Calculate wait time and potentially wait if needed
-            let wait_time = { self.rate_limiter.lock().unwrap().time_until_next_slot() };
-
-            if wait_time > Duration::from_secs(0) {
-                // Return rate limit error rather than waiting
-                tracing::warn!(
-                    "Rate limit exceeded for Brave API, need to wait {:?}",
-                    wait_time
-                );
-                return Err(anyhow::anyhow!("429"));
+    /// Suggestions for `query` in a `country` ("ALL" for none) and
+    /// `language`, from the cache when it was asked recently. Concurrent
+    /// calls for the same query wait for a single request.
+    pub async fn get_suggestions(
+        &self,
+        query: &str,
+        country: &str,
+        language: &str,
+    ) -> Result<Vec<Suggestion>> {
+        let normalized = normalize_query(query);
+        if normalized.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Queries that only differ in case or spacing share a cache entry
+        let key = format!("{}|{}|{}", country, language, normalized);
+
+        let fetch = {
+            let mut lookups = self.lookups.lock().unwrap();
+            if let Some(suggestions) = lookups.cache.get(&key, Instant::now()) {
+                tracing::debug!("Suggestions for {:?} served from cache", key);
+                return Ok(suggestions);
             }
+            lookups.in_flight.entry(key.clone()).or_default().clone()
+        };
 
-            // Try again after rate limit window has passed
-            let mut limiter = self.rate_limiter.lock().unwrap();
-            if !limiter.can_make_request() {
-                tracing::warn!("Rate limit still exceeded for Brave API");
-                return Err(anyhow::anyhow!("429"));
+        let result = fetch
+            .get_or_init(|| async {
+                self.fetch(&normalized, country, language)
+                    .await
+                    .map_err(|e| e.to_string())
+            })
+            .await
+            .clone();
+
+        // Whoever gets here first moves the result into the cache
+        {
+            let mut lookups = self.lookups.lock().unwrap();
+            if lookups
+                .in_flight
+                .get(&key)
+                .is_some_and(|current| Arc::ptr_eq(current, &fetch))
+            {
+                lookups.in_flight.remove(&key);
+                if let Ok(suggestions) = &result {
+                    lookups
+                        .cache
+                        .insert(key.clone(), suggestions.clone(), Instant::now());
+                }
             }
         }
 
+        result.map_err(|e| anyhow::anyhow!(e))
+    }
+
+    async fn fetch(&self, query: &str, country: &str, language: &str) -> Result<Vec<Suggestion>> {
+        tracing::info!("Fetching suggestions for query: {}", query);
+
         let response = self
             .client
             .get(&self.url)
             .header("X-Subscription-Token", &self.api_key)
-            .query(&[("q", query), ("country", "US"), ("rich", "false")])
+            .query(&[
+                ("q", query),
+                ("country", country),
+                ("lang", language),
+                ("rich", "false"),
+            ])
             .send()
             .await?;
 
@@ -144,6 +168,9 @@ impl Brave {
         if status == 429 {
             return Err(anyhow::anyhow!("429"));
         }
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("Brave API returned {}", status));
+        }
 
         // Parse the response body back to a Response to return
         let suggestions: SuggestResponse = serde_json::from_str(&response_body)?;
@@ -151,6 +178,6 @@ impl Brave {
             "Successfully fetched {} suggestions",
             suggestions.results.len()
         );
-        Ok(suggestions)
+        Ok(suggestions.results)
     }
 }
@@ -0,0 +1,97 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+struct Entry<V> {
+    value: V,
+    inserted: Instant,
+    used: u64,
+}
+
+/// In-memory cache that forgets entries after `ttl` and drops the least
+/// recently used one once it holds `capacity` entries
+pub struct TtlCache<V> {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<String, Entry<V>>,
+    /// Keys by when they were last used, oldest first
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        TtlCache {
+            capacity: capacity.max(1),
+            ttl,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &str, now: Instant) -> Option<V> {
+        let entry = self.entries.get_mut(key)?;
+        if now.saturating_duration_since(entry.inserted) >= self.ttl {
+            self.remove(key);
+            return None;
+        }
+
+        self.clock += 1;
+        self.recency.remove(&entry.used);
+        self.recency.insert(self.clock, key.to_string());
+        entry.used = self.clock;
+        Some(entry.value.clone())
+    }
+
+    pub fn insert(&mut self, key: String, value: V, now: Instant) {
+        self.remove(&key);
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        self.clock += 1;
+        self.recency.insert(self.clock, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                inserted: now,
+                used: self.clock,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.used);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ttl_cache() {
+        let mut cache = TtlCache::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        cache.insert("a".to_string(), 1, now);
+        cache.insert("b".to_string(), 2, now);
+        assert_eq!(cache.get("a", now), Some(1));
+
+        // b is the least recently used now
+        cache.insert("c".to_string(), 3, now);
+        assert_eq!(cache.get("b", now), None);
+        assert_eq!(cache.get("a", now), Some(1));
+        assert_eq!(cache.get("c", now), Some(3));
+
+        assert_eq!(cache.get("a", now + Duration::from_secs(60)), None);
+        cache.insert("c".to_string(), 4, now);
+        assert_eq!(cache.get("c", now), Some(4));
+    }
+}
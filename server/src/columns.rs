@@ -0,0 +1,67 @@
+/// Longest column name we accept, in characters
+pub const MAX_COLUMN_NAME_LENGTH: usize = 64;
+
+/// Trim and collapse whitespace in a column name. Returns None for names
+/// that are empty or too long.
+pub fn normalize_column_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() || name.chars().count() > MAX_COLUMN_NAME_LENGTH {
+        return None;
+    }
+    Some(name)
+}
+
+/// Column colors are CSS hex colors, #rgb or #rrggbb
+pub fn is_valid_color(color: &str) -> bool {
+    let Some(hex) = color.strip_prefix('#') else {
+        return false;
+    };
+    matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Final order of a column's links. `placed` links land at their requested
+/// positions, clamped to the end of the column, and the `rest` keep their
+/// relative order around them.
+pub fn arrange(placed: &[(String, usize)], rest: &[String]) -> Vec<String> {
+    let mut placed = placed.to_vec();
+    placed.sort_by_key(|(_, position)| *position);
+
+    let mut order = rest.to_vec();
+    for (id, position) in placed {
+        order.insert(position.min(order.len()), id);
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_validation() {
+        assert_eq!(
+            normalize_column_name("  Work   stuff ").as_deref(),
+            Some("Work stuff")
+        );
+        assert_eq!(normalize_column_name("   "), None);
+
+        assert!(is_valid_color("#1e90ff"));
+        assert!(is_valid_color("#FFF"));
+        assert!(!is_valid_color("1e90ff"));
+        assert!(!is_valid_color("#12345"));
+        assert!(!is_valid_color("#ggg"));
+    }
+
+    #[test]
+    // moved links take their positions, the others close up around them
+    fn test_arrange() {
+        let rest: Vec<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        let placed = vec![
+            ("y".to_string(), 2),
+            ("x".to_string(), 0),
+            ("z".to_string(), 99),
+        ];
+        assert_eq!(arrange(&placed, &rest), vec!["x", "a", "y", "b", "c", "z"]);
+        assert_eq!(arrange(&[], &rest), rest);
+    }
+}
@@ -0,0 +1,142 @@
+use serde_json::{Map, Value};
+
+/// Longest dashboard name we accept, in characters
+pub const MAX_DASHBOARD_NAME_LENGTH: usize = 64;
+
+/// Name of the dashboard every user starts with
+pub const DEFAULT_DASHBOARD_NAME: &str = "Home";
+
+/// Trim and collapse whitespace in a dashboard name. Returns None for names
+/// that are empty or too long.
+pub fn normalize_dashboard_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() || name.chars().count() > MAX_DASHBOARD_NAME_LENGTH {
+        return None;
+    }
+    Some(name)
+}
+
+/// Settings overrides are a JSON object of settings keys, anything else is rejected
+pub fn parse_settings_overrides(overrides: &Value) -> Option<String> {
+    overrides.is_object().then(|| overrides.to_string())
+}
+
+fn parse_object(json: &str) -> Option<Map<String, Value>> {
+    match serde_json::from_str(json) {
+        Ok(Value::Object(object)) => Some(object),
+        _ => None,
+    }
+}
+
+/// The user's `settings` blob as seen on a dashboard, its `overrides` win
+/// key by key. `settings` is returned as is when either isn't an object.
+pub fn apply_settings_overrides(settings: &str, overrides: &str) -> String {
+    match (parse_object(settings), parse_object(overrides)) {
+        (Some(mut settings), Some(overrides)) if !overrides.is_empty() => {
+            settings.extend(overrides);
+            Value::Object(settings).to_string()
+        }
+        _ => settings.to_string(),
+    }
+}
+
+/// Split `saved`, settings saved on a dashboard, into the user's own
+/// settings and the dashboard's overrides. Keys the dashboard overrides
+/// change the override and keep their value in `settings`, so one
+/// dashboard's overrides never leak into the others.
+pub fn split_settings_overrides(
+    saved: &Value,
+    settings: &str,
+    overrides: &str,
+) -> (String, String) {
+    let (Value::Object(saved), Some(mut overrides)) = (saved, parse_object(overrides)) else {
+        return (saved.to_string(), overrides.to_string());
+    };
+    let base = parse_object(settings).unwrap_or_default();
+
+    let mut settings = Map::new();
+    for (key, value) in saved {
+        if overrides.contains_key(key) {
+            overrides.insert(key.clone(), value.clone());
+            if let Some(own) = base.get(key) {
+                settings.insert(key.clone(), own.clone());
+            }
+        } else {
+            settings.insert(key.clone(), value.clone());
+        }
+    }
+    (
+        Value::Object(settings).to_string(),
+        Value::Object(overrides).to_string(),
+    )
+}
+
+/// Name for a copy of `name` that doesn't clash with any of `taken`:
+/// "Work copy", then "Work copy 2" and so on
+pub fn copy_name(name: &str, taken: &[String]) -> String {
+    let base: String = format!("{} copy", name)
+        .chars()
+        .take(MAX_DASHBOARD_NAME_LENGTH - 4)
+        .collect();
+
+    let mut candidate = base.clone();
+    let mut n = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{} {}", base, n);
+        n += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_copy_name() {
+        assert_eq!(copy_name("Work", &[]), "Work copy");
+        assert_eq!(
+            copy_name(
+                "Work",
+                &[
+                    "Work".to_string(),
+                    "Work copy".to_string(),
+                    "Work copy 2".to_string()
+                ]
+            ),
+            "Work copy 3"
+        );
+    }
+
+    #[test]
+    fn test_parse_settings_overrides() {
+        assert_eq!(
+            parse_settings_overrides(&json!({"new_tabs": true})).as_deref(),
+            Some("{\"new_tabs\":true}")
+        );
+        assert_eq!(parse_settings_overrides(&json!([1, 2])), None);
+    }
+
+    #[test]
+    fn test_settings_overrides() {
+        let settings = r#"{"new_tabs":false,"metadata":true}"#;
+        let overrides = r#"{"new_tabs":true}"#;
+        assert_eq!(
+            apply_settings_overrides(settings, overrides),
+            r#"{"metadata":true,"new_tabs":true}"#
+        );
+        assert_eq!(apply_settings_overrides(settings, "{}"), settings);
+        assert_eq!(apply_settings_overrides("not json", overrides), "not json");
+
+        // Saving what the dashboard showed changes the override, not the
+        // user's own settings
+        let (settings, overrides) = split_settings_overrides(
+            &json!({"new_tabs": false, "metadata": false}),
+            settings,
+            overrides,
+        );
+        assert_eq!(settings, r#"{"metadata":false,"new_tabs":false}"#);
+        assert_eq!(overrides, r#"{"new_tabs":false}"#);
+    }
+}
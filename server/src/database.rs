@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
 use anyhow::Result;
@@ -8,9 +8,38 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sqlx::{
     FromRow, Row,
-    sqlite::{SqlitePool, SqlitePoolOptions},
+    sqlite::{SqliteConnection, SqlitePool, SqlitePoolOptions},
 };
 
+use crate::{
+    account_tokens::Purpose, columns, dashboards, icon_store, login_guard, search, suggestions,
+    teams::Role,
+};
+
+/// Aliased links the user bound to `?1` can follow: their own, their
+/// teams' (directly or through the team's organization) and their
+/// organizations'
+const VISIBLE_ALIASES: &str =
+    "SELECT l.alias, l.id AS link_id, l.title, l.url, l.owner_type, l.owner_id
+     FROM links l
+     WHERE l.alias IS NOT NULL AND (
+        (l.owner_type = 'user' AND l.owner_id = ?1)
+        OR (l.owner_type = 'team' AND l.owner_id IN (
+            SELECT t.id FROM teams t
+            JOIN user_memberships m ON m.user_id = ?1 AND (
+                (m.entity_type = 'team' AND m.entity_id = t.id)
+                OR (m.entity_type = 'organization' AND m.entity_id = t.organization_id)
+            )))
+        OR (l.owner_type = 'organization' AND l.owner_id IN (
+            SELECT entity_id FROM user_memberships
+            WHERE user_id = ?1 AND entity_type = 'organization'))
+     )";
+
+/// Which of several links sharing an alias wins: the user's, then a team's,
+/// then an organization's, the oldest first among equals
+const ALIAS_PRECEDENCE: &str =
+    "CASE l.owner_type WHEN 'user' THEN 0 WHEN 'team' THEN 1 ELSE 2 END, l.created_at";
+
 // Type definitions matching Database.ts
 #[derive(Serialize, Deserialize, Debug, Clone, FromRow)]
 pub struct User {
@@ -19,6 +48,9 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub created_at: String,
+    /// When the user confirmed their address, None until they do
+    #[serde(default)]
+    pub email_verified_at: Option<String>,
     #[sqlx(skip)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auth_token: Option<String>,
@@ -35,7 +67,96 @@ pub struct Link {
     pub owner_id: String,
     pub created_at: String,
     pub description: Option<String>,
+    /// Name of the link's column, kept in sync with the column for older clients
     pub column_type: String,
+    #[serde(default)]
+    pub column_id: Option<String>,
+    /// Go-link alias, unique per owner. Set through set_link_alias only so
+    /// link edits and copies never carry one along.
+    #[serde(default)]
+    pub alias: Option<String>,
+    /// Tag names, loaded separately from link_tags
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Dashboard {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    /// JSON object of settings that win over the user's own on this dashboard
+    pub settings_overrides: String,
+    pub created_at: String,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Column {
+    pub id: String,
+    pub name: String,
+    pub position: i32,
+    pub color: Option<String>,
+    pub collapsed: bool,
+    pub owner_type: String,
+    pub owner_id: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub owner_type: String,
+    pub owner_id: String,
+    pub created_at: String,
+    #[sqlx(default)]
+    pub link_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct LinkHealth {
+    pub link_id: String,
+    pub status_code: Option<i64>,
+    pub final_url: Option<String>,
+    pub error: Option<String>,
+    pub checked_at: String,
+}
+
+#[derive(Debug, Serialize, Clone, FromRow)]
+pub struct LinkSearchHit {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub link: Link,
+    pub rank: f64,
+    pub title_highlight: String,
+    pub url_highlight: String,
+    pub snippet: String,
+}
+
+/// Where a link should end up in a batch reorder
+#[derive(Debug, Deserialize, Clone)]
+pub struct LinkPosition {
+    pub link_id: String,
+    /// Target column, the link stays in its current column when neither this
+    /// nor `column_type` is set
+    #[serde(default)]
+    pub column_id: Option<String>,
+    /// Target column by name, created if missing like in update_link
+    #[serde(default)]
+    pub column_type: Option<String>,
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Icon {
+    pub hash: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub created_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
@@ -58,7 +179,7 @@ pub struct Subscription {
     pub created_at: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct UserMembership {
     pub user_id: String,
     pub entity_id: String,
@@ -67,6 +188,149 @@ pub struct UserMembership {
     pub created_at: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+    pub owner_id: Option<String>,
+    pub created_at: String,
+    /// The requesting user's role
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Team {
+    pub id: String,
+    pub name: String,
+    pub owner_id: Option<String>,
+    pub organization_id: Option<String>,
+    pub created_at: String,
+    /// The requesting user's role, the stronger of their team and organization role
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Member {
+    pub user_id: String,
+    pub email: String,
+    pub role: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Invitation {
+    pub id: String,
+    pub email: String,
+    pub entity_id: String,
+    pub entity_type: String,
+    pub role: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub invited_by: Option<String>,
+    pub expires_at: String,
+    pub created_at: String,
+}
+
+/// A personal access token, without the token itself
+#[derive(Debug, Serialize, Clone, FromRow)]
+pub struct ApiToken {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: String,
+    pub name: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    /// Space separated in the database, a list in JSON
+    #[serde(serialize_with = "serialize_space_separated")]
+    pub scopes: String,
+    pub last_used_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub created_at: String,
+}
+
+fn serialize_space_separated<S: serde::Serializer>(
+    value: &str,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(value.split_whitespace())
+}
+
+/// A signed-in device or browser
+#[derive(Debug, Serialize, Clone, FromRow)]
+pub struct Session {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: String,
+    #[serde(skip)]
+    pub refresh_token_hash: String,
+    #[serde(skip)]
+    pub previous_refresh_hash: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: String,
+    pub last_used_at: String,
+    pub expires_at: String,
+    #[serde(skip)]
+    pub revoked_at: Option<String>,
+    /// Whether this is the session making the request
+    #[sqlx(skip)]
+    pub current: bool,
+}
+
+/// One of a user's search engines, `url` has `%s` where the query goes
+#[derive(Debug, Serialize, Clone, FromRow)]
+pub struct SearchEngine {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: String,
+    pub name: String,
+    pub url: String,
+    /// Bang without the "!", searches this engine with `!keyword terms`
+    pub keyword: Option<String>,
+    pub icon: Option<String>,
+    pub is_default: bool,
+    pub order_index: i64,
+    pub created_at: String,
+}
+
+/// A link with a go-link alias, as one user sees it
+#[derive(Debug, Serialize, Clone, FromRow)]
+pub struct GoLink {
+    pub alias: String,
+    pub link_id: String,
+    pub title: String,
+    pub url: String,
+    pub owner_type: String,
+    pub owner_id: String,
+    /// False when a link of the user's own or of a closer owner has the
+    /// same alias and wins
+    #[sqlx(skip)]
+    pub active: bool,
+}
+
+/// A distinct search a user made and how often
+#[derive(Debug, Serialize, Clone, FromRow)]
+pub struct SearchHistoryEntry {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: String,
+    pub query: String,
+    pub searches: i64,
+    pub last_searched_at: String,
+    pub created_at: String,
+    /// Frecency, only set by `get_search_history`
+    #[sqlx(default)]
+    pub score: i64,
+}
+
+/// A built-in bang everyone can use
+#[derive(Debug, Serialize, Clone, FromRow)]
+pub struct Bang {
+    pub keyword: String,
+    pub name: String,
+    pub url: String,
+    pub icon: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct UserSettings {
     pub user_id: String,
@@ -77,6 +341,8 @@ pub struct UserSettings {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserData {
     pub user: User,
+    pub dashboards: Vec<Dashboard>,
+    pub columns: Vec<Column>,
     pub links: Vec<Link>,
     pub subscription: Option<Subscription>,
     pub settings: Option<UserSettings>,
@@ -89,7 +355,7 @@ pub struct Database {
 }
 
 /// Get the platform-appropriate data directory for storing the database
-fn get_data_dir() -> PathBuf {
+pub fn get_data_dir() -> PathBuf {
     if let Some(data_dir) = dirs::data_local_dir() {
         data_dir.join("omega-tab")
     } else {
@@ -175,10 +441,18 @@ impl Database {
 
         tracing::info!("Database migrations completed successfully");
 
-        Ok(Self {
+        let database = Self {
             client: Client::new(),
             pool,
-        })
+        };
+
+        // Move any inline data: URI icons into the icon store
+        if let Err(e) = database.migrate_inline_icons().await {
+            tracing::warn!("Failed to migrate inline icons: {}", e);
+            // Continue anyway - links keep their inline icons until the next start
+        }
+
+        Ok(database)
     }
 
     pub async fn get_user(&self, id: &str) -> Result<User> {
@@ -270,11 +544,20 @@ impl Database {
             email: email.to_string(),
             password_hash,
             created_at,
+            email_verified_at: None,
             auth_token: None,
         })
     }
 
-    pub async fn verify_password(&self, email: &str, password: &str) -> Result<User> {
+    /// Check an email and password. With `require_verified` an unverified
+    /// account fails with "403", only once the password has been checked so
+    /// it doesn't reveal which addresses have accounts.
+    pub async fn verify_password(
+        &self,
+        email: &str,
+        password: &str,
+        require_verified: bool,
+    ) -> Result<User> {
         tracing::info!("Verifying password for user: {}", email);
 
         let user = self.get_user_by_email(email).await?;
@@ -287,10 +570,124 @@ impl Database {
             return Err(anyhow::anyhow!("Invalid credentials"));
         }
 
+        if require_verified && user.email_verified_at.is_none() {
+            tracing::warn!("Email not verified for user: {}", email);
+            return Err(anyhow::anyhow!("403"));
+        }
+
         tracing::info!("Successfully verified password for user: {}", email);
         Ok(user)
     }
 
+    /// Store a token for an emailed link, replacing any earlier one for the
+    /// same purpose so only the newest link works
+    pub async fn create_email_token(
+        &self,
+        user_id: &str,
+        purpose: Purpose,
+        token_hash: &str,
+        expires_at: &str,
+    ) -> Result<()> {
+        tracing::info!("Creating {} token for user: {}", purpose.as_str(), user_id);
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM email_tokens WHERE user_id = ? AND purpose = ?")
+            .bind(user_id)
+            .bind(purpose.as_str())
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO email_tokens (id, user_id, purpose, token_hash, expires_at, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .bind(token_hash)
+        .bind(expires_at)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Delete a token and return its user id and expiry. Deleting and reading
+    /// in one statement makes sure a token is only ever redeemed once.
+    pub async fn redeem_email_token(
+        &self,
+        token_hash: &str,
+        purpose: Purpose,
+    ) -> Result<(String, String)> {
+        let row = sqlx::query(
+            "DELETE FROM email_tokens WHERE token_hash = ? AND purpose = ?
+             RETURNING user_id, expires_at",
+        )
+        .bind(token_hash)
+        .bind(purpose.as_str())
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("404"))?;
+
+        Ok((row.get("user_id"), row.get("expires_at")))
+    }
+
+    /// Set a new password. Whoever could open the reset link controls the
+    /// address, so it counts as verified too.
+    pub async fn reset_password(&self, user_id: &str, password: &str) -> Result<()> {
+        tracing::info!("Resetting password for user: {}", user_id);
+
+        let password_hash = hash(password, DEFAULT_COST)
+            .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE users SET password_hash = ?, email_verified_at = COALESCE(email_verified_at, ?)
+             WHERE id = ?",
+        )
+        .bind(&password_hash)
+        .bind(Utc::now().to_rfc3339())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        sqlx::query("DELETE FROM email_tokens WHERE user_id = ? AND purpose = ?")
+            .bind(user_id)
+            .bind(Purpose::PasswordReset.as_str())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::info!("Successfully reset password for user: {}", user_id);
+        Ok(())
+    }
+
+    pub async fn mark_email_verified(&self, user_id: &str) -> Result<()> {
+        tracing::info!("Marking email verified for user: {}", user_id);
+
+        let result = sqlx::query(
+            "UPDATE users SET email_verified_at = COALESCE(email_verified_at, ?) WHERE id = ?",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+        Ok(())
+    }
+
     pub async fn create_user(&self, user: User) -> Result<User> {
         tracing::info!("Creating new user: {}", user.email);
 
@@ -332,15 +729,46 @@ impl Database {
     }
 
     // Links
-    pub async fn get_links(&self, owner_id: &str, owner_type: &str) -> Result<Vec<Link>> {
+    /// Fetch an owner's links with their tags. With `dashboard_id` only links
+    /// in that dashboard's columns are returned, and when `tags` isn't empty
+    /// only links carrying every one of those tags.
+    pub async fn get_links(
+        &self,
+        owner_id: &str,
+        owner_type: &str,
+        dashboard_id: Option<&str>,
+        tags: &[String],
+    ) -> Result<Vec<Link>> {
         tracing::info!("Fetching links for owner {}: {}", owner_type, owner_id);
 
-        let links =
-            sqlx::query_as::<_, Link>("SELECT * FROM links WHERE owner_id = ? AND owner_type = ?")
-                .bind(owner_id)
-                .bind(owner_type)
-                .fetch_all(&self.pool)
-                .await?;
+        if !tags.is_empty() {
+            tracing::info!("Filtering links by tags: {:?}", tags);
+        }
+
+        // tags.name is NOCASE, so the IN comparison ignores case too
+        let mut links = sqlx::query_as::<_, Link>(
+            "SELECT * FROM links WHERE owner_id = ?1 AND owner_type = ?2
+            AND (?3 IS NULL OR column_id IN (
+                SELECT id FROM columns WHERE owner_type = 'dashboard' AND owner_id = ?3
+            ))
+            AND (?5 = 0 OR id IN (
+                SELECT lt.link_id FROM link_tags lt
+                JOIN tags t ON t.id = lt.tag_id
+                WHERE t.owner_id = ?1 AND t.owner_type = ?2
+                AND t.name IN (SELECT value FROM json_each(?4))
+                GROUP BY lt.link_id
+                HAVING COUNT(*) = ?5
+            ))",
+        )
+        .bind(owner_id)
+        .bind(owner_type)
+        .bind(dashboard_id)
+        .bind(serde_json::to_string(tags)?)
+        .bind(tags.len() as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.attach_tags(owner_id, owner_type, &mut links).await?;
 
         tracing::info!("Successfully fetched {} links", links.len());
         Ok(links)
@@ -367,6 +795,15 @@ impl Database {
         }
     }
 
+    /// Fetch a link whoever owns it, callers check access
+    pub async fn get_link_by_id(&self, id: &str) -> Result<Link> {
+        sqlx::query_as::<_, Link>("SELECT * FROM links WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("404"))
+    }
+
     pub async fn create_link(&self, link: Link) -> Result<Link> {
         tracing::info!(
             "Creating new link for owner {}: {}",
@@ -374,678 +811,3582 @@ impl Database {
             link.url
         );
 
-        let result = sqlx::query(
-            "INSERT INTO links (id, title, url, icon, order_index, owner_type, owner_id, created_at, description, column_type)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
-        )
-        .bind(&link.id)
-        .bind(&link.title)
-        .bind(&link.url)
-        .bind(&link.icon)
-        .bind(&link.order_index)
-        .bind(&link.owner_type)
-        .bind(&link.owner_id)
-        .bind(&link.created_at)
-        .bind(&link.description)
-        .bind(&link.column_type)
-        .execute(&self.pool)
-        .await?;
-
-        if result.rows_affected() == 0 {
-            tracing::error!("Failed to create link");
-            return Err(anyhow::anyhow!("Failed to create link: database error"));
-        }
+        let mut tx = self.pool.begin().await?;
+        let link = insert_link(&mut tx, &link).await?;
+        tx.commit().await?;
 
         tracing::info!("Successfully created link: {}", link.id);
         Ok(link)
     }
 
-    pub async fn update_link(&self, link: Link) -> Result<()> {
+    pub async fn create_links(&self, links: &[Link]) -> Result<()> {
+        tracing::info!("Creating {} links in a single transaction", links.len());
+
+        let mut tx = self.pool.begin().await?;
+
+        for link in links {
+            insert_link(&mut tx, link).await?;
+        }
+
+        tx.commit().await?;
+
+        tracing::info!("Successfully created {} links", links.len());
+        Ok(())
+    }
+
+    /// Update a link. It moves to `column_id` when set, otherwise to the
+    /// column named `column_type` on the dashboard the link is on (the
+    /// owner's, for team links), which is created if missing. The
+    /// link keeps its position, or goes last when it changes column.
+    /// `order_index` is ignored, reorder_links moves links around. `tags`
+    /// replace the link's tags when given.
+    pub async fn update_link(&self, link: Link, tags: Option<&[String]>) -> Result<()> {
         tracing::info!("Updating link: {}", link.id);
 
+        let mut tx = self.pool.begin().await?;
+
+        let existing = sqlx::query_as::<_, Link>("SELECT * FROM links WHERE id = ?")
+            .bind(&link.id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Link not found or update failed"))?;
+
+        // A column name is looked up on the dashboard the link is on, only
+        // new links go to the active one
+        let dashboard_id = match (&link.column_id, &existing.column_id) {
+            (None, Some(column_id)) if existing.owner_type == "user" => {
+                sqlx::query_scalar::<_, String>(
+                    "SELECT owner_id FROM columns WHERE id = ? AND owner_type = 'dashboard'",
+                )
+                .bind(column_id)
+                .fetch_optional(&mut *tx)
+                .await?
+            }
+            _ => None,
+        };
+        let column = match dashboard_id {
+            Some(dashboard_id) => {
+                named_column(&mut tx, &dashboard_id, "dashboard", &link.column_type).await?
+            }
+            None => {
+                resolve_column(
+                    &mut tx,
+                    &existing.owner_id,
+                    &existing.owner_type,
+                    link.column_id.as_deref(),
+                    &link.column_type,
+                )
+                .await?
+            }
+        };
+
+        let order_index = if existing.column_id.as_deref() == Some(column.id.as_str()) {
+            existing.order_index
+        } else {
+            sqlx::query_scalar(
+                "SELECT COALESCE(MAX(order_index) + 1, 0) FROM links WHERE column_id = ?",
+            )
+            .bind(&column.id)
+            .fetch_one(&mut *tx)
+            .await?
+        };
+
         let result = sqlx::query(
             "UPDATE links
             SET title = ?, url = ?, icon = ?,
-            order_index = ?, description = ?, column_type = ?
+            order_index = ?, description = ?, column_type = ?, column_id = ?
             WHERE id = ?",
         )
         .bind(&link.title)
         .bind(&link.url)
         .bind(&link.icon)
-        .bind(&link.order_index)
+        .bind(order_index)
         .bind(&link.description)
-        .bind(&link.column_type)
+        .bind(&column.name)
+        .bind(&column.id)
         .bind(&link.id)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
         if result.rows_affected() == 0 {
             return Err(anyhow::anyhow!("Link not found or update failed"));
         }
 
+        // The old health record describes the old URL, let the checker start over
+        if existing.url != link.url {
+            sqlx::query("DELETE FROM link_health WHERE link_id = ?")
+                .bind(&link.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        if let Some(tags) = tags {
+            write_link_tags(
+                &mut tx,
+                &Link {
+                    tags: tags.to_vec(),
+                    ..existing
+                },
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
         tracing::info!("Successfully updated link: {}", link.id);
         Ok(())
     }
 
-    pub async fn delete_link(&self, id: &str) -> Result<()> {
-        tracing::info!("Deleting link: {}", id);
+    /// Move and reorder an owner's links in one transaction. Every column a
+    /// link leaves or enters is renumbered from 0. Fails with "404" if any
+    /// link or column isn't the owner's, in which case nothing changes.
+    /// Returns the links of the affected columns, in order.
+    pub async fn reorder_links(
+        &self,
+        owner_id: &str,
+        owner_type: &str,
+        positions: &[LinkPosition],
+    ) -> Result<Vec<Link>> {
+        tracing::info!(
+            "Reordering {} links for owner {}: {}",
+            positions.len(),
+            owner_type,
+            owner_id
+        );
 
-        let result = sqlx::query("DELETE FROM links WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
+        let mut tx = self.pool.begin().await?;
 
-        if result.rows_affected() == 0 {
-            tracing::info!("No link found to delete with ID: {}", id);
-            return Err(anyhow::anyhow!("Link not found"));
+        let mut placed: HashMap<String, Vec<(String, usize)>> = HashMap::new();
+        let mut touched: Vec<String> = Vec::new();
+        for position in positions {
+            let link = sqlx::query_as::<_, Link>(
+                "SELECT * FROM links WHERE id = ? AND owner_id = ? AND owner_type = ?",
+            )
+            .bind(&position.link_id)
+            .bind(owner_id)
+            .bind(owner_type)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                tracing::info!("Reorder references unknown link: {}", position.link_id);
+                anyhow::anyhow!("404")
+            })?;
+
+            // Without a target the link stays in its column
+            let (column_id, column_name) = match (&position.column_id, &position.column_type) {
+                (None, None) => (link.column_id.as_deref(), link.column_type.as_str()),
+                (id, name) => (id.as_deref(), name.as_deref().unwrap_or_default()),
+            };
+            let column =
+                resolve_column(&mut tx, owner_id, owner_type, column_id, column_name).await?;
+
+            sqlx::query("UPDATE links SET column_id = ?, column_type = ? WHERE id = ?")
+                .bind(&column.id)
+                .bind(&column.name)
+                .bind(&link.id)
+                .execute(&mut *tx)
+                .await?;
+
+            for column_id in link.column_id.iter().chain([&column.id]) {
+                if !touched.contains(column_id) {
+                    touched.push(column_id.clone());
+                }
+            }
+            placed
+                .entry(column.id)
+                .or_default()
+                .push((link.id, position.position));
         }
 
-        tracing::info!("Successfully deleted link: {}", id);
-        Ok(())
-    }
+        for column_id in &touched {
+            let placed = placed.remove(column_id).unwrap_or_default();
+            let rest: Vec<String> = sqlx::query_scalar::<_, String>(
+                "SELECT id FROM links WHERE column_id = ? ORDER BY order_index, rowid",
+            )
+            .bind(column_id)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .filter(|id| !placed.iter().any(|(placed_id, _)| placed_id == id))
+            .collect();
+
+            for (order_index, id) in columns::arrange(&placed, &rest).iter().enumerate() {
+                sqlx::query("UPDATE links SET order_index = ? WHERE id = ?")
+                    .bind(order_index as i32)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
 
-    // User Memberships
-    pub async fn get_user_memberships(&self, user_id: &str) -> Result<Vec<UserMembership>> {
-        tracing::info!("Fetching memberships for user: {}", user_id);
+        tx.commit().await?;
 
-        let memberships =
-            sqlx::query_as::<_, UserMembership>("SELECT * FROM user_memberships WHERE user_id = ?")
-                .bind(user_id)
-                .fetch_all(&self.pool)
-                .await?;
+        let mut links = sqlx::query_as::<_, Link>(
+            "SELECT l.* FROM links l
+             JOIN json_each(?) t ON t.value = l.column_id
+             ORDER BY t.key, l.order_index",
+        )
+        .bind(serde_json::to_string(&touched)?)
+        .fetch_all(&self.pool)
+        .await?;
+        self.attach_tags(owner_id, owner_type, &mut links).await?;
 
-        tracing::info!("Successfully fetched {} memberships", memberships.len());
-        Ok(memberships)
+        tracing::info!("Successfully reordered links for owner: {}", owner_id);
+        Ok(links)
     }
 
-    pub async fn add_member(&self, membership: UserMembership) -> Result<()> {
-        tracing::info!(
-            "Adding member: {} to entity: {}",
-            membership.user_id,
-            membership.entity_id
-        );
+    pub async fn delete_link(&self, id: &str) -> Result<()> {
+        tracing::info!("Deleting link: {}", id);
 
-        let result = sqlx::query(
-            "INSERT INTO user_memberships (user_id, entity_id, entity_type, role, created_at)
-             VALUES (?, ?, ?, ?, ?)",
-        )
-        .bind(&membership.user_id)
-        .bind(&membership.entity_id)
-        .bind(&membership.entity_type)
-        .bind(&membership.role)
-        .bind(&membership.created_at)
-        .execute(&self.pool)
-        .await?;
+        let result = sqlx::query("DELETE FROM links WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
 
         if result.rows_affected() == 0 {
-            tracing::error!("Failed to add member");
-            return Err(anyhow::anyhow!("Failed to add member: database error"));
+            tracing::info!("No link found to delete with ID: {}", id);
+            return Err(anyhow::anyhow!("Link not found"));
         }
 
-        tracing::info!("Successfully added member");
+        tracing::info!("Successfully deleted link: {}", id);
         Ok(())
     }
 
-    pub async fn update_member_role(
+    /// Full-text search over an owner's links, best match first.
+    /// `fts_query` must already be valid FTS5 syntax (see search::fts_query).
+    pub async fn search_links(
         &self,
-        user_id: &str,
-        entity_id: &str,
-        role: &str,
-    ) -> Result<UserMembership> {
-        tracing::info!(
-            "Updating role for user: {} in entity: {}",
-            user_id,
-            entity_id
-        );
-
-        let result =
-            sqlx::query("UPDATE user_memberships SET role = ? WHERE user_id = ? AND entity_id = ?")
-                .bind(role)
-                .bind(user_id)
-                .bind(entity_id)
-                .execute(&self.pool)
-                .await?;
+        owner_id: &str,
+        owner_type: &str,
+        fts_query: &str,
+        limit: i64,
+    ) -> Result<Vec<LinkSearchHit>> {
+        tracing::info!("Searching links for owner {}: {}", owner_type, owner_id);
+
+        // bm25 weights follow the column order: link_id, title, url, description, column_type
+        let mut hits = sqlx::query_as::<_, LinkSearchHit>(
+            "SELECT l.*,
+                bm25(links_fts, 0.0, 10.0, 4.0, 2.0, 1.0) AS rank,
+                highlight(links_fts, 1, ?1, ?2) AS title_highlight,
+                highlight(links_fts, 2, ?1, ?2) AS url_highlight,
+                snippet(links_fts, 3, ?1, ?2, '…', 12) AS snippet
+             FROM links_fts
+             JOIN links l ON l.id = links_fts.link_id
+             WHERE links_fts MATCH ?3 AND l.owner_id = ?4 AND l.owner_type = ?5
+             ORDER BY rank
+             LIMIT ?6",
+        )
+        .bind(search::MATCH_START)
+        .bind(search::MATCH_END)
+        .bind(fts_query)
+        .bind(owner_id)
+        .bind(owner_type)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(anyhow::anyhow!("Membership not found or update failed"));
+        let mut link_tags = self.get_link_tags(owner_id, owner_type).await?;
+        for hit in &mut hits {
+            hit.link.tags = link_tags.remove(&hit.link.id).unwrap_or_default();
         }
 
-        // Fetch and return the updated membership
-        let membership = sqlx::query_as::<_, UserMembership>(
-            "SELECT * FROM user_memberships WHERE user_id = ? AND entity_id = ?",
+        tracing::info!("Found {} matching links", hits.len());
+        Ok(hits)
+    }
+
+    /// URL of the user's own link titled exactly `title`, ignoring case.
+    /// Lets a link's name be typed into the browser's address bar.
+    pub async fn find_link_url_by_title(
+        &self,
+        user_id: &str,
+        title: &str,
+    ) -> Result<Option<String>> {
+        let url = sqlx::query_scalar::<_, String>(
+            "SELECT url FROM links
+             WHERE owner_type = 'user' AND owner_id = ? AND lower(trim(title)) = lower(trim(?))
+             ORDER BY order_index
+             LIMIT 1",
         )
         .bind(user_id)
-        .bind(entity_id)
-        .fetch_one(&self.pool)
+        .bind(title)
+        .fetch_optional(&self.pool)
         .await?;
 
-        tracing::info!("Successfully updated member role");
-        Ok(membership)
+        Ok(url)
     }
 
-    pub async fn remove_member(&self, user_id: &str, entity_id: &str) -> Result<()> {
-        tracing::info!("Removing member: {} from entity: {}", user_id, entity_id);
+    // Go links
 
-        let result =
-            sqlx::query("DELETE FROM user_memberships WHERE user_id = ? AND entity_id = ?")
-                .bind(user_id)
-                .bind(entity_id)
-                .execute(&self.pool)
-                .await?;
+    /// Every alias the user can follow, by alias with the link that wins
+    /// first and marked active
+    pub async fn get_go_links(&self, user_id: &str) -> Result<Vec<GoLink>> {
+        tracing::info!("Fetching go links for user: {}", user_id);
 
-        if result.rows_affected() == 0 {
-            tracing::info!("No membership found to delete");
-            return Err(anyhow::anyhow!("Membership not found"));
+        let mut go_links = sqlx::query_as::<_, GoLink>(&format!(
+            "{} ORDER BY l.alias, {}",
+            VISIBLE_ALIASES, ALIAS_PRECEDENCE
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut seen = HashSet::new();
+        for go_link in &mut go_links {
+            go_link.active = seen.insert(go_link.alias.clone());
         }
 
-        tracing::info!("Successfully removed member");
-        Ok(())
+        tracing::info!("Successfully fetched {} go links", go_links.len());
+        Ok(go_links)
     }
 
-    // Plans
-    pub async fn get_plans(&self) -> Result<Vec<Plan>> {
-        tracing::info!("Fetching all plans");
+    /// The link `alias` takes the user to, their own before their teams'
+    /// before their organizations'
+    pub async fn resolve_alias(&self, user_id: &str, alias: &str) -> Result<Option<GoLink>> {
+        let go_link = sqlx::query_as::<_, GoLink>(&format!(
+            "{} AND l.alias = ?2 ORDER BY {} LIMIT 1",
+            VISIBLE_ALIASES, ALIAS_PRECEDENCE
+        ))
+        .bind(user_id)
+        .bind(alias)
+        .fetch_optional(&self.pool)
+        .await?;
 
-        let plans = sqlx::query_as::<_, Plan>("SELECT * FROM plans")
-            .fetch_all(&self.pool)
-            .await?;
+        Ok(go_link.map(|go_link| GoLink {
+            active: true,
+            ..go_link
+        }))
+    }
 
-        tracing::info!("Successfully fetched {} plans", plans.len());
-        Ok(plans)
+    /// Id of the owner's link that has `alias`, if any
+    pub async fn find_alias(
+        &self,
+        owner_type: &str,
+        owner_id: &str,
+        alias: &str,
+    ) -> Result<Option<String>> {
+        let link_id = sqlx::query_scalar::<_, String>(
+            "SELECT id FROM links WHERE owner_type = ? AND owner_id = ? AND alias = ?",
+        )
+        .bind(owner_type)
+        .bind(owner_id)
+        .bind(alias)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(link_id)
     }
 
-    pub async fn get_plan(&self, id: &str) -> Result<Plan> {
-        tracing::info!("Fetching plan: {}", id);
+    /// Give a link an alias, or take it away with None. Fails with "409"
+    /// when another of the owner's links has the alias.
+    pub async fn set_link_alias(&self, link_id: &str, alias: Option<&str>) -> Result<Link> {
+        tracing::info!("Setting alias of link {} to {:?}", link_id, alias);
 
-        let plan = sqlx::query_as::<_, Plan>("SELECT * FROM plans WHERE id = ?")
-            .bind(id)
-            .fetch_optional(&self.pool)
-            .await?;
+        let mut tx = self.pool.begin().await?;
 
-        match plan {
-            Some(plan) => {
-                tracing::info!("Successfully fetched plan");
-                Ok(plan)
-            }
-            None => {
-                tracing::info!("Plan not found: {}", id);
-                Err(anyhow::anyhow!("404"))
+        let link = sqlx::query_as::<_, Link>("SELECT * FROM links WHERE id = ?")
+            .bind(link_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("404"))?;
+
+        if let Some(alias) = alias {
+            let taken = sqlx::query_scalar::<_, String>(
+                "SELECT id FROM links
+                 WHERE owner_type = ? AND owner_id = ? AND alias = ? AND id != ?",
+            )
+            .bind(&link.owner_type)
+            .bind(&link.owner_id)
+            .bind(alias)
+            .bind(link_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .is_some();
+            if taken {
+                tracing::info!("Alias already used by {}: {}", link.owner_id, alias);
+                return Err(anyhow::anyhow!("409"));
             }
         }
+
+        let mut link =
+            sqlx::query_as::<_, Link>("UPDATE links SET alias = ? WHERE id = ? RETURNING *")
+                .bind(alias)
+                .bind(link_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        tx.commit().await?;
+
+        let (owner_id, owner_type) = (link.owner_id.clone(), link.owner_type.clone());
+        self.attach_tags(&owner_id, &owner_type, std::slice::from_mut(&mut link))
+            .await?;
+        tracing::info!("Successfully set alias of link: {}", link_id);
+        Ok(link)
     }
 
-    // Subscriptions
-    pub async fn get_user_subscription(&self, user_id: &str) -> Result<Subscription> {
-        tracing::info!("Fetching subscription for user: {}", user_id);
+    // Search engines
 
-        let subscription = sqlx::query_as::<_, Subscription>(
-            "SELECT * FROM subscriptions WHERE entity_id = ? AND entity_type = 'user'",
+    pub async fn get_search_engines(&self, user_id: &str) -> Result<Vec<SearchEngine>> {
+        tracing::info!("Fetching search engines for user: {}", user_id);
+
+        let engines = sqlx::query_as::<_, SearchEngine>(
+            "SELECT * FROM search_engines WHERE user_id = ? ORDER BY order_index, created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(engines)
+    }
+
+    pub async fn get_default_search_engine(&self, user_id: &str) -> Result<Option<SearchEngine>> {
+        let engine = sqlx::query_as::<_, SearchEngine>(
+            "SELECT * FROM search_engines WHERE user_id = ? AND is_default = 1",
         )
         .bind(user_id)
         .fetch_optional(&self.pool)
         .await?;
 
-        match subscription {
-            Some(subscription) => {
-                tracing::info!("Successfully fetched subscription for user");
-                println!("Successfully fetched subscription for user");
-                Ok(subscription)
-            }
-            None => {
-                tracing::info!("No subscription found for user: {}", user_id);
-                println!("No subscription found for user: {}", user_id);
-                Err(anyhow::anyhow!("404"))
-            }
-        }
+        Ok(engine)
     }
 
-    pub async fn create_subscription(
-        &self,
-        entity_id: &str,
-        entity_type: &str,
-        plan_id: &str,
-        status: &str,
-        current_period_end: DateTime<Utc>,
-    ) -> Result<Subscription> {
-        let sub_uuid = uuid::Uuid::new_v4().to_string();
-        let created_at = Utc::now().to_rfc3339();
-        let period_end = current_period_end.to_rfc3339();
+    /// Add a search engine at the end of the user's list. Fails with "409"
+    /// when another of their engines has the keyword.
+    pub async fn create_search_engine(&self, engine: &SearchEngine) -> Result<SearchEngine> {
+        tracing::info!(
+            "Creating search engine for user {}: {}",
+            engine.user_id,
+            engine.name
+        );
 
-        let subscription = Subscription {
-            id: sub_uuid.clone(),
-            entity_id: entity_id.to_string(),
-            entity_type: entity_type.to_string(),
-            plan_id: plan_id.to_string(),
-            status: status.to_string(),
-            current_period_end: period_end.clone(),
-            created_at: created_at.clone(),
-        };
+        let mut tx = self.pool.begin().await?;
 
-        tracing::info!("Creating subscription with payload: {:?}", subscription);
+        if keyword_taken(&mut tx, engine).await? {
+            tracing::info!("Search engine keyword already used: {:?}", engine.keyword);
+            return Err(anyhow::anyhow!("409"));
+        }
+        if engine.is_default {
+            clear_default_search_engine(&mut tx, &engine.user_id).await?;
+        }
 
-        let result = sqlx::query(
-            "INSERT INTO subscriptions (id, entity_id, entity_type, plan_id, status, current_period_end, created_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        let order_index = sqlx::query_scalar::<_, i64>(
+            "SELECT COALESCE(MAX(order_index) + 1, 0) FROM search_engines WHERE user_id = ?",
         )
-        .bind(&sub_uuid)
-        .bind(entity_id)
-        .bind(entity_type)
-        .bind(plan_id)
-        .bind(status)
-        .bind(&period_end)
-        .bind(&created_at)
-        .execute(&self.pool)
+        .bind(&engine.user_id)
+        .fetch_one(&mut *tx)
         .await?;
 
-        if result.rows_affected() == 0 {
-            tracing::error!("Failed to create subscription");
-            return Err(anyhow::anyhow!(
-                "Failed to create subscription: database error"
-            ));
-        }
+        sqlx::query(
+            "INSERT INTO search_engines
+                (id, user_id, name, url, keyword, icon, is_default, order_index, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&engine.id)
+        .bind(&engine.user_id)
+        .bind(&engine.name)
+        .bind(&engine.url)
+        .bind(&engine.keyword)
+        .bind(&engine.icon)
+        .bind(engine.is_default)
+        .bind(order_index)
+        .bind(&engine.created_at)
+        .execute(&mut *tx)
+        .await?;
 
-        tracing::info!("Successfully created subscription: {}", subscription.id);
-        Ok(subscription)
+        tx.commit().await?;
+
+        tracing::info!("Successfully created search engine: {}", engine.id);
+        Ok(SearchEngine {
+            order_index,
+            ..engine.clone()
+        })
     }
 
-    pub async fn update_subscription(&self, subscription: Subscription) -> Result<()> {
-        tracing::info!("Updating subscription: {}", subscription.id);
+    /// Update one of the user's search engines, making it the default takes
+    /// that from the previous one. "404" when it isn't theirs, "409" for a
+    /// keyword they already use.
+    pub async fn update_search_engine(&self, engine: &SearchEngine) -> Result<SearchEngine> {
+        tracing::info!("Updating search engine: {}", engine.id);
 
-        let result = sqlx::query(
-            "UPDATE subscriptions
-            SET entity_id = ?, entity_type = ?, plan_id = ?,
-            status = ?, current_period_end = ?
-            WHERE id = ?",
-        )
-        .bind(&subscription.entity_id)
-        .bind(&subscription.entity_type)
-        .bind(&subscription.plan_id)
-        .bind(&subscription.status)
-        .bind(&subscription.current_period_end)
-        .bind(&subscription.id)
-        .execute(&self.pool)
-        .await?;
+        let mut tx = self.pool.begin().await?;
 
-        if result.rows_affected() == 0 {
-            return Err(anyhow::anyhow!("Subscription not found or update failed"));
+        if keyword_taken(&mut tx, engine).await? {
+            tracing::info!("Search engine keyword already used: {:?}", engine.keyword);
+            return Err(anyhow::anyhow!("409"));
+        }
+        if engine.is_default {
+            clear_default_search_engine(&mut tx, &engine.user_id).await?;
         }
 
-        tracing::info!("Successfully updated subscription: {}", subscription.id);
+        let updated = sqlx::query_as::<_, SearchEngine>(
+            "UPDATE search_engines SET name = ?, url = ?, keyword = ?, icon = ?, is_default = ?
+             WHERE id = ? AND user_id = ?
+             RETURNING *",
+        )
+        .bind(&engine.name)
+        .bind(&engine.url)
+        .bind(&engine.keyword)
+        .bind(&engine.icon)
+        .bind(engine.is_default)
+        .bind(&engine.id)
+        .bind(&engine.user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("404"))?;
 
-        Ok(())
+        tx.commit().await?;
+
+        tracing::info!("Successfully updated search engine: {}", engine.id);
+        Ok(updated)
     }
 
-    pub async fn create_feedback_timestamp(
-        &self,
-        user_id: &str,
-        created_at: &DateTime<Utc>,
-    ) -> Result<()> {
-        tracing::info!("Creating feedback timestamp for user: {}", user_id);
+    pub async fn delete_search_engine(&self, id: &str, user_id: &str) -> Result<()> {
+        tracing::info!("Deleting search engine: {}", id);
 
-        let created_at_str = created_at.to_rfc3339();
-
-        let result =
-            sqlx::query("INSERT INTO feedback_timestamps (user_id, created_at) VALUES (?, ?)")
-                .bind(user_id)
-                .bind(&created_at_str)
-                .execute(&self.pool)
-                .await?;
+        let result = sqlx::query("DELETE FROM search_engines WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
 
         if result.rows_affected() == 0 {
-            tracing::error!("Failed to create feedback timestamp");
-            return Err(anyhow::anyhow!(
-                "Failed to create feedback timestamp: database error"
-            ));
+            tracing::info!("No search engine found to delete with ID: {}", id);
+            return Err(anyhow::anyhow!("404"));
         }
 
-        tracing::info!("Successfully created feedback timestamp");
+        tracing::info!("Successfully deleted search engine: {}", id);
         Ok(())
     }
 
-    pub async fn check_feedback_timestamp(&self, user_id: &str) -> Result<bool> {
-        tracing::info!("Checking feedback timestamp for user: {}", user_id);
+    pub async fn get_bangs(&self) -> Result<Vec<Bang>> {
+        let bangs = sqlx::query_as::<_, Bang>("SELECT * FROM bangs ORDER BY keyword")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(bangs)
+    }
 
-        let timestamp = sqlx::query("SELECT created_at FROM feedback_timestamps WHERE user_id = ?")
+    /// URL template for a bang keyword, the user's own engines before the
+    /// built-in catalog
+    pub async fn find_bang(&self, user_id: Option<&str>, keyword: &str) -> Result<Option<String>> {
+        if let Some(user_id) = user_id {
+            let url = sqlx::query_scalar::<_, String>(
+                "SELECT url FROM search_engines WHERE user_id = ? AND keyword = ?",
+            )
             .bind(user_id)
+            .bind(keyword)
             .fetch_optional(&self.pool)
             .await?;
+            if url.is_some() {
+                return Ok(url);
+            }
+        }
 
-        if let Some(record) = timestamp {
-            let created_at: String = record.try_get("created_at")?;
-            tracing::info!("Found feedback timestamp: {}", created_at);
+        let url = sqlx::query_scalar::<_, String>("SELECT url FROM bangs WHERE keyword = ?")
+            .bind(keyword)
+            .fetch_optional(&self.pool)
+            .await?;
 
-            let timestamp = chrono::DateTime::parse_from_rfc3339(&created_at)?;
-            if chrono::Utc::now().signed_duration_since(timestamp) < chrono::Duration::hours(24) {
-                return Ok(false);
-            }
+        Ok(url)
+    }
 
-            // Delete the record if it's older than 24 hours
-            let result = sqlx::query("DELETE FROM feedback_timestamps WHERE user_id = ?")
-                .bind(user_id)
-                .execute(&self.pool)
-                .await?;
+    // Search history
+
+    /// Remember a search, counting repeats of the same normalized query
+    pub async fn record_search(&self, user_id: &str, query: &str) -> Result<SearchHistoryEntry> {
+        let now = Utc::now().to_rfc3339();
+        let entry = sqlx::query_as::<_, SearchHistoryEntry>(
+            "INSERT INTO search_history
+                (id, user_id, query, normalized_query, searches, last_searched_at, created_at)
+             VALUES (?, ?, ?, ?, 1, ?, ?)
+             ON CONFLICT(user_id, normalized_query) DO UPDATE SET
+                query = excluded.query,
+                searches = searches + 1,
+                last_searched_at = excluded.last_searched_at
+             RETURNING *",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(user_id)
+        .bind(query.trim())
+        .bind(suggestions::normalize_query(query))
+        .bind(&now)
+        .bind(&now)
+        .fetch_one(&self.pool)
+        .await?;
 
-            tracing::info!(
-                "Deleted old feedback timestamp, rows affected: {}",
-                result.rows_affected()
-            );
+        Ok(entry)
+    }
+
+    /// The user's best `limit` searches starting with `prefix`, a normalized
+    /// query, that are within their retention period (their
+    /// `search_history_days` setting or `default_retention_days`). Ranked by
+    /// frecency like Firefox's: the number of searches weighted by how many
+    /// days ago the last one was, recent ones winning ties.
+    pub async fn get_search_history(
+        &self,
+        user_id: &str,
+        prefix: &str,
+        default_retention_days: i64,
+        limit: i64,
+    ) -> Result<Vec<SearchHistoryEntry>> {
+        let pattern = format!(
+            "{}%",
+            prefix
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let entries = sqlx::query_as::<_, SearchHistoryEntry>(
+            "SELECT *,
+                MAX(searches, 1) * CASE
+                    WHEN age <= 4 THEN 100
+                    WHEN age <= 14 THEN 70
+                    WHEN age <= 31 THEN 50
+                    WHEN age <= 90 THEN 30
+                    ELSE 10
+                END AS score
+             FROM (
+                SELECT *, CAST(julianday('now') - julianday(last_searched_at) AS INTEGER) AS age
+                FROM search_history
+                WHERE user_id = ?1 AND normalized_query LIKE ?2 ESCAPE '\\'
+                  AND last_searched_at >= strftime('%Y-%m-%dT%H:%M:%S', 'now', printf('-%d days', COALESCE(
+                      (SELECT json_extract(settings_blob, '$.search_history_days')
+                       FROM user_settings WHERE user_id = ?1), ?3)))
+             )
+             ORDER BY score DESC, last_searched_at DESC
+             LIMIT ?4",
+        )
+        .bind(user_id)
+        .bind(pattern)
+        .bind(default_retention_days)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Delete searches older than their user's retention period, only one
+    /// user's when `user_id` is given
+    pub async fn prune_search_history(
+        &self,
+        user_id: Option<&str>,
+        default_retention_days: i64,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM search_history
+             WHERE (?1 IS NULL OR user_id = ?1)
+               AND last_searched_at < strftime('%Y-%m-%dT%H:%M:%S', 'now', printf('-%d days', COALESCE(
+                   (SELECT json_extract(settings_blob, '$.search_history_days') FROM user_settings
+                    WHERE user_settings.user_id = search_history.user_id), ?2)))",
+        )
+        .bind(user_id)
+        .bind(default_retention_days)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            tracing::info!("Pruned {} old searches", result.rows_affected());
         }
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_search_history_entry(&self, id: &str, user_id: &str) -> Result<()> {
+        tracing::info!("Deleting search history entry: {}", id);
+
+        let result = sqlx::query("DELETE FROM search_history WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
 
-        Ok(true)
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+        Ok(())
     }
 
-    // User Settings
-    pub async fn get_user_settings(&self, user_id: &str) -> Result<UserSettings> {
-        tracing::info!("Fetching settings for user: {}", user_id);
+    pub async fn clear_search_history(&self, user_id: &str) -> Result<u64> {
+        tracing::info!("Clearing search history for user: {}", user_id);
 
-        let settings =
-            sqlx::query_as::<_, UserSettings>("SELECT * FROM user_settings WHERE user_id = ?")
-                .bind(user_id)
-                .fetch_optional(&self.pool)
-                .await?;
+        let result = sqlx::query("DELETE FROM search_history WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
 
-        match settings {
-            Some(settings) => {
-                tracing::info!("Successfully fetched user settings");
-                Ok(settings)
-            }
+        Ok(result.rows_affected())
+    }
+
+    // Dashboards
+    pub async fn get_dashboards(&self, user_id: &str) -> Result<Vec<Dashboard>> {
+        tracing::info!("Fetching dashboards for user: {}", user_id);
+
+        let dashboards = sqlx::query_as::<_, Dashboard>(
+            "SELECT d.*, d.id IS u.active_dashboard_id AS active FROM dashboards d
+             JOIN users u ON u.id = d.user_id
+             WHERE d.user_id = ?
+             ORDER BY d.created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully fetched {} dashboards", dashboards.len());
+        Ok(dashboards)
+    }
+
+    /// Id of the user a dashboard belongs to
+    pub async fn get_dashboard_owner(&self, id: &str) -> Result<String> {
+        sqlx::query_scalar("SELECT user_id FROM dashboards WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("404"))
+    }
+
+    pub async fn get_dashboard(&self, id: &str, user_id: &str) -> Result<Dashboard> {
+        tracing::info!("Fetching dashboard: {} for user: {}", id, user_id);
+
+        let dashboard = sqlx::query_as::<_, Dashboard>(
+            "SELECT d.*, d.id IS u.active_dashboard_id AS active FROM dashboards d
+             JOIN users u ON u.id = d.user_id
+             WHERE d.id = ? AND d.user_id = ?",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match dashboard {
+            Some(dashboard) => Ok(dashboard),
             None => {
-                tracing::info!("No settings found for user: {}", user_id);
+                tracing::info!("Dashboard not found: {}", id);
                 Err(anyhow::anyhow!("404"))
             }
         }
     }
 
-    pub async fn create_user_settings(&self, user_settings: UserSettings) -> Result<UserSettings> {
-        tracing::info!("Creating settings for user: {}", user_settings.user_id);
+    /// The dashboard the user is looking at, see `active_dashboard`
+    pub async fn get_active_dashboard(&self, user_id: &str) -> Result<Dashboard> {
+        tracing::info!("Fetching active dashboard for user: {}", user_id);
+
+        let mut tx = self.pool.begin().await?;
+        let dashboard = active_dashboard(&mut tx, user_id).await?;
+        tx.commit().await?;
+
+        Ok(dashboard)
+    }
+
+    /// Create an empty dashboard, fails with "409" if the user already has one by that name
+    pub async fn create_dashboard(&self, dashboard: Dashboard) -> Result<Dashboard> {
+        tracing::info!(
+            "Creating dashboard for user {}: {}",
+            dashboard.user_id,
+            dashboard.name
+        );
 
         let result = sqlx::query(
-            "INSERT INTO user_settings (user_id, settings_blob, created_at) VALUES (?, ?, ?)",
+            "INSERT OR IGNORE INTO dashboards (id, user_id, name, settings_overrides, created_at)
+             VALUES (?, ?, ?, ?, ?)",
         )
-        .bind(&user_settings.user_id)
-        .bind(&user_settings.settings_blob)
-        .bind(&user_settings.created_at)
+        .bind(&dashboard.id)
+        .bind(&dashboard.user_id)
+        .bind(&dashboard.name)
+        .bind(&dashboard.settings_overrides)
+        .bind(&dashboard.created_at)
         .execute(&self.pool)
         .await?;
 
         if result.rows_affected() == 0 {
-            tracing::error!("Failed to create user settings");
-            return Err(anyhow::anyhow!("500"));
+            tracing::info!("Dashboard already exists: {}", dashboard.name);
+            return Err(anyhow::anyhow!("409"));
         }
 
-        tracing::info!("Successfully created user settings");
-        Ok(user_settings)
+        tracing::info!("Successfully created dashboard: {}", dashboard.id);
+        Ok(dashboard)
     }
 
-    pub async fn update_user_settings(
-        &self,
-        user_id: &str,
-        updates: HashMap<String, serde_json::Value>,
-    ) -> Result<()> {
-        tracing::info!("Updating settings for user: {}", user_id);
+    /// Save a dashboard's name and settings overrides, fails with "409" if the name is taken
+    pub async fn update_dashboard(&self, dashboard: &Dashboard) -> Result<()> {
+        tracing::info!("Updating dashboard: {}", dashboard.id);
 
-        // Since user_settings typically only has one JSON blob column, simplify the update
-        if let Some(settings_blob) = updates.get("settings_blob") {
-            let settings_str = settings_blob.to_string();
-            let result =
-                sqlx::query("UPDATE user_settings SET settings_blob = ? WHERE user_id = ?")
-                    .bind(&settings_str)
-                    .bind(user_id)
-                    .execute(&self.pool)
-                    .await?;
+        let conflict =
+            sqlx::query("SELECT id FROM dashboards WHERE user_id = ? AND name = ? AND id != ?")
+                .bind(&dashboard.user_id)
+                .bind(&dashboard.name)
+                .bind(&dashboard.id)
+                .fetch_optional(&self.pool)
+                .await?;
 
-            if result.rows_affected() == 0 {
-                return Err(anyhow::anyhow!("User settings not found or update failed"));
-            }
-        } else {
-            return Err(anyhow::anyhow!("No settings_blob provided for update"));
+        if conflict.is_some() {
+            tracing::info!("Another dashboard is already named: {}", dashboard.name);
+            return Err(anyhow::anyhow!("409"));
         }
 
-        tracing::info!("Successfully updated user settings");
+        let result = sqlx::query(
+            "UPDATE dashboards SET name = ?, settings_overrides = ? WHERE id = ? AND user_id = ?",
+        )
+        .bind(&dashboard.name)
+        .bind(&dashboard.settings_overrides)
+        .bind(&dashboard.id)
+        .bind(&dashboard.user_id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        tracing::info!("Successfully updated dashboard: {}", dashboard.id);
         Ok(())
     }
 
-    pub async fn delete_user_settings(&self, user_id: &str) -> Result<()> {
-        tracing::info!("Deleting settings for user: {}", user_id);
+    pub async fn set_active_dashboard(&self, user_id: &str, dashboard_id: &str) -> Result<()> {
+        tracing::info!("Switching user {} to dashboard: {}", user_id, dashboard_id);
 
-        let result = sqlx::query("DELETE FROM user_settings WHERE user_id = ?")
-            .bind(user_id)
-            .execute(&self.pool)
-            .await?;
+        let result = sqlx::query(
+            "UPDATE users SET active_dashboard_id = ?1
+             WHERE id = ?2 AND EXISTS (SELECT 1 FROM dashboards WHERE id = ?1 AND user_id = ?2)",
+        )
+        .bind(dashboard_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
 
         if result.rows_affected() == 0 {
-            tracing::info!("No user settings found to delete");
-            return Err(anyhow::anyhow!("User settings not found"));
+            tracing::info!("Dashboard not found: {}", dashboard_id);
+            return Err(anyhow::anyhow!("404"));
         }
 
-        tracing::info!("Successfully deleted user settings");
+        tracing::info!("Successfully switched dashboard");
         Ok(())
     }
 
-    pub async fn get_user_data(&self, user_id: &str) -> Result<UserData> {
-        let rows = sqlx::query(
-            "
-        SELECT
-            u.*,
-            l.id as link_id,
-            l.title as link_title,
-            l.url as link_url,
-            l.icon as link_icon,
-            l.order_index as link_order_index,
-            l.owner_type as link_owner_type,
-            l.owner_id as link_owner_id,
-            l.created_at as link_created_at,
-            l.description as link_description,
-            l.column_type as link_column_type,
-            s.id as subscription_id,
-            s.entity_id as subscription_entity_id,
-            s.entity_type as subscription_entity_type,
-            s.plan_id as subscription_plan_id,
-            s.status as subscription_status,
-            s.current_period_end as subscription_current_period_end,
-            s.created_at as subscription_created_at,
-            us.settings_blob as settings_blob,
-            us.created_at as settings_created_at
-            FROM users u
-            LEFT JOIN links l ON u.id = l.owner_id
-            LEFT JOIN subscriptions s ON u.id = s.entity_id
-            LEFT JOIN user_settings us ON u.id = us.user_id
-            WHERE u.id = ?
-            ORDER BY l.order_index ASC;",
+    /// Copy a dashboard with its columns, links and their tags into a new
+    /// dashboard called `name`. Fails with "409" if the name is taken.
+    pub async fn clone_dashboard(&self, id: &str, user_id: &str, name: &str) -> Result<Dashboard> {
+        tracing::info!("Cloning dashboard {} as: {}", id, name);
+
+        let source = self.get_dashboard(id, user_id).await?;
+        let columns = self.get_columns(&source.id, "dashboard").await?;
+        let links = self
+            .get_links(user_id, "user", Some(&source.id), &[])
+            .await?;
+
+        let dashboard = Dashboard {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            active: false,
+            ..source
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO dashboards (id, user_id, name, settings_overrides, created_at)
+             VALUES (?, ?, ?, ?, ?)",
         )
-        .bind(user_id)
-        .fetch_all(&self.pool)
+        .bind(&dashboard.id)
+        .bind(&dashboard.user_id)
+        .bind(&dashboard.name)
+        .bind(&dashboard.settings_overrides)
+        .bind(&dashboard.created_at)
+        .execute(&mut *tx)
         .await?;
 
-        if rows.is_empty() {
-            return Err(anyhow::anyhow!("User not found"));
+        if result.rows_affected() == 0 {
+            tracing::info!("Dashboard already exists: {}", dashboard.name);
+            return Err(anyhow::anyhow!("409"));
         }
 
-        let first_row = &rows[0];
-        let user = User {
-            id: first_row.get("id"),
-            email: first_row.get("email"),
-            created_at: first_row.get("created_at"),
-            auth_token: None,
-            password_hash: first_row.get::<String, _>("password_hash"),
-        };
+        let mut column_ids = HashMap::new();
+        for column in columns {
+            let source_id = column.id.clone();
+            let copy = append_column(
+                &mut tx,
+                &Column {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    owner_id: dashboard.id.clone(),
+                    created_at: Utc::now().to_rfc3339(),
+                    ..column
+                },
+            )
+            .await?;
+            column_ids.insert(source_id, copy.id);
+        }
 
-        let settings_blob: Option<String> = first_row.try_get("settings_blob").ok();
-        let settings_created_at: Option<String> = first_row.try_get("settings_created_at").ok();
-        let settings = if let (Some(blob), Some(created)) = (settings_blob, settings_created_at) {
-            Some(UserSettings {
-                user_id: first_row.get("id"),
-                settings_blob: blob,
-                created_at: created,
-            })
-        } else {
-            None
-        };
+        for link in links {
+            let Some(column_id) = link.column_id.as_ref().and_then(|id| column_ids.get(id)) else {
+                continue;
+            };
+            insert_link(
+                &mut tx,
+                &Link {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    column_id: Some(column_id.clone()),
+                    created_at: Utc::now().to_rfc3339(),
+                    ..link
+                },
+            )
+            .await?;
+        }
 
-        let subscription = if let Ok(sub_id) = first_row.try_get::<String, _>("subscription_id") {
-            Some(Subscription {
-                id: sub_id,
-                entity_id: first_row
-                    .try_get::<String, _>("subscription_entity_id")
-                    .unwrap_or_default(),
-                entity_type: first_row
-                    .try_get::<String, _>("subscription_entity_type")
-                    .unwrap_or_default(),
-                plan_id: first_row
-                    .try_get::<String, _>("subscription_plan_id")
-                    .unwrap_or_default(),
-                status: first_row
-                    .try_get::<String, _>("subscription_status")
-                    .unwrap_or_default(),
-                current_period_end: first_row
-                    .try_get::<String, _>("subscription_current_period_end")
-                    .unwrap_or_default(),
-                created_at: first_row
-                    .try_get::<String, _>("subscription_created_at")
-                    .unwrap_or_default(),
-            })
-        } else {
-            None
-        };
-
-        let links = rows
-            .iter()
-            .filter_map(|row| {
-                if let Ok(link_id) = row.try_get::<String, _>("link_id") {
-                    Some(Link {
-                        id: link_id,
-                        title: row.try_get("link_title").unwrap_or_default(),
-                        url: row.try_get("link_url").unwrap_or_default(),
-                        icon: row.try_get("link_icon").ok(),
-                        order_index: row.try_get("link_order_index").unwrap_or(0),
-                        owner_type: row.try_get("link_owner_type").unwrap_or_default(),
-                        owner_id: row.try_get("link_owner_id").unwrap_or_default(),
-                        created_at: row.try_get("link_created_at").unwrap_or_default(),
-                        description: row.try_get("link_description").ok(),
-                        column_type: row.try_get("link_column_type").unwrap_or_default(),
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<Link>>();
-
-        Ok(UserData {
-            user,
-            links,
-            subscription,
-            settings,
-        })
-    }
+        tx.commit().await?;
 
-    // Organization and Team functions (reimplemented from PL/pgSQL)
-    pub async fn create_organization(
-        &self,
-        org_name: &str,
-        owner_id: &str,
-        plan_id: &str,
-    ) -> Result<String> {
         tracing::info!(
-            "Creating organization: {} for owner: {}",
-            org_name,
-            owner_id
+            "Successfully cloned dashboard {} into: {}",
+            id,
+            dashboard.id
         );
+        Ok(dashboard)
+    }
+
+    /// Delete a dashboard along with its columns and their links. The user's
+    /// last dashboard can't be deleted, that fails with "409".
+    pub async fn delete_dashboard(&self, id: &str, user_id: &str) -> Result<()> {
+        tracing::info!("Deleting dashboard: {}", id);
 
-        // Start a transaction
         let mut tx = self.pool.begin().await?;
 
-        let org_id = uuid::Uuid::new_v4().to_string();
-        let created_at = Utc::now().to_rfc3339();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM dashboards WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
 
-        // Create the organization
+        // Links go with their columns (ON DELETE CASCADE)
         sqlx::query(
-            "INSERT INTO organizations (id, name, owner_id, created_at) VALUES (?, ?, ?, ?)",
+            "DELETE FROM columns WHERE owner_type = 'dashboard' AND owner_id = ?1
+             AND EXISTS (SELECT 1 FROM dashboards WHERE id = ?1 AND user_id = ?2)",
         )
-        .bind(&org_id)
-        .bind(org_name)
-        .bind(owner_id)
-        .bind(&created_at)
+        .bind(id)
+        .bind(user_id)
         .execute(&mut *tx)
         .await?;
 
-        // Create subscription for the organization
-        let sub_id = uuid::Uuid::new_v4().to_string();
-        sqlx::query(
-            "INSERT INTO subscriptions (id, entity_id, entity_type, plan_id, status, created_at) VALUES (?, ?, 'organization', ?, 'active', ?)"
+        let result = sqlx::query("DELETE FROM dashboards WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::info!("No dashboard found to delete with ID: {}", id);
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        if count <= 1 {
+            tracing::info!("Refusing to delete the last dashboard of user: {}", user_id);
+            return Err(anyhow::anyhow!("409"));
+        }
+
+        tx.commit().await?;
+
+        tracing::info!("Successfully deleted dashboard: {}", id);
+        Ok(())
+    }
+
+    // Columns
+    pub async fn get_columns(&self, owner_id: &str, owner_type: &str) -> Result<Vec<Column>> {
+        tracing::info!("Fetching columns for owner {}: {}", owner_type, owner_id);
+
+        let columns = sqlx::query_as::<_, Column>(
+            "SELECT * FROM columns WHERE owner_id = ? AND owner_type = ? ORDER BY position",
         )
-        .bind(&sub_id)
-        .bind(&org_id)
-        .bind(plan_id)
-        .bind(&created_at)
-        .execute(&mut *tx)
+        .bind(owner_id)
+        .bind(owner_type)
+        .fetch_all(&self.pool)
         .await?;
 
-        // Add owner as admin member
-        sqlx::query(
-            "INSERT INTO user_memberships (user_id, entity_id, entity_type, role, created_at) VALUES (?, ?, 'organization', 'admin', ?)"
+        tracing::info!("Successfully fetched {} columns", columns.len());
+        Ok(columns)
+    }
+
+    pub async fn get_column(&self, id: &str, owner_id: &str, owner_type: &str) -> Result<Column> {
+        tracing::info!("Fetching column: {} for owner: {}", id, owner_id);
+
+        let mut conn = self.pool.acquire().await?;
+        owned_column(&mut conn, id, owner_id, owner_type).await
+    }
+
+    /// Fetch a column whoever owns it, callers check access
+    pub async fn get_column_by_id(&self, id: &str) -> Result<Column> {
+        sqlx::query_as::<_, Column>("SELECT * FROM columns WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("404"))
+    }
+
+    /// Add a column after the owner's existing ones, fails with "409" if the
+    /// owner already has a column by that name
+    pub async fn create_column(&self, column: Column) -> Result<Column> {
+        tracing::info!(
+            "Creating column for owner {}: {}",
+            column.owner_id,
+            column.name
+        );
+
+        let mut tx = self.pool.begin().await?;
+
+        if find_column(&mut tx, &column.owner_id, &column.owner_type, &column.name)
+            .await?
+            .is_some()
+        {
+            tracing::info!("Column already exists: {}", column.name);
+            return Err(anyhow::anyhow!("409"));
+        }
+
+        let column = append_column(&mut tx, &column).await?;
+        tx.commit().await?;
+
+        tracing::info!("Successfully created column: {}", column.id);
+        Ok(column)
+    }
+
+    /// Save a column's name, color and collapsed state. Renames carry over to
+    /// the links' column_type. Fails with "409" if the new name is taken.
+    pub async fn update_column(&self, column: &Column) -> Result<()> {
+        tracing::info!("Updating column: {}", column.id);
+
+        let mut tx = self.pool.begin().await?;
+
+        let conflict = find_column(&mut tx, &column.owner_id, &column.owner_type, &column.name)
+            .await?
+            .is_some_and(|c| c.id != column.id);
+        if conflict {
+            tracing::info!("Another column is already named: {}", column.name);
+            return Err(anyhow::anyhow!("409"));
+        }
+
+        let result = sqlx::query(
+            "UPDATE columns SET name = ?, color = ?, collapsed = ?
+             WHERE id = ? AND owner_id = ? AND owner_type = ?",
         )
-        .bind(owner_id)
-        .bind(&org_id)
-        .bind(&created_at)
+        .bind(&column.name)
+        .bind(&column.color)
+        .bind(column.collapsed)
+        .bind(&column.id)
+        .bind(&column.owner_id)
+        .bind(&column.owner_type)
         .execute(&mut *tx)
         .await?;
 
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        sqlx::query("UPDATE links SET column_type = ? WHERE column_id = ? AND column_type != ?")
+            .bind(&column.name)
+            .bind(&column.id)
+            .bind(&column.name)
+            .execute(&mut *tx)
+            .await?;
+
         tx.commit().await?;
 
-        tracing::info!("Successfully created organization: {}", org_id);
-        Ok(org_id)
+        tracing::info!("Successfully updated column: {}", column.id);
+        Ok(())
     }
 
-    pub async fn create_team(
+    /// Delete a column. Its links move to the end of `move_to`, which is
+    /// needed unless the column is empty. Fails with "409" when the column
+    /// still has links and there's nowhere to move them.
+    pub async fn delete_column(
         &self,
-        team_name: &str,
+        id: &str,
         owner_id: &str,
-        plan_id: &str,
-        org_id: Option<&str>,
-    ) -> Result<String> {
-        tracing::info!("Creating team: {} for owner: {}", team_name, owner_id);
+        owner_type: &str,
+        move_to: Option<&str>,
+    ) -> Result<()> {
+        tracing::info!("Deleting column: {} (moving links to: {:?})", id, move_to);
 
-        // Start a transaction
         let mut tx = self.pool.begin().await?;
 
-        let team_id = uuid::Uuid::new_v4().to_string();
-        let created_at = Utc::now().to_rfc3339();
+        // Fails with "404" unless the column belongs to the owner
+        owned_column(&mut tx, id, owner_id, owner_type).await?;
 
-        // Create the team
-        sqlx::query("INSERT INTO teams (id, name, owner_id, organization_id, created_at) VALUES (?, ?, ?, ?, ?)")
-            .bind(&team_id)
-            .bind(team_name)
-            .bind(owner_id)
-            .bind(org_id)
-            .bind(&created_at)
+        match move_to.filter(|target| *target != id) {
+            Some(move_to) => {
+                let target = owned_column(&mut tx, move_to, owner_id, owner_type).await?;
+
+                let offset: i32 = sqlx::query_scalar(
+                    "SELECT COALESCE(MAX(order_index) + 1, 0) FROM links WHERE column_id = ?",
+                )
+                .bind(&target.id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                let result = sqlx::query(
+                    "UPDATE links SET column_id = ?, column_type = ?, order_index = order_index + ?
+                     WHERE column_id = ?",
+                )
+                .bind(&target.id)
+                .bind(&target.name)
+                .bind(offset)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+                tracing::info!(
+                    "Moved {} links to column: {}",
+                    result.rows_affected(),
+                    target.id
+                );
+            }
+            None => {
+                // Links would go with the column (ON DELETE CASCADE), never
+                // delete them without asking
+                let links: i64 =
+                    sqlx::query_scalar("SELECT COUNT(*) FROM links WHERE column_id = ?")
+                        .bind(id)
+                        .fetch_one(&mut *tx)
+                        .await?;
+                if links > 0 {
+                    tracing::info!("Column {} still has {} links", id, links);
+                    return Err(anyhow::anyhow!("409"));
+                }
+            }
+        }
+
+        sqlx::query("DELETE FROM columns WHERE id = ?")
+            .bind(id)
             .execute(&mut *tx)
             .await?;
 
-        // Create subscription for the team
-        let sub_id = uuid::Uuid::new_v4().to_string();
-        sqlx::query(
-            "INSERT INTO subscriptions (id, entity_id, entity_type, plan_id, status, created_at) VALUES (?, ?, 'team', ?, 'active', ?)"
+        tx.commit().await?;
+
+        tracing::info!("Successfully deleted column: {}", id);
+        Ok(())
+    }
+
+    /// Put the given columns first, in that order. Columns left out keep
+    /// their relative order after them. Fails with "404" on unknown ids.
+    pub async fn reorder_columns(
+        &self,
+        owner_id: &str,
+        owner_type: &str,
+        column_ids: &[String],
+    ) -> Result<Vec<Column>> {
+        tracing::info!(
+            "Reordering {} columns for owner: {}",
+            column_ids.len(),
+            owner_id
+        );
+
+        let mut tx = self.pool.begin().await?;
+
+        let current: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM columns WHERE owner_id = ? AND owner_type = ? ORDER BY position",
         )
-        .bind(&sub_id)
-        .bind(&team_id)
-        .bind(plan_id)
-        .bind(&created_at)
-        .execute(&mut *tx)
+        .bind(owner_id)
+        .bind(owner_type)
+        .fetch_all(&mut *tx)
         .await?;
 
-        // Add owner as admin member
-        sqlx::query(
-            "INSERT INTO user_memberships (user_id, entity_id, entity_type, role, created_at) VALUES (?, ?, 'team', 'admin', ?)"
+        if column_ids.iter().any(|id| !current.contains(id)) {
+            tracing::info!("Reorder references unknown columns");
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        let mut seen = HashSet::new();
+        let order = column_ids
+            .iter()
+            .chain(current.iter())
+            .filter(|id| seen.insert(id.as_str()));
+
+        for (position, id) in order.enumerate() {
+            sqlx::query("UPDATE columns SET position = ? WHERE id = ?")
+                .bind(position as i32)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        tracing::info!("Successfully reordered columns for owner: {}", owner_id);
+        self.get_columns(owner_id, owner_type).await
+    }
+
+    // Tags
+    /// Tag names for every link an owner has, keyed by link id
+    pub async fn get_link_tags(
+        &self,
+        owner_id: &str,
+        owner_type: &str,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        let rows = sqlx::query(
+            "SELECT lt.link_id, t.name FROM link_tags lt
+             JOIN tags t ON t.id = lt.tag_id
+             WHERE t.owner_id = ? AND t.owner_type = ?
+             ORDER BY t.name",
         )
         .bind(owner_id)
-        .bind(&team_id)
-        .bind(&created_at)
-        .execute(&mut *tx)
+        .bind(owner_type)
+        .fetch_all(&self.pool)
         .await?;
 
-        tx.commit().await?;
+        let mut link_tags: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            link_tags
+                .entry(row.get("link_id"))
+                .or_default()
+                .push(row.get("name"));
+        }
+        Ok(link_tags)
+    }
 
-        tracing::info!("Successfully created team: {}", team_id);
-        Ok(team_id)
+    async fn attach_tags(
+        &self,
+        owner_id: &str,
+        owner_type: &str,
+        links: &mut [Link],
+    ) -> Result<()> {
+        let mut link_tags = self.get_link_tags(owner_id, owner_type).await?;
+        for link in links {
+            link.tags = link_tags.remove(&link.id).unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    pub async fn get_tags(&self, owner_id: &str, owner_type: &str) -> Result<Vec<Tag>> {
+        tracing::info!("Fetching tags for owner {}: {}", owner_type, owner_id);
+
+        let tags = sqlx::query_as::<_, Tag>(
+            "SELECT t.*, COUNT(lt.link_id) AS link_count FROM tags t
+             LEFT JOIN link_tags lt ON lt.tag_id = t.id
+             WHERE t.owner_id = ? AND t.owner_type = ?
+             GROUP BY t.id
+             ORDER BY t.name",
+        )
+        .bind(owner_id)
+        .bind(owner_type)
+        .fetch_all(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully fetched {} tags", tags.len());
+        Ok(tags)
+    }
+
+    pub async fn get_tag(&self, id: &str, owner_id: &str, owner_type: &str) -> Result<Tag> {
+        tracing::info!("Fetching tag: {} for owner: {}", id, owner_id);
+
+        let tag = sqlx::query_as::<_, Tag>(
+            "SELECT t.*, COUNT(lt.link_id) AS link_count FROM tags t
+             LEFT JOIN link_tags lt ON lt.tag_id = t.id
+             WHERE t.id = ? AND t.owner_id = ? AND t.owner_type = ?
+             GROUP BY t.id",
+        )
+        .bind(id)
+        .bind(owner_id)
+        .bind(owner_type)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match tag {
+            Some(tag) => Ok(tag),
+            None => {
+                tracing::info!("Tag not found: {}", id);
+                Err(anyhow::anyhow!("404"))
+            }
+        }
+    }
+
+    /// Create a tag, fails with "409" if the owner already has one by that name
+    pub async fn create_tag(&self, tag: Tag) -> Result<Tag> {
+        tracing::info!("Creating tag for owner {}: {}", tag.owner_id, tag.name);
+
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO tags (id, name, owner_id, owner_type, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&tag.id)
+        .bind(&tag.name)
+        .bind(&tag.owner_id)
+        .bind(&tag.owner_type)
+        .bind(&tag.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::info!("Tag already exists: {}", tag.name);
+            return Err(anyhow::anyhow!("409"));
+        }
+
+        tracing::info!("Successfully created tag: {}", tag.id);
+        Ok(tag)
+    }
+
+    /// Rename a tag, fails with "409" if another of the owner's tags has the new name
+    pub async fn rename_tag(
+        &self,
+        id: &str,
+        owner_id: &str,
+        owner_type: &str,
+        name: &str,
+    ) -> Result<()> {
+        tracing::info!("Renaming tag {} to: {}", id, name);
+
+        let conflict = sqlx::query(
+            "SELECT id FROM tags WHERE owner_id = ? AND owner_type = ? AND name = ? AND id != ?",
+        )
+        .bind(owner_id)
+        .bind(owner_type)
+        .bind(name)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if conflict.is_some() {
+            tracing::info!("Another tag is already named: {}", name);
+            return Err(anyhow::anyhow!("409"));
+        }
+
+        let result = sqlx::query(
+            "UPDATE tags SET name = ? WHERE id = ? AND owner_id = ? AND owner_type = ?",
+        )
+        .bind(name)
+        .bind(id)
+        .bind(owner_id)
+        .bind(owner_type)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        tracing::info!("Successfully renamed tag: {}", id);
+        Ok(())
+    }
+
+    /// Move every link from the source tags onto the target tag, then delete the sources
+    pub async fn merge_tags(
+        &self,
+        target_id: &str,
+        source_ids: &[String],
+        owner_id: &str,
+        owner_type: &str,
+    ) -> Result<()> {
+        tracing::info!("Merging tags {:?} into: {}", source_ids, target_id);
+
+        let mut tag_ids: HashSet<&str> = source_ids.iter().map(String::as_str).collect();
+        tag_ids.remove(target_id);
+        let sources: Vec<&str> = tag_ids.iter().copied().collect();
+        tag_ids.insert(target_id);
+
+        let mut tx = self.pool.begin().await?;
+
+        let tag_count = sqlx::query(
+            "SELECT COUNT(*) AS count FROM tags
+             WHERE owner_id = ? AND owner_type = ? AND id IN (SELECT value FROM json_each(?))",
+        )
+        .bind(owner_id)
+        .bind(owner_type)
+        .bind(serde_json::to_string(&tag_ids)?)
+        .fetch_one(&mut *tx)
+        .await?
+        .get::<i64, _>("count");
+
+        // Every tag involved has to exist and belong to the owner
+        if tag_count as usize != tag_ids.len() {
+            tracing::info!("Merge references unknown tags");
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        for source_id in &sources {
+            sqlx::query(
+                "INSERT OR IGNORE INTO link_tags (link_id, tag_id)
+                 SELECT link_id, ? FROM link_tags WHERE tag_id = ?",
+            )
+            .bind(target_id)
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query("DELETE FROM tags WHERE id = ?")
+                .bind(source_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        tracing::info!("Successfully merged {} tags", sources.len());
+        Ok(())
+    }
+
+    pub async fn delete_tag(&self, id: &str, owner_id: &str, owner_type: &str) -> Result<()> {
+        tracing::info!("Deleting tag: {}", id);
+
+        let result =
+            sqlx::query("DELETE FROM tags WHERE id = ? AND owner_id = ? AND owner_type = ?")
+                .bind(id)
+                .bind(owner_id)
+                .bind(owner_type)
+                .execute(&self.pool)
+                .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::info!("No tag found to delete with ID: {}", id);
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        tracing::info!("Successfully deleted tag: {}", id);
+        Ok(())
+    }
+
+    // Link health
+    pub async fn get_links_due_for_check(
+        &self,
+        checked_before: &str,
+        limit: i64,
+    ) -> Result<Vec<Link>> {
+        tracing::info!("Fetching links last checked before: {}", checked_before);
+
+        let links = sqlx::query_as::<_, Link>(
+            "SELECT l.* FROM links l
+             LEFT JOIN link_health h ON h.link_id = l.id
+             WHERE h.checked_at IS NULL OR h.checked_at < ?
+             ORDER BY h.checked_at ASC
+             LIMIT ?",
+        )
+        .bind(checked_before)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        tracing::info!("Found {} links due for a health check", links.len());
+        Ok(links)
+    }
+
+    pub async fn record_link_health(&self, health: &LinkHealth) -> Result<()> {
+        tracing::debug!("Recording health for link: {}", health.link_id);
+
+        sqlx::query(
+            "INSERT INTO link_health (link_id, status_code, final_url, error, checked_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(link_id) DO UPDATE SET
+                status_code = excluded.status_code,
+                final_url = excluded.final_url,
+                error = excluded.error,
+                checked_at = excluded.checked_at",
+        )
+        .bind(&health.link_id)
+        .bind(health.status_code)
+        .bind(&health.final_url)
+        .bind(&health.error)
+        .bind(&health.checked_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_link_health(
+        &self,
+        owner_id: &str,
+        owner_type: &str,
+    ) -> Result<Vec<LinkHealth>> {
+        tracing::info!(
+            "Fetching link health for owner {}: {}",
+            owner_type,
+            owner_id
+        );
+
+        let health = sqlx::query_as::<_, LinkHealth>(
+            "SELECT h.* FROM link_health h
+             JOIN links l ON l.id = h.link_id
+             WHERE l.owner_id = ? AND l.owner_type = ?",
+        )
+        .bind(owner_id)
+        .bind(owner_type)
+        .fetch_all(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully fetched {} link health records", health.len());
+        Ok(health)
+    }
+
+    pub async fn update_link_url(&self, id: &str, url: &str) -> Result<()> {
+        tracing::info!("Updating URL for link: {}", id);
+
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query("UPDATE links SET url = ? WHERE id = ?")
+            .bind(url)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Link not found"));
+        }
+
+        // The old health record describes the old URL, let the checker start over
+        sqlx::query("DELETE FROM link_health WHERE link_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::info!("Successfully updated URL for link: {}", id);
+        Ok(())
+    }
+
+    // Icons
+    /// Store icon bytes and return their hash. Storing the same bytes twice is a no-op.
+    pub async fn store_icon(&self, data: &[u8]) -> Result<String> {
+        if data.len() > icon_store::MAX_ICON_BYTES {
+            return Err(anyhow::anyhow!("Icon too large: {} bytes", data.len()));
+        }
+
+        let content_type = icon_store::sniff_content_type(data)
+            .ok_or_else(|| anyhow::anyhow!("Unrecognized icon format"))?;
+        let hash = icon_store::hash(data);
+
+        sqlx::query(
+            "INSERT OR IGNORE INTO icons (hash, content_type, data, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&hash)
+        .bind(content_type)
+        .bind(data)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        tracing::info!("Stored {} icon: {}", content_type, hash);
+        Ok(hash)
+    }
+
+    pub async fn get_icon(&self, hash: &str) -> Result<Icon> {
+        tracing::debug!("Fetching icon: {}", hash);
+
+        let icon = sqlx::query_as::<_, Icon>("SELECT * FROM icons WHERE hash = ?")
+            .bind(hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match icon {
+            Some(icon) => Ok(icon),
+            None => Err(anyhow::anyhow!("404")),
+        }
+    }
+
+    /// Move `data:` URI icons out of the links table into the icon store
+    pub async fn migrate_inline_icons(&self) -> Result<()> {
+        let rows = sqlx::query("SELECT id, icon FROM links WHERE icon LIKE 'data:%'")
+            .fetch_all(&self.pool)
+            .await?;
+
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        tracing::info!("Migrating {} inline icons to the icon store", rows.len());
+
+        let mut migrated = 0;
+        for row in rows {
+            let id: String = row.try_get("id")?;
+            let icon: String = row.try_get("icon")?;
+
+            // Icons we can't decode or recognize were never displayable, drop them
+            let url = match icon_store::decode_data_uri(&icon) {
+                Some(bytes) => match self.store_icon(&bytes).await {
+                    Ok(hash) => Some(icon_store::icon_url(&hash)),
+                    Err(e) => {
+                        tracing::warn!("Dropping inline icon for link {}: {}", id, e);
+                        None
+                    }
+                },
+                None => {
+                    tracing::warn!("Dropping malformed inline icon for link {}", id);
+                    None
+                }
+            };
+
+            sqlx::query("UPDATE links SET icon = ? WHERE id = ?")
+                .bind(&url)
+                .bind(&id)
+                .execute(&self.pool)
+                .await?;
+            migrated += 1;
+        }
+
+        tracing::info!("Successfully migrated {} inline icons", migrated);
+        Ok(())
+    }
+
+    // User Memberships
+    pub async fn get_user_memberships(&self, user_id: &str) -> Result<Vec<UserMembership>> {
+        tracing::info!("Fetching memberships for user: {}", user_id);
+
+        let memberships =
+            sqlx::query_as::<_, UserMembership>("SELECT * FROM user_memberships WHERE user_id = ?")
+                .bind(user_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        tracing::info!("Successfully fetched {} memberships", memberships.len());
+        Ok(memberships)
+    }
+
+    pub async fn add_member(&self, membership: UserMembership) -> Result<()> {
+        tracing::info!(
+            "Adding member: {} to entity: {}",
+            membership.user_id,
+            membership.entity_id
+        );
+
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO user_memberships (user_id, entity_id, entity_type, role, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&membership.user_id)
+        .bind(&membership.entity_id)
+        .bind(&membership.entity_type)
+        .bind(&membership.role)
+        .bind(&membership.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::info!("User is already a member");
+            return Err(anyhow::anyhow!("409"));
+        }
+
+        tracing::info!("Successfully added member");
+        Ok(())
+    }
+
+    pub async fn update_member_role(
+        &self,
+        user_id: &str,
+        entity_id: &str,
+        role: &str,
+    ) -> Result<UserMembership> {
+        tracing::info!(
+            "Updating role for user: {} in entity: {}",
+            user_id,
+            entity_id
+        );
+
+        let result =
+            sqlx::query("UPDATE user_memberships SET role = ? WHERE user_id = ? AND entity_id = ?")
+                .bind(role)
+                .bind(user_id)
+                .bind(entity_id)
+                .execute(&self.pool)
+                .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        // Fetch and return the updated membership
+        let membership = sqlx::query_as::<_, UserMembership>(
+            "SELECT * FROM user_memberships WHERE user_id = ? AND entity_id = ?",
+        )
+        .bind(user_id)
+        .bind(entity_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully updated member role");
+        Ok(membership)
+    }
+
+    pub async fn remove_member(&self, user_id: &str, entity_id: &str) -> Result<()> {
+        tracing::info!("Removing member: {} from entity: {}", user_id, entity_id);
+
+        let result =
+            sqlx::query("DELETE FROM user_memberships WHERE user_id = ? AND entity_id = ?")
+                .bind(user_id)
+                .bind(entity_id)
+                .execute(&self.pool)
+                .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::info!("No membership found to delete");
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        tracing::info!("Successfully removed member");
+        Ok(())
+    }
+
+    // Plans
+    pub async fn get_plans(&self) -> Result<Vec<Plan>> {
+        tracing::info!("Fetching all plans");
+
+        let plans = sqlx::query_as::<_, Plan>("SELECT * FROM plans")
+            .fetch_all(&self.pool)
+            .await?;
+
+        tracing::info!("Successfully fetched {} plans", plans.len());
+        Ok(plans)
+    }
+
+    pub async fn get_plan(&self, id: &str) -> Result<Plan> {
+        tracing::info!("Fetching plan: {}", id);
+
+        let plan = sqlx::query_as::<_, Plan>("SELECT * FROM plans WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match plan {
+            Some(plan) => {
+                tracing::info!("Successfully fetched plan");
+                Ok(plan)
+            }
+            None => {
+                tracing::info!("Plan not found: {}", id);
+                Err(anyhow::anyhow!("404"))
+            }
+        }
+    }
+
+    // Subscriptions
+    pub async fn get_user_subscription(&self, user_id: &str) -> Result<Subscription> {
+        tracing::info!("Fetching subscription for user: {}", user_id);
+
+        let subscription = sqlx::query_as::<_, Subscription>(
+            "SELECT * FROM subscriptions WHERE entity_id = ? AND entity_type = 'user'",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match subscription {
+            Some(subscription) => {
+                tracing::info!("Successfully fetched subscription for user");
+                println!("Successfully fetched subscription for user");
+                Ok(subscription)
+            }
+            None => {
+                tracing::info!("No subscription found for user: {}", user_id);
+                println!("No subscription found for user: {}", user_id);
+                Err(anyhow::anyhow!("404"))
+            }
+        }
+    }
+
+    pub async fn create_subscription(
+        &self,
+        entity_id: &str,
+        entity_type: &str,
+        plan_id: &str,
+        status: &str,
+        current_period_end: DateTime<Utc>,
+    ) -> Result<Subscription> {
+        let sub_uuid = uuid::Uuid::new_v4().to_string();
+        let created_at = Utc::now().to_rfc3339();
+        let period_end = current_period_end.to_rfc3339();
+
+        let subscription = Subscription {
+            id: sub_uuid.clone(),
+            entity_id: entity_id.to_string(),
+            entity_type: entity_type.to_string(),
+            plan_id: plan_id.to_string(),
+            status: status.to_string(),
+            current_period_end: period_end.clone(),
+            created_at: created_at.clone(),
+        };
+
+        tracing::info!("Creating subscription with payload: {:?}", subscription);
+
+        let result = sqlx::query(
+            "INSERT INTO subscriptions (id, entity_id, entity_type, plan_id, status, current_period_end, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&sub_uuid)
+        .bind(entity_id)
+        .bind(entity_type)
+        .bind(plan_id)
+        .bind(status)
+        .bind(&period_end)
+        .bind(&created_at)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::error!("Failed to create subscription");
+            return Err(anyhow::anyhow!(
+                "Failed to create subscription: database error"
+            ));
+        }
+
+        tracing::info!("Successfully created subscription: {}", subscription.id);
+        Ok(subscription)
+    }
+
+    pub async fn update_subscription(&self, subscription: Subscription) -> Result<()> {
+        tracing::info!("Updating subscription: {}", subscription.id);
+
+        let result = sqlx::query(
+            "UPDATE subscriptions
+            SET entity_id = ?, entity_type = ?, plan_id = ?,
+            status = ?, current_period_end = ?
+            WHERE id = ?",
+        )
+        .bind(&subscription.entity_id)
+        .bind(&subscription.entity_type)
+        .bind(&subscription.plan_id)
+        .bind(&subscription.status)
+        .bind(&subscription.current_period_end)
+        .bind(&subscription.id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Subscription not found or update failed"));
+        }
+
+        tracing::info!("Successfully updated subscription: {}", subscription.id);
+
+        Ok(())
+    }
+
+    // User Settings
+    pub async fn get_user_settings(&self, user_id: &str) -> Result<UserSettings> {
+        tracing::info!("Fetching settings for user: {}", user_id);
+
+        let settings =
+            sqlx::query_as::<_, UserSettings>("SELECT * FROM user_settings WHERE user_id = ?")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        match settings {
+            Some(settings) => {
+                tracing::info!("Successfully fetched user settings");
+                Ok(settings)
+            }
+            None => {
+                tracing::info!("No settings found for user: {}", user_id);
+                Err(anyhow::anyhow!("404"))
+            }
+        }
+    }
+
+    /// The user's settings with their active dashboard's overrides applied
+    pub async fn get_dashboard_settings(&self, user_id: &str) -> Result<UserSettings> {
+        let mut tx = self.pool.begin().await?;
+        let dashboard = active_dashboard(&mut tx, user_id).await?;
+        let settings =
+            sqlx::query_as::<_, UserSettings>("SELECT * FROM user_settings WHERE user_id = ?")
+                .bind(user_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        tx.commit().await?;
+
+        let mut settings = settings.ok_or_else(|| anyhow::anyhow!("404"))?;
+        settings.settings_blob = dashboards::apply_settings_overrides(
+            &settings.settings_blob,
+            &dashboard.settings_overrides,
+        );
+        Ok(settings)
+    }
+
+    /// Save settings edited on the user's active dashboard, the keys it
+    /// overrides go to the dashboard
+    pub async fn save_dashboard_settings(
+        &self,
+        user_id: &str,
+        settings_blob: &serde_json::Value,
+    ) -> Result<()> {
+        tracing::info!("Saving dashboard settings for user: {}", user_id);
+
+        let mut tx = self.pool.begin().await?;
+        let dashboard = active_dashboard(&mut tx, user_id).await?;
+        let current = sqlx::query_scalar::<_, String>(
+            "SELECT settings_blob FROM user_settings WHERE user_id = ?",
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("404"))?;
+
+        let (settings, overrides) = dashboards::split_settings_overrides(
+            settings_blob,
+            &current,
+            &dashboard.settings_overrides,
+        );
+        sqlx::query("UPDATE user_settings SET settings_blob = ? WHERE user_id = ?")
+            .bind(&settings)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE dashboards SET settings_overrides = ? WHERE id = ?")
+            .bind(&overrides)
+            .bind(&dashboard.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn create_user_settings(&self, user_settings: UserSettings) -> Result<UserSettings> {
+        tracing::info!("Creating settings for user: {}", user_settings.user_id);
+
+        let result = sqlx::query(
+            "INSERT INTO user_settings (user_id, settings_blob, created_at) VALUES (?, ?, ?)",
+        )
+        .bind(&user_settings.user_id)
+        .bind(&user_settings.settings_blob)
+        .bind(&user_settings.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::error!("Failed to create user settings");
+            return Err(anyhow::anyhow!("500"));
+        }
+
+        tracing::info!("Successfully created user settings");
+        Ok(user_settings)
+    }
+
+    pub async fn update_user_settings(
+        &self,
+        user_id: &str,
+        updates: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        tracing::info!("Updating settings for user: {}", user_id);
+
+        // Since user_settings typically only has one JSON blob column, simplify the update
+        if let Some(settings_blob) = updates.get("settings_blob") {
+            let settings_str = settings_blob.to_string();
+            let result =
+                sqlx::query("UPDATE user_settings SET settings_blob = ? WHERE user_id = ?")
+                    .bind(&settings_str)
+                    .bind(user_id)
+                    .execute(&self.pool)
+                    .await?;
+
+            if result.rows_affected() == 0 {
+                return Err(anyhow::anyhow!("User settings not found or update failed"));
+            }
+        } else {
+            return Err(anyhow::anyhow!("No settings_blob provided for update"));
+        }
+
+        tracing::info!("Successfully updated user settings");
+        Ok(())
+    }
+
+    pub async fn delete_user_settings(&self, user_id: &str) -> Result<()> {
+        tracing::info!("Deleting settings for user: {}", user_id);
+
+        let result = sqlx::query("DELETE FROM user_settings WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::info!("No user settings found to delete");
+            return Err(anyhow::anyhow!("User settings not found"));
+        }
+
+        tracing::info!("Successfully deleted user settings");
+        Ok(())
+    }
+
+    pub async fn get_user_data(&self, user_id: &str) -> Result<UserData> {
+        let rows = sqlx::query(
+            "
+        SELECT
+            u.*,
+            l.id as link_id,
+            l.title as link_title,
+            l.url as link_url,
+            l.icon as link_icon,
+            l.order_index as link_order_index,
+            l.owner_type as link_owner_type,
+            l.owner_id as link_owner_id,
+            l.created_at as link_created_at,
+            l.description as link_description,
+            l.column_type as link_column_type,
+            l.column_id as link_column_id,
+            l.alias as link_alias,
+            s.id as subscription_id,
+            s.entity_id as subscription_entity_id,
+            s.entity_type as subscription_entity_type,
+            s.plan_id as subscription_plan_id,
+            s.status as subscription_status,
+            s.current_period_end as subscription_current_period_end,
+            s.created_at as subscription_created_at,
+            us.settings_blob as settings_blob,
+            us.created_at as settings_created_at
+            FROM users u
+            LEFT JOIN links l ON u.id = l.owner_id
+            LEFT JOIN subscriptions s ON u.id = s.entity_id
+            LEFT JOIN user_settings us ON u.id = us.user_id
+            WHERE u.id = ?
+            ORDER BY l.order_index ASC;",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        if rows.is_empty() {
+            return Err(anyhow::anyhow!("User not found"));
+        }
+
+        let first_row = &rows[0];
+        let user = User {
+            id: first_row.get("id"),
+            email: first_row.get("email"),
+            created_at: first_row.get("created_at"),
+            email_verified_at: first_row.get("email_verified_at"),
+            auth_token: None,
+            password_hash: first_row.get::<String, _>("password_hash"),
+        };
+
+        let settings_blob: Option<String> = first_row.try_get("settings_blob").ok();
+        let settings_created_at: Option<String> = first_row.try_get("settings_created_at").ok();
+        let settings = if let (Some(blob), Some(created)) = (settings_blob, settings_created_at) {
+            Some(UserSettings {
+                user_id: first_row.get("id"),
+                settings_blob: blob,
+                created_at: created,
+            })
+        } else {
+            None
+        };
+
+        let subscription = if let Ok(sub_id) = first_row.try_get::<String, _>("subscription_id") {
+            Some(Subscription {
+                id: sub_id,
+                entity_id: first_row
+                    .try_get::<String, _>("subscription_entity_id")
+                    .unwrap_or_default(),
+                entity_type: first_row
+                    .try_get::<String, _>("subscription_entity_type")
+                    .unwrap_or_default(),
+                plan_id: first_row
+                    .try_get::<String, _>("subscription_plan_id")
+                    .unwrap_or_default(),
+                status: first_row
+                    .try_get::<String, _>("subscription_status")
+                    .unwrap_or_default(),
+                current_period_end: first_row
+                    .try_get::<String, _>("subscription_current_period_end")
+                    .unwrap_or_default(),
+                created_at: first_row
+                    .try_get::<String, _>("subscription_created_at")
+                    .unwrap_or_default(),
+            })
+        } else {
+            None
+        };
+
+        let mut links = rows
+            .iter()
+            .filter_map(|row| {
+                if let Ok(link_id) = row.try_get::<String, _>("link_id") {
+                    Some(Link {
+                        id: link_id,
+                        title: row.try_get("link_title").unwrap_or_default(),
+                        url: row.try_get("link_url").unwrap_or_default(),
+                        icon: row.try_get("link_icon").ok(),
+                        order_index: row.try_get("link_order_index").unwrap_or(0),
+                        owner_type: row.try_get("link_owner_type").unwrap_or_default(),
+                        owner_id: row.try_get("link_owner_id").unwrap_or_default(),
+                        created_at: row.try_get("link_created_at").unwrap_or_default(),
+                        description: row.try_get("link_description").ok(),
+                        column_type: row.try_get("link_column_type").unwrap_or_default(),
+                        column_id: row.try_get("link_column_id").ok(),
+                        alias: row.try_get("link_alias").unwrap_or_default(),
+                        tags: Vec::new(),
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<Link>>();
+
+        let mut link_tags = self.get_link_tags(user_id, "user").await?;
+        for link in &mut links {
+            link.tags = link_tags.remove(&link.id).unwrap_or_default();
+        }
+
+        let dashboards = self.get_dashboards(user_id).await?;
+        let columns = sqlx::query_as::<_, Column>(
+            "SELECT c.* FROM columns c
+             JOIN dashboards d ON c.owner_type = 'dashboard' AND c.owner_id = d.id
+             WHERE d.user_id = ?
+             ORDER BY d.created_at, c.position",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(UserData {
+            user,
+            dashboards,
+            columns,
+            links,
+            subscription,
+            settings,
+        })
+    }
+
+    /// Write restored dashboards, columns, links and settings for a user in one
+    /// transaction. With `replace` the user's existing dashboards, columns and
+    /// links are removed first. Dashboards and columns the user already has
+    /// (by name) are kept as they are. Links point at archived column ids, which
+    /// are mapped onto the restored columns; links whose column isn't in the
+    /// archive land in the active dashboard by column name.
+    pub async fn restore_user_data(
+        &self,
+        user_id: &str,
+        dashboards: &[Dashboard],
+        columns: &[Column],
+        links: &[Link],
+        settings_blob: Option<&str>,
+        replace: bool,
+    ) -> Result<()> {
+        tracing::info!(
+            "Restoring {} links for user: {} (replace: {})",
+            links.len(),
+            user_id,
+            replace
+        );
+
+        let mut tx = self.pool.begin().await?;
+
+        if replace {
+            let result =
+                sqlx::query("DELETE FROM links WHERE owner_id = ? AND owner_type = 'user'")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+            tracing::info!("Removed {} existing links", result.rows_affected());
+
+            sqlx::query(
+                "DELETE FROM columns WHERE owner_type = 'dashboard'
+                 AND owner_id IN (SELECT id FROM dashboards WHERE user_id = ?)",
+            )
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query("DELETE FROM dashboards WHERE user_id = ?")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let mut dashboard_ids = HashMap::new();
+        for dashboard in dashboards {
+            let existing: Option<String> =
+                sqlx::query_scalar("SELECT id FROM dashboards WHERE user_id = ? AND name = ?")
+                    .bind(user_id)
+                    .bind(&dashboard.name)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    let id = uuid::Uuid::new_v4().to_string();
+                    sqlx::query(
+                        "INSERT INTO dashboards (id, user_id, name, settings_overrides, created_at)
+                         VALUES (?, ?, ?, ?, ?)",
+                    )
+                    .bind(&id)
+                    .bind(user_id)
+                    .bind(&dashboard.name)
+                    .bind(&dashboard.settings_overrides)
+                    .bind(Utc::now().to_rfc3339())
+                    .execute(&mut *tx)
+                    .await?;
+                    id
+                }
+            };
+
+            if replace && dashboard.active {
+                sqlx::query("UPDATE users SET active_dashboard_id = ? WHERE id = ?")
+                    .bind(&id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            dashboard_ids.insert(dashboard.id.clone(), id);
+        }
+
+        let mut columns = columns.to_vec();
+        columns.sort_by_key(|c| c.position);
+        let mut column_ids = HashMap::new();
+        for column in &columns {
+            // Columns from archives before dashboards go into the active dashboard
+            let dashboard_id = match dashboard_ids.get(&column.owner_id) {
+                Some(id) if column.owner_type == "dashboard" => id.clone(),
+                _ => active_dashboard(&mut tx, user_id).await?.id,
+            };
+
+            let restored =
+                match find_column(&mut tx, &dashboard_id, "dashboard", &column.name).await? {
+                    Some(existing) => existing,
+                    None => {
+                        let column = Column {
+                            id: uuid::Uuid::new_v4().to_string(),
+                            owner_type: "dashboard".to_string(),
+                            owner_id: dashboard_id,
+                            created_at: Utc::now().to_rfc3339(),
+                            ..column.clone()
+                        };
+                        append_column(&mut tx, &column).await?
+                    }
+                };
+            column_ids.insert(column.id.clone(), restored.id);
+        }
+
+        for link in links {
+            let column_id = link
+                .column_id
+                .as_ref()
+                .and_then(|id| column_ids.get(id))
+                .cloned();
+            insert_link(
+                &mut tx,
+                &Link {
+                    column_id,
+                    ..link.clone()
+                },
+            )
+            .await?;
+        }
+
+        if let Some(settings_blob) = settings_blob {
+            sqlx::query(
+                "INSERT INTO user_settings (user_id, settings_blob, created_at) VALUES (?, ?, ?)
+                 ON CONFLICT(user_id) DO UPDATE SET settings_blob = excluded.settings_blob",
+            )
+            .bind(user_id)
+            .bind(settings_blob)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        tracing::info!("Successfully restored user data for: {}", user_id);
+        Ok(())
+    }
+
+    // Organization and Team functions (reimplemented from PL/pgSQL)
+    pub async fn create_organization(
+        &self,
+        org_name: &str,
+        owner_id: &str,
+        plan_id: &str,
+    ) -> Result<String> {
+        tracing::info!(
+            "Creating organization: {} for owner: {}",
+            org_name,
+            owner_id
+        );
+
+        // Start a transaction
+        let mut tx = self.pool.begin().await?;
+
+        let org_id = uuid::Uuid::new_v4().to_string();
+        let created_at = Utc::now().to_rfc3339();
+
+        // Create the organization
+        sqlx::query(
+            "INSERT INTO organizations (id, name, owner_id, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&org_id)
+        .bind(org_name)
+        .bind(owner_id)
+        .bind(&created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        // Create subscription for the organization
+        let sub_id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO subscriptions (id, entity_id, entity_type, plan_id, status, created_at) VALUES (?, ?, 'organization', ?, 'active', ?)"
+        )
+        .bind(&sub_id)
+        .bind(&org_id)
+        .bind(plan_id)
+        .bind(&created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        // Add owner as admin member
+        sqlx::query(
+            "INSERT INTO user_memberships (user_id, entity_id, entity_type, role, created_at) VALUES (?, ?, 'organization', 'admin', ?)"
+        )
+        .bind(owner_id)
+        .bind(&org_id)
+        .bind(&created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!("Successfully created organization: {}", org_id);
+        Ok(org_id)
+    }
+
+    pub async fn create_team(
+        &self,
+        team_name: &str,
+        owner_id: &str,
+        plan_id: &str,
+        org_id: Option<&str>,
+    ) -> Result<String> {
+        tracing::info!("Creating team: {} for owner: {}", team_name, owner_id);
+
+        // Start a transaction
+        let mut tx = self.pool.begin().await?;
+
+        let team_id = uuid::Uuid::new_v4().to_string();
+        let created_at = Utc::now().to_rfc3339();
+
+        // Create the team
+        sqlx::query("INSERT INTO teams (id, name, owner_id, organization_id, created_at) VALUES (?, ?, ?, ?, ?)")
+            .bind(&team_id)
+            .bind(team_name)
+            .bind(owner_id)
+            .bind(org_id)
+            .bind(&created_at)
+            .execute(&mut *tx)
+            .await?;
+
+        // Create subscription for the team
+        let sub_id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO subscriptions (id, entity_id, entity_type, plan_id, status, created_at) VALUES (?, ?, 'team', ?, 'active', ?)"
+        )
+        .bind(&sub_id)
+        .bind(&team_id)
+        .bind(plan_id)
+        .bind(&created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        // Add owner as admin member
+        sqlx::query(
+            "INSERT INTO user_memberships (user_id, entity_id, entity_type, role, created_at) VALUES (?, ?, 'team', 'admin', ?)"
+        )
+        .bind(owner_id)
+        .bind(&team_id)
+        .bind(&created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!("Successfully created team: {}", team_id);
+        Ok(team_id)
+    }
+
+    /// Organizations the user is a member of, with their role
+    pub async fn get_organizations(&self, user_id: &str) -> Result<Vec<Organization>> {
+        tracing::info!("Fetching organizations for user: {}", user_id);
+
+        let organizations = sqlx::query_as::<_, Organization>(
+            "SELECT o.*, m.role FROM organizations o
+             JOIN user_memberships m ON m.entity_id = o.id AND m.entity_type = 'organization'
+             WHERE m.user_id = ?
+             ORDER BY o.name",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully fetched {} organizations", organizations.len());
+        Ok(organizations)
+    }
+
+    /// Teams the user can see, directly or through the team's organization
+    pub async fn get_teams(&self, user_id: &str) -> Result<Vec<Team>> {
+        tracing::info!("Fetching teams for user: {}", user_id);
+
+        let teams = sqlx::query_as::<_, Team>(
+            "SELECT t.*,
+                CASE MAX(CASE m.role WHEN 'admin' THEN 3 WHEN 'editor' THEN 2 ELSE 1 END)
+                    WHEN 3 THEN 'admin' WHEN 2 THEN 'editor' ELSE 'viewer' END AS role
+             FROM teams t
+             JOIN user_memberships m ON m.user_id = ? AND (
+                (m.entity_type = 'team' AND m.entity_id = t.id)
+                OR (m.entity_type = 'organization' AND m.entity_id = t.organization_id)
+             )
+             GROUP BY t.id
+             ORDER BY t.name",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully fetched {} teams", teams.len());
+        Ok(teams)
+    }
+
+    /// The user's role in a team or organization, None when they aren't a
+    /// member. Organization roles carry over to the organization's teams.
+    pub async fn get_member_role(
+        &self,
+        user_id: &str,
+        entity_type: &str,
+        entity_id: &str,
+    ) -> Result<Option<Role>> {
+        let roles: Vec<String> = sqlx::query_scalar(
+            "SELECT role FROM user_memberships WHERE user_id = ?1 AND (
+                (entity_type = ?2 AND entity_id = ?3)
+                OR (?2 = 'team' AND entity_type = 'organization'
+                    AND entity_id = (SELECT organization_id FROM teams WHERE id = ?3))
+            )",
+        )
+        .bind(user_id)
+        .bind(entity_type)
+        .bind(entity_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(roles.iter().filter_map(|role| Role::parse(role)).max())
+    }
+
+    pub async fn get_members(&self, entity_type: &str, entity_id: &str) -> Result<Vec<Member>> {
+        tracing::info!("Fetching members of {}: {}", entity_type, entity_id);
+
+        let members = sqlx::query_as::<_, Member>(
+            "SELECT m.user_id, u.email, m.role, m.created_at FROM user_memberships m
+             JOIN users u ON u.id = m.user_id
+             WHERE m.entity_type = ? AND m.entity_id = ?
+             ORDER BY u.email",
+        )
+        .bind(entity_type)
+        .bind(entity_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully fetched {} members", members.len());
+        Ok(members)
+    }
+
+    pub async fn rename_organization(&self, id: &str, name: &str) -> Result<()> {
+        tracing::info!("Renaming organization: {}", id);
+
+        let result = sqlx::query("UPDATE organizations SET name = ? WHERE id = ?")
+            .bind(name)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        tracing::info!("Successfully renamed organization: {}", id);
+        Ok(())
+    }
+
+    pub async fn rename_team(&self, id: &str, name: &str) -> Result<()> {
+        tracing::info!("Renaming team: {}", id);
+
+        let result = sqlx::query("UPDATE teams SET name = ? WHERE id = ?")
+            .bind(name)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        tracing::info!("Successfully renamed team: {}", id);
+        Ok(())
+    }
+
+    /// Display name of a team or organization
+    pub async fn get_entity_name(&self, entity_type: &str, entity_id: &str) -> Result<String> {
+        let query = match entity_type {
+            "team" => "SELECT name FROM teams WHERE id = ?",
+            "organization" => "SELECT name FROM organizations WHERE id = ?",
+            _ => return Err(anyhow::anyhow!("404")),
+        };
+
+        sqlx::query_scalar(query)
+            .bind(entity_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("404"))
+    }
+
+    // Invitations
+    /// Store an invitation. Inviting the same email again replaces the
+    /// pending invitation, so only the newest link works.
+    pub async fn create_invitation(&self, invitation: Invitation) -> Result<Invitation> {
+        tracing::info!(
+            "Inviting {} to {}: {}",
+            invitation.email,
+            invitation.entity_type,
+            invitation.entity_id
+        );
+
+        let invitation = sqlx::query_as::<_, Invitation>(
+            "INSERT INTO invitations
+                (id, email, entity_id, entity_type, role, token_hash, invited_by, expires_at, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (entity_id, entity_type, email) DO UPDATE SET
+                role = excluded.role,
+                token_hash = excluded.token_hash,
+                invited_by = excluded.invited_by,
+                expires_at = excluded.expires_at,
+                created_at = excluded.created_at
+             RETURNING *",
+        )
+        .bind(&invitation.id)
+        .bind(&invitation.email)
+        .bind(&invitation.entity_id)
+        .bind(&invitation.entity_type)
+        .bind(&invitation.role)
+        .bind(&invitation.token_hash)
+        .bind(&invitation.invited_by)
+        .bind(&invitation.expires_at)
+        .bind(&invitation.created_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully created invitation: {}", invitation.id);
+        Ok(invitation)
+    }
+
+    pub async fn get_invitations(
+        &self,
+        entity_type: &str,
+        entity_id: &str,
+    ) -> Result<Vec<Invitation>> {
+        tracing::info!("Fetching invitations for {}: {}", entity_type, entity_id);
+
+        let invitations = sqlx::query_as::<_, Invitation>(
+            "SELECT * FROM invitations WHERE entity_type = ? AND entity_id = ?
+             ORDER BY created_at DESC",
+        )
+        .bind(entity_type)
+        .bind(entity_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully fetched {} invitations", invitations.len());
+        Ok(invitations)
+    }
+
+    pub async fn get_invitation(&self, id: &str) -> Result<Invitation> {
+        sqlx::query_as::<_, Invitation>("SELECT * FROM invitations WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("404"))
+    }
+
+    pub async fn get_invitation_by_token_hash(&self, token_hash: &str) -> Result<Invitation> {
+        sqlx::query_as::<_, Invitation>("SELECT * FROM invitations WHERE token_hash = ?")
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("404"))
+    }
+
+    pub async fn delete_invitation(&self, id: &str) -> Result<()> {
+        tracing::info!("Deleting invitation: {}", id);
+
+        let result = sqlx::query("DELETE FROM invitations WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        tracing::info!("Successfully deleted invitation: {}", id);
+        Ok(())
+    }
+
+    /// Delete an organization along with its teams and everything they own
+    pub async fn delete_organization(&self, id: &str) -> Result<()> {
+        tracing::info!("Deleting organization: {}", id);
+
+        let mut tx = self.pool.begin().await?;
+
+        let team_ids: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM teams WHERE organization_id = ?")
+                .bind(id)
+                .fetch_all(&mut *tx)
+                .await?;
+        for team_id in &team_ids {
+            delete_shared_entity(&mut tx, "team", team_id).await?;
+        }
+
+        if !delete_shared_entity(&mut tx, "organization", id).await? {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        tx.commit().await?;
+
+        tracing::info!(
+            "Successfully deleted organization {} and {} teams",
+            id,
+            team_ids.len()
+        );
+        Ok(())
+    }
+
+    /// Delete a team and everything it owns
+    pub async fn delete_team(&self, id: &str) -> Result<()> {
+        tracing::info!("Deleting team: {}", id);
+
+        let mut tx = self.pool.begin().await?;
+        if !delete_shared_entity(&mut tx, "team", id).await? {
+            return Err(anyhow::anyhow!("404"));
+        }
+        tx.commit().await?;
+
+        tracing::info!("Successfully deleted team: {}", id);
+        Ok(())
+    }
+
+    pub async fn get_api_tokens(&self, user_id: &str) -> Result<Vec<ApiToken>> {
+        tracing::info!("Fetching API tokens for user: {}", user_id);
+
+        let api_tokens = sqlx::query_as::<_, ApiToken>(
+            "SELECT * FROM api_tokens WHERE user_id = ? ORDER BY created_at DESC",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(api_tokens)
+    }
+
+    pub async fn create_api_token(&self, api_token: ApiToken) -> Result<ApiToken> {
+        tracing::info!(
+            "Creating API token {} for user: {}",
+            api_token.name,
+            api_token.user_id
+        );
+
+        let api_token = sqlx::query_as::<_, ApiToken>(
+            "INSERT INTO api_tokens
+                (id, user_id, name, token_hash, scopes, last_used_at, expires_at, created_at)
+             VALUES (?, ?, ?, ?, ?, NULL, ?, ?)
+             RETURNING *",
+        )
+        .bind(&api_token.id)
+        .bind(&api_token.user_id)
+        .bind(&api_token.name)
+        .bind(&api_token.token_hash)
+        .bind(&api_token.scopes)
+        .bind(&api_token.expires_at)
+        .bind(&api_token.created_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully created API token: {}", api_token.id);
+        Ok(api_token)
+    }
+
+    /// Look up a token by its hash, along with its user's email
+    pub async fn get_api_token_by_hash(&self, token_hash: &str) -> Result<(ApiToken, String)> {
+        let api_token =
+            sqlx::query_as::<_, ApiToken>("SELECT * FROM api_tokens WHERE token_hash = ?")
+                .bind(token_hash)
+                .fetch_optional(&self.pool)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("404"))?;
+
+        let email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+            .bind(&api_token.user_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("404"))?;
+
+        Ok((api_token, email))
+    }
+
+    pub async fn touch_api_token(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = ? WHERE id = ?")
+            .bind(Utc::now().to_rfc3339())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_api_token(&self, user_id: &str, id: &str) -> Result<()> {
+        tracing::info!("Deleting API token {} of user {}", id, user_id);
+
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("404"));
+        }
+        Ok(())
+    }
+
+    pub async fn record_login_attempt(
+        &self,
+        email: &str,
+        ip: Option<&str>,
+        outcome: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO login_attempts (id, email, ip, outcome, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(email)
+        .bind(ip)
+        .bind(outcome)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Failed logins for an account since `since` and its last successful
+    /// login, with the time of the latest one
+    pub async fn get_account_login_failures(
+        &self,
+        email: &str,
+        since: &str,
+    ) -> Result<(u32, Option<String>)> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS failures, MAX(created_at) AS last_failure
+             FROM login_attempts
+             WHERE email = ? AND outcome = ? AND created_at > MAX(?, COALESCE(
+                (SELECT MAX(created_at) FROM login_attempts WHERE email = ? AND outcome = ?), ''))",
+        )
+        .bind(email)
+        .bind(login_guard::OUTCOME_FAILURE)
+        .bind(since)
+        .bind(email)
+        .bind(login_guard::OUTCOME_SUCCESS)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((
+            row.get::<i64, _>("failures") as u32,
+            row.get("last_failure"),
+        ))
+    }
+
+    /// Failed logins from an IP address since `since`, whichever accounts
+    /// they were for. Signing in to an account of your own doesn't reset it.
+    pub async fn get_ip_login_failures(
+        &self,
+        ip: &str,
+        since: &str,
+    ) -> Result<(u32, Option<String>)> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS failures, MAX(created_at) AS last_failure
+             FROM login_attempts
+             WHERE ip = ? AND outcome = ? AND created_at > ?",
+        )
+        .bind(ip)
+        .bind(login_guard::OUTCOME_FAILURE)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((
+            row.get::<i64, _>("failures") as u32,
+            row.get("last_failure"),
+        ))
+    }
+
+    pub async fn delete_login_attempts_before(&self, before: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM login_attempts WHERE created_at < ?")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn create_session(
+        &self,
+        user_id: &str,
+        refresh_token_hash: &str,
+        user_agent: Option<&str>,
+        expires_at: &str,
+    ) -> Result<Session> {
+        tracing::info!("Creating session for user: {}", user_id);
+
+        let now = Utc::now().to_rfc3339();
+        let session = sqlx::query_as::<_, Session>(
+            "INSERT INTO sessions
+                (id, user_id, refresh_token_hash, user_agent, created_at, last_used_at, expires_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             RETURNING *",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(user_id)
+        .bind(refresh_token_hash)
+        .bind(user_agent)
+        .bind(&now)
+        .bind(&now)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        tracing::info!("Successfully created session: {}", session.id);
+        Ok(session)
+    }
+
+    /// Swap a session's refresh token for a new one. Presenting a token that
+    /// was already swapped means two parties hold it, so the session is
+    /// revoked and returned with `revoked_at` set for the caller to act on.
+    /// Unknown, revoked and expired tokens are "404".
+    pub async fn rotate_session(
+        &self,
+        refresh_token_hash: &str,
+        new_refresh_token_hash: &str,
+        expires_at: &str,
+    ) -> Result<Session> {
+        let mut tx = self.pool.begin().await?;
+        let now = Utc::now();
+
+        let session = sqlx::query_as::<_, Session>(
+            "SELECT * FROM sessions
+             WHERE refresh_token_hash = ? OR previous_refresh_hash = ?",
+        )
+        .bind(refresh_token_hash)
+        .bind(refresh_token_hash)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("404"))?;
+
+        let expired = DateTime::parse_from_rfc3339(&session.expires_at)
+            .map_or(true, |expires_at| expires_at <= now);
+        if session.revoked_at.is_some() || expired {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        if session.refresh_token_hash != refresh_token_hash {
+            tracing::warn!("Refresh token reused for session: {}", session.id);
+            let session = sqlx::query_as::<_, Session>(
+                "UPDATE sessions SET revoked_at = ? WHERE id = ? RETURNING *",
+            )
+            .bind(now.to_rfc3339())
+            .bind(&session.id)
+            .fetch_one(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            return Ok(session);
+        }
+
+        let session = sqlx::query_as::<_, Session>(
+            "UPDATE sessions
+             SET previous_refresh_hash = refresh_token_hash, refresh_token_hash = ?,
+                 last_used_at = ?, expires_at = ?
+             WHERE id = ?
+             RETURNING *",
+        )
+        .bind(new_refresh_token_hash)
+        .bind(now.to_rfc3339())
+        .bind(expires_at)
+        .bind(&session.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(session)
+    }
+
+    /// Sessions of a user that can still be refreshed, most recently used first
+    pub async fn get_sessions(&self, user_id: &str) -> Result<Vec<Session>> {
+        tracing::info!("Fetching sessions for user: {}", user_id);
+
+        let sessions = sqlx::query_as::<_, Session>(
+            "SELECT * FROM sessions
+             WHERE user_id = ? AND revoked_at IS NULL AND expires_at > ?
+             ORDER BY last_used_at DESC",
+        )
+        .bind(user_id)
+        .bind(Utc::now().to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    /// Revoke sessions of a user, either one of them or all but `keep`.
+    /// Their ids go on the revocation list until `revoked_until` so access
+    /// tokens already handed out stop working too. Returns the revoked ids.
+    pub async fn revoke_sessions(
+        &self,
+        user_id: &str,
+        session_id: Option<&str>,
+        keep: Option<&str>,
+        revoked_until: i64,
+    ) -> Result<Vec<String>> {
+        tracing::info!("Revoking sessions for user: {}", user_id);
+
+        let mut tx = self.pool.begin().await?;
+
+        let ids: Vec<String> = sqlx::query_scalar(
+            "UPDATE sessions SET revoked_at = ?
+             WHERE user_id = ? AND revoked_at IS NULL
+               AND (? IS NULL OR id = ?) AND (? IS NULL OR id != ?)
+             RETURNING id",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(user_id)
+        .bind(session_id)
+        .bind(session_id)
+        .bind(keep)
+        .bind(keep)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if session_id.is_some() && ids.is_empty() {
+            return Err(anyhow::anyhow!("404"));
+        }
+
+        for id in &ids {
+            insert_revoked_token(&mut tx, id, revoked_until).await?;
+        }
+
+        tx.commit().await?;
+
+        tracing::info!("Revoked {} sessions for user {}", ids.len(), user_id);
+        Ok(ids)
+    }
+
+    /// Put a single access token id on the revocation list
+    pub async fn revoke_token(&self, id: &str, revoked_until: i64) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        insert_revoked_token(&mut conn, id, revoked_until).await
+    }
+
+    /// Forget sessions that can't be refreshed anymore
+    pub async fn delete_stale_sessions(&self) -> Result<u64> {
+        let result =
+            sqlx::query("DELETE FROM sessions WHERE expires_at <= ? OR revoked_at IS NOT NULL")
+                .bind(Utc::now().to_rfc3339())
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Revocations still in force, forgetting the ones that no longer matter
+    pub async fn get_revoked_tokens(&self) -> Result<Vec<(String, i64)>> {
+        let now = Utc::now().timestamp();
+
+        sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        let revoked =
+            sqlx::query_as::<_, (String, i64)>("SELECT id, expires_at FROM revoked_tokens")
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(revoked)
+    }
+}
+
+async fn insert_revoked_token(conn: &mut SqliteConnection, id: &str, until: i64) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO revoked_tokens (id, expires_at) VALUES (?, ?)
+         ON CONFLICT (id) DO UPDATE SET expires_at = MAX(expires_at, excluded.expires_at)",
+    )
+    .bind(id)
+    .bind(until)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Remove a team or organization with its links, columns, tags, members and
+/// subscription. Owner ids aren't foreign keys, so nothing cascades on its own.
+/// Returns false when the entity doesn't exist.
+async fn delete_shared_entity(
+    conn: &mut SqliteConnection,
+    entity_type: &str,
+    entity_id: &str,
+) -> Result<bool> {
+    let table = match entity_type {
+        "team" => "teams",
+        "organization" => "organizations",
+        _ => return Err(anyhow::anyhow!("Unknown entity type: {}", entity_type)),
+    };
+
+    for statement in [
+        "DELETE FROM links WHERE owner_type = ? AND owner_id = ?",
+        "DELETE FROM columns WHERE owner_type = ? AND owner_id = ?",
+        "DELETE FROM tags WHERE owner_type = ? AND owner_id = ?",
+        "DELETE FROM user_memberships WHERE entity_type = ? AND entity_id = ?",
+        "DELETE FROM invitations WHERE entity_type = ? AND entity_id = ?",
+        "DELETE FROM subscriptions WHERE entity_type = ? AND entity_id = ?",
+    ] {
+        sqlx::query(statement)
+            .bind(entity_type)
+            .bind(entity_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    let result = sqlx::query(&format!("DELETE FROM {} WHERE id = ?", table))
+        .bind(entity_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Point a link at its tags by name, creating tags the owner doesn't have yet.
+/// Whatever tags the link had before are replaced.
+async fn write_link_tags(conn: &mut SqliteConnection, link: &Link) -> Result<()> {
+    sqlx::query("DELETE FROM link_tags WHERE link_id = ?")
+        .bind(&link.id)
+        .execute(&mut *conn)
+        .await?;
+
+    for name in &link.tags {
+        sqlx::query(
+            "INSERT OR IGNORE INTO tags (id, name, owner_id, owner_type, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(name)
+        .bind(&link.owner_id)
+        .bind(&link.owner_type)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            "INSERT OR IGNORE INTO link_tags (link_id, tag_id)
+             SELECT ?, id FROM tags WHERE owner_id = ? AND owner_type = ? AND name = ?",
+        )
+        .bind(&link.id)
+        .bind(&link.owner_id)
+        .bind(&link.owner_type)
+        .bind(name)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Insert a link with its tags. The link goes into `column_id` when set,
+/// otherwise into the owner's column named `column_type`, created if missing.
+/// Returns the link with both column fields filled in.
+async fn insert_link(conn: &mut SqliteConnection, link: &Link) -> Result<Link> {
+    let column = resolve_column(
+        conn,
+        &link.owner_id,
+        &link.owner_type,
+        link.column_id.as_deref(),
+        &link.column_type,
+    )
+    .await?;
+
+    let link = Link {
+        column_type: column.name,
+        column_id: Some(column.id),
+        alias: None,
+        ..link.clone()
+    };
+
+    let result = sqlx::query(
+        "INSERT INTO links (id, title, url, icon, order_index, owner_type, owner_id, created_at, description, column_type, column_id)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&link.id)
+    .bind(&link.title)
+    .bind(&link.url)
+    .bind(&link.icon)
+    .bind(link.order_index)
+    .bind(&link.owner_type)
+    .bind(&link.owner_id)
+    .bind(&link.created_at)
+    .bind(&link.description)
+    .bind(&link.column_type)
+    .bind(&link.column_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        tracing::error!("Failed to create link");
+        return Err(anyhow::anyhow!("Failed to create link: database error"));
+    }
+
+    write_link_tags(conn, &link).await?;
+    Ok(link)
+}
+
+/// Fetch a column by id, fails with "404" unless it belongs to the owner
+async fn owned_column(
+    conn: &mut SqliteConnection,
+    id: &str,
+    owner_id: &str,
+    owner_type: &str,
+) -> Result<Column> {
+    sqlx::query_as::<_, Column>(
+        "SELECT * FROM columns WHERE id = ? AND owner_id = ? AND owner_type = ?",
+    )
+    .bind(id)
+    .bind(owner_id)
+    .bind(owner_type)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| anyhow::anyhow!("404"))
+}
+
+async fn find_column(
+    conn: &mut SqliteConnection,
+    owner_id: &str,
+    owner_type: &str,
+    name: &str,
+) -> Result<Option<Column>> {
+    let column = sqlx::query_as::<_, Column>(
+        "SELECT * FROM columns WHERE owner_id = ? AND owner_type = ? AND name = ?",
+    )
+    .bind(owner_id)
+    .bind(owner_type)
+    .bind(name)
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(column)
+}
+
+/// Insert a column after the owner's existing ones, ignoring `column.position`
+async fn append_column(conn: &mut SqliteConnection, column: &Column) -> Result<Column> {
+    let position: i32 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM columns WHERE owner_id = ? AND owner_type = ?",
+    )
+    .bind(&column.owner_id)
+    .bind(&column.owner_type)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let column = Column {
+        position,
+        ..column.clone()
+    };
+
+    sqlx::query(
+        "INSERT INTO columns (id, name, position, color, collapsed, owner_id, owner_type, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&column.id)
+    .bind(&column.name)
+    .bind(column.position)
+    .bind(&column.color)
+    .bind(column.collapsed)
+    .bind(&column.owner_id)
+    .bind(&column.owner_type)
+    .bind(&column.created_at)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(column)
+}
+
+/// The column a link owned by `owner_id` should live in. A `column_id` has to
+/// be one the owner can use. Otherwise it's the column called `name` in the
+/// user's active dashboard (or among a team's own columns), created if missing.
+async fn resolve_column(
+    conn: &mut SqliteConnection,
+    owner_id: &str,
+    owner_type: &str,
+    column_id: Option<&str>,
+    name: &str,
+) -> Result<Column> {
+    if let Some(column_id) = column_id {
+        return link_owner_column(conn, column_id, owner_id, owner_type).await;
+    }
+
+    let (column_owner_id, column_owner_type) = if owner_type == "user" {
+        (active_dashboard(conn, owner_id).await?.id, "dashboard")
+    } else {
+        (owner_id.to_string(), owner_type)
+    };
+
+    named_column(conn, &column_owner_id, column_owner_type, name).await
+}
+
+/// The column owner's column named `name`, created if missing
+async fn named_column(
+    conn: &mut SqliteConnection,
+    owner_id: &str,
+    owner_type: &str,
+    name: &str,
+) -> Result<Column> {
+    if let Some(column) = find_column(conn, owner_id, owner_type, name).await? {
+        return Ok(column);
+    }
+
+    tracing::info!("Creating column for owner {}: {}", owner_id, name);
+    append_column(
+        conn,
+        &Column {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            position: 0,
+            color: None,
+            collapsed: false,
+            owner_type: owner_type.to_string(),
+            owner_id: owner_id.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+        },
+    )
+    .await
+}
+
+/// Fetch a column the link owner can put links in, fails with "404" otherwise.
+/// Users can use the columns of any of their dashboards.
+async fn link_owner_column(
+    conn: &mut SqliteConnection,
+    id: &str,
+    owner_id: &str,
+    owner_type: &str,
+) -> Result<Column> {
+    sqlx::query_as::<_, Column>(
+        "SELECT * FROM columns WHERE id = ?1 AND (
+            (owner_id = ?2 AND owner_type = ?3)
+            OR (?3 = 'user' AND owner_type = 'dashboard'
+                AND owner_id IN (SELECT id FROM dashboards WHERE user_id = ?2))
+        )",
+    )
+    .bind(id)
+    .bind(owner_id)
+    .bind(owner_type)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| anyhow::anyhow!("404"))
+}
+
+/// The user's active dashboard. Falls back to their oldest dashboard, and
+/// creates the default one for users who don't have any yet.
+async fn active_dashboard(conn: &mut SqliteConnection, user_id: &str) -> Result<Dashboard> {
+    let active = sqlx::query_as::<_, Dashboard>(
+        "SELECT d.*, 1 AS active FROM dashboards d
+         JOIN users u ON u.active_dashboard_id = d.id
+         WHERE u.id = ?",
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(dashboard) = active {
+        return Ok(dashboard);
+    }
+
+    sqlx::query(
+        "INSERT OR IGNORE INTO dashboards (id, user_id, name, settings_overrides, created_at)
+         SELECT ?, ?, ?, '{}', ? WHERE NOT EXISTS (SELECT 1 FROM dashboards WHERE user_id = ?)",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(user_id)
+    .bind(dashboards::DEFAULT_DASHBOARD_NAME)
+    .bind(Utc::now().to_rfc3339())
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    let dashboard = sqlx::query_as::<_, Dashboard>(
+        "SELECT *, 1 AS active FROM dashboards WHERE user_id = ? ORDER BY created_at LIMIT 1",
+    )
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query("UPDATE users SET active_dashboard_id = ? WHERE id = ?")
+        .bind(&dashboard.id)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    tracing::info!(
+        "Active dashboard for user {} is now: {}",
+        user_id,
+        dashboard.id
+    );
+    Ok(dashboard)
+}
+
+/// Whether another of the user's search engines has this one's keyword
+async fn keyword_taken(conn: &mut SqliteConnection, engine: &SearchEngine) -> Result<bool> {
+    let Some(keyword) = &engine.keyword else {
+        return Ok(false);
+    };
+    let taken = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM search_engines WHERE user_id = ? AND keyword = ? AND id != ?",
+    )
+    .bind(&engine.user_id)
+    .bind(keyword)
+    .bind(&engine.id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .is_some();
+
+    Ok(taken)
+}
+
+async fn clear_default_search_engine(conn: &mut SqliteConnection, user_id: &str) -> Result<()> {
+    sqlx::query("UPDATE search_engines SET is_default = 0 WHERE user_id = ? AND is_default = 1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_database() -> Database {
+        // One connection, every connection to :memory: is its own database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        Database {
+            client: Client::new(),
+            pool,
+        }
+    }
+
+    fn team_link(title: &str, column_type: &str) -> Link {
+        Link {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.to_string(),
+            url: format!("https://{}.example", title),
+            icon: None,
+            order_index: 0,
+            owner_type: "team".to_string(),
+            owner_id: "team".to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            description: None,
+            column_type: column_type.to_string(),
+            column_id: None,
+            alias: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    // links are never deleted along with their column unless asked to move
+    async fn test_delete_column_with_links() {
+        let database = test_database().await;
+        let link = database
+            .create_link(team_link("docs", "Work"))
+            .await
+            .unwrap();
+        let work = link.column_id.clone().unwrap();
+        let other = database
+            .create_link(team_link("news", "Other"))
+            .await
+            .unwrap()
+            .column_id
+            .unwrap();
+
+        let conflict = database.delete_column(&work, "team", "team", None).await;
+        assert_eq!(conflict.unwrap_err().to_string(), "409");
+        let conflict = database
+            .delete_column(&work, "team", "team", Some(&work))
+            .await;
+        assert_eq!(conflict.unwrap_err().to_string(), "409");
+        assert!(database.get_link_by_id(&link.id).await.is_ok());
+
+        database
+            .delete_column(&work, "team", "team", Some(&other))
+            .await
+            .unwrap();
+        let moved = database.get_link_by_id(&link.id).await.unwrap();
+        assert_eq!(moved.column_id.as_deref(), Some(other.as_str()));
+        assert_eq!(moved.column_type, "Other");
+    }
+
+    #[tokio::test]
+    async fn test_update_link_keeps_position() {
+        let database = test_database().await;
+        let first = database.create_link(team_link("a", "Work")).await.unwrap();
+        let second = database
+            .create_link(Link {
+                order_index: 1,
+                ..team_link("b", "Work")
+            })
+            .await
+            .unwrap();
+        database.create_link(team_link("c", "Other")).await.unwrap();
+
+        database
+            .update_link(
+                Link {
+                    title: "B".to_string(),
+                    order_index: 0,
+                    ..second.clone()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            database
+                .get_link_by_id(&second.id)
+                .await
+                .unwrap()
+                .order_index,
+            1
+        );
+
+        // Changing column puts the link last in the new one
+        database
+            .update_link(
+                Link {
+                    column_id: None,
+                    column_type: "Other".to_string(),
+                    ..first.clone()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            database
+                .get_link_by_id(&first.id)
+                .await
+                .unwrap()
+                .order_index,
+            1
+        );
+    }
+
+    #[tokio::test]
+    // renaming a link's column doesn't pull it onto the active dashboard
+    async fn test_update_link_stays_on_its_dashboard() {
+        let database = test_database().await;
+        sqlx::query(
+            "INSERT INTO users (id, email, password_hash) VALUES ('u', 'u@example.com', '')",
+        )
+        .execute(&database.pool)
+        .await
+        .unwrap();
+        let home = database.get_active_dashboard("u").await.unwrap();
+        let work = database
+            .create_dashboard(Dashboard {
+                id: uuid::Uuid::new_v4().to_string(),
+                user_id: "u".to_string(),
+                name: "Work".to_string(),
+                settings_overrides: "{}".to_string(),
+                created_at: Utc::now().to_rfc3339(),
+                active: false,
+            })
+            .await
+            .unwrap();
+
+        database.set_active_dashboard("u", &work.id).await.unwrap();
+        let link = database
+            .create_link(Link {
+                owner_type: "user".to_string(),
+                owner_id: "u".to_string(),
+                ..team_link("a", "Read")
+            })
+            .await
+            .unwrap();
+        database.set_active_dashboard("u", &home.id).await.unwrap();
+
+        database
+            .update_link(
+                Link {
+                    column_id: None,
+                    column_type: "Later".to_string(),
+                    ..link.clone()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        let column_id = database.get_link_by_id(&link.id).await.unwrap().column_id;
+        let columns = database.get_columns(&work.id, "dashboard").await.unwrap();
+        assert!(
+            columns
+                .iter()
+                .any(|column| column.name == "Later" && Some(&column.id) == column_id.as_ref())
+        );
+        assert!(
+            database
+                .get_columns(&home.id, "dashboard")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    // three searches last week beat eleven from last year
+    async fn test_search_history_ranking() {
+        let database = test_database().await;
+        sqlx::query(
+            "INSERT INTO users (id, email, password_hash) VALUES ('u', 'u@example.com', '')",
+        )
+        .execute(&database.pool)
+        .await
+        .unwrap();
+        for (query, searches, days_ago) in [
+            ("rust old", 11, 400),
+            ("rust recent", 3, 8),
+            ("rust today", 1, 0),
+        ] {
+            let last_searched_at = (Utc::now() - chrono::Duration::days(days_ago)).to_rfc3339();
+            sqlx::query(
+                "INSERT INTO search_history
+                    (id, user_id, query, normalized_query, searches, last_searched_at, created_at)
+                 VALUES (?1, 'u', ?1, ?1, ?2, ?3, ?3)",
+            )
+            .bind(query)
+            .bind(searches)
+            .bind(last_searched_at)
+            .execute(&database.pool)
+            .await
+            .unwrap();
+        }
+
+        let entries = database
+            .get_search_history("u", "rust", 3650, 2)
+            .await
+            .unwrap();
+        let ranked: Vec<(&str, i64)> = entries
+            .iter()
+            .map(|entry| (entry.query.as_str(), entry.score))
+            .collect();
+        assert_eq!(ranked, [("rust recent", 210), ("rust old", 110)]);
+
+        // Out of the default retention
+        let entries = database
+            .get_search_history("u", "rust", 365, 5)
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            database.prune_search_history(Some("u"), 365).await.unwrap(),
+            1
+        );
     }
 }
@@ -0,0 +1,86 @@
+/// Longest alias a link can have
+pub const MAX_ALIAS_LENGTH: usize = 64;
+
+/// Lowercase an alias and drop a leading "go/". None for aliases that are
+/// empty, too long, don't start with a letter or digit or have anything but
+/// letters, digits, `-`, `_` and `.`
+pub fn normalize_alias(alias: &str) -> Option<String> {
+    let alias = alias.trim().trim_matches('/');
+    let alias = alias.strip_prefix("go/").unwrap_or(alias).to_lowercase();
+    let valid = alias.chars().next().is_some_and(char::is_alphanumeric)
+        && alias.chars().count() <= MAX_ALIAS_LENGTH
+        && alias
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    valid.then_some(alias)
+}
+
+/// Where `/go/alias/rest?query` goes for a link to `link_url`: the rest of
+/// the path is added to the link's and the query to the link's own.
+pub fn target_url(link_url: &str, rest: &str, query: Option<&str>) -> String {
+    let Ok(mut url) = url::Url::parse(link_url) else {
+        return link_url.to_string();
+    };
+
+    let segments: Vec<&str> = rest
+        .split('/')
+        .filter(|segment| !matches!(*segment, "" | "." | ".."))
+        .collect();
+    if !segments.is_empty()
+        && let Ok(mut path) = url.path_segments_mut()
+    {
+        path.pop_if_empty().extend(segments);
+    }
+
+    if let Some(query) = query.filter(|query| !query.is_empty()) {
+        let query = match url.query() {
+            Some(own) if !own.is_empty() => format!("{}&{}", own, query),
+            _ => query.to_string(),
+        };
+        url.set_query(Some(&query));
+    }
+    url.to_string()
+}
+
+/// The search page an unknown alias lands on, searching for the alias and
+/// the rest of the path
+pub fn fallback_url(app_url: &str, alias: &str, rest: &str) -> String {
+    let terms = std::iter::once(alias)
+        .chain(rest.split('/').filter(|segment| !segment.is_empty()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let encoded: String = url::form_urlencoded::byte_serialize(terms.as_bytes()).collect();
+    format!("{}/?q={}", app_url, encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_go_links() {
+        assert_eq!(normalize_alias(" go/Docs ").as_deref(), Some("docs"));
+        assert_eq!(normalize_alias("q3-okrs.v2").as_deref(), Some("q3-okrs.v2"));
+        assert_eq!(normalize_alias(".."), None);
+        assert_eq!(normalize_alias("a b"), None);
+        assert_eq!(normalize_alias(""), None);
+
+        assert_eq!(
+            target_url("https://github.com/acme", "repo/issues", None),
+            "https://github.com/acme/repo/issues"
+        );
+        assert_eq!(
+            target_url("https://wiki.example/?space=eng", "../a b", Some("page=2")),
+            "https://wiki.example/a%20b?space=eng&page=2"
+        );
+        assert_eq!(
+            target_url("https://example.com/", "", None),
+            "https://example.com/"
+        );
+
+        assert_eq!(
+            fallback_url("https://tab.example", "jira", "ENG-1/"),
+            "https://tab.example/?q=jira+ENG-1"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use base64::prelude::*;
+use sha2::{Digest, Sha256};
+
+/// Path icons are served from, links store this instead of the icon bytes
+pub const ICON_ROUTE_PREFIX: &str = "/api/icons/";
+
+/// Served with every icon. Uploaded SVGs come from the app's own origin, so
+/// they must never run scripts or load anything.
+pub const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; style-src 'unsafe-inline'; sandbox";
+
+/// Icons larger than this are rejected rather than stored
+pub const MAX_ICON_BYTES: usize = 1024 * 1024;
+
+/// Content-address for icon bytes
+pub fn hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+pub fn icon_url(hash: &str) -> String {
+    format!("{}{}", ICON_ROUTE_PREFIX, hash)
+}
+
+/// Hashes are lowercase hex SHA-256, anything else can't be in the store
+pub fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Work out the image type from its magic bytes. Servers regularly send
+/// icons as text/html or application/octet-stream, so the header can't be trusted.
+pub fn sniff_content_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some("image/png");
+    }
+    if bytes.starts_with(&[0x00, 0x00, 0x01, 0x00]) {
+        return Some("image/x-icon");
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some("image/gif");
+    }
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some("image/jpeg");
+    }
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if bytes.starts_with(b"BM") {
+        return Some("image/bmp");
+    }
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" && &bytes[8..12] == b"avif" {
+        return Some("image/avif");
+    }
+
+    // SVG is text, look for the root element near the start of the document
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_lowercase();
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    if (head.starts_with("<?xml") || head.starts_with("<svg") || head.starts_with("<!--"))
+        && head.contains("<svg")
+    {
+        return Some("image/svg+xml");
+    }
+
+    None
+}
+
+/// Decode the payload of a `data:` URI, base64 or percent-encoded
+pub fn decode_data_uri(uri: &str) -> Option<Vec<u8>> {
+    let rest = uri.strip_prefix("data:")?;
+    let (meta, payload) = rest.split_once(',')?;
+
+    if meta.ends_with(";base64") {
+        // Some encoders wrap base64 or leave whitespace in
+        let cleaned: String = payload.chars().filter(|c| !c.is_whitespace()).collect();
+        BASE64_STANDARD.decode(cleaned).ok()
+    } else {
+        Some(percent_decode(payload))
+    }
+}
+
+fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%' && i + 2 < bytes.len())
+            .then(|| std::str::from_utf8(&bytes[i + 1..i + 3]).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // magic bytes win over whatever the server claimed
+    fn test_sniff_content_type() {
+        assert_eq!(
+            sniff_content_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(
+            sniff_content_type(&[0x00, 0x00, 0x01, 0x00, 0x01]),
+            Some("image/x-icon")
+        );
+        assert_eq!(
+            sniff_content_type(
+                b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>"
+            ),
+            Some("image/svg+xml")
+        );
+        assert_eq!(sniff_content_type(b"<!DOCTYPE html><html>"), None);
+    }
+
+    #[test]
+    fn test_decode_data_uri() {
+        assert_eq!(
+            decode_data_uri("data:image/png;base64,aGVsbG8=").as_deref(),
+            Some(&b"hello"[..])
+        );
+        assert_eq!(
+            decode_data_uri("data:image/svg+xml,%3Csvg%3E").as_deref(),
+            Some(&b"<svg>"[..])
+        );
+        assert_eq!(decode_data_uri("https://example.com/favicon.ico"), None);
+    }
+}
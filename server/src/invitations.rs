@@ -0,0 +1,85 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// How long an invite link stays valid
+pub const INVITATION_TTL_DAYS: i64 = 7;
+
+/// Where invite links point when APP_URL isn't set
+const DEFAULT_APP_URL: &str = "http://127.0.0.1:3000";
+
+/// Emails are compared case-insensitively, so store them lowercased.
+/// Returns None for anything that can't be an address.
+pub fn normalize_email(email: &str) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    let (local, domain) = email.split_once('@')?;
+    if local.is_empty() || !domain.contains('.') || email.chars().any(char::is_whitespace) {
+        return None;
+    }
+    Some(email)
+}
+
+pub fn expires_at(now: DateTime<Utc>) -> DateTime<Utc> {
+    now + Duration::days(INVITATION_TTL_DAYS)
+}
+
+pub fn is_expired(expires_at: &str, now: DateTime<Utc>) -> bool {
+    DateTime::parse_from_rfc3339(expires_at).map_or(true, |expires_at| expires_at <= now)
+}
+
+/// Where links in emails point, without a trailing slash
+pub fn app_url() -> String {
+    let app_url = std::env::var("APP_URL").unwrap_or_else(|_| DEFAULT_APP_URL.to_string());
+    app_url.trim_end_matches('/').to_string()
+}
+
+/// The page the invitee opens, the client picks the token up from the query
+pub fn accept_url(token: &str) -> String {
+    format!("{}/?invite={}", app_url(), token)
+}
+
+pub fn email_subject(entity_name: &str) -> String {
+    format!("You're invited to join {} on Omega Tab", entity_name)
+}
+
+pub fn email_body(inviter: &str, entity_name: &str, role: &str, url: &str) -> String {
+    format!(
+        "<p>{} invited you to join <b>{}</b> as {} on Omega Tab.</p>\
+         <p><a href=\"{}\">Accept the invitation</a></p>\
+         <p>The link expires in {} days.</p>",
+        escape_html(inviter),
+        escape_html(entity_name),
+        role,
+        escape_html(url),
+        INVITATION_TTL_DAYS
+    )
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invitation_helpers() {
+        assert_eq!(
+            normalize_email(" Alice@Example.COM ").as_deref(),
+            Some("alice@example.com")
+        );
+        assert_eq!(normalize_email("alice"), None);
+        assert_eq!(normalize_email("al ice@example.com"), None);
+
+        let now = Utc::now();
+        assert!(!is_expired(&expires_at(now).to_rfc3339(), now));
+        assert!(is_expired(&now.to_rfc3339(), now));
+        assert!(is_expired("not a date", now));
+
+        assert!(
+            email_body("a@b.c", "<Ops>", "editor", "http://x/?invite=t").contains("&lt;Ops&gt;")
+        );
+    }
+}
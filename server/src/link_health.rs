@@ -0,0 +1,226 @@
+use crate::database::{Database, LinkHealth};
+use chrono::Utc;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+const DEFAULT_INTERVAL_MINS: u64 = 6 * 60;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+// Links checked more recently than this are skipped by the background task
+const RECHECK_AFTER: chrono::Duration = chrono::Duration::hours(24);
+const BATCH_SIZE: i64 = 200;
+const MAX_IN_FLIGHT: usize = 4;
+// Upper bound on batches per run, so a failing database can't spin forever
+const MAX_BATCHES_PER_RUN: usize = 50;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthFilter {
+    /// Broken and redirected links
+    #[default]
+    Problems,
+    Broken,
+    Redirected,
+    All,
+}
+
+impl HealthFilter {
+    pub fn matches(&self, health: &LinkHealth) -> bool {
+        match self {
+            HealthFilter::Problems => is_broken(health) || is_redirected(health),
+            HealthFilter::Broken => is_broken(health),
+            HealthFilter::Redirected => is_redirected(health),
+            HealthFilter::All => true,
+        }
+    }
+}
+
+pub fn is_broken(health: &LinkHealth) -> bool {
+    health.error.is_some() || health.status_code.is_some_and(|s| s >= 400)
+}
+
+pub fn is_redirected(health: &LinkHealth) -> bool {
+    health.final_url.is_some()
+}
+
+/// Check a single URL. HEAD first since it's cheap, falling back to GET for
+/// servers that don't implement HEAD properly.
+pub async fn check_url(client: &Client, link_id: &str, url: &str) -> LinkHealth {
+    let head = client.head(url).timeout(REQUEST_TIMEOUT).send().await;
+
+    let response = match head {
+        Ok(response)
+            if response.status() != StatusCode::METHOD_NOT_ALLOWED
+                && response.status() != StatusCode::NOT_IMPLEMENTED
+                && response.status() != StatusCode::FORBIDDEN =>
+        {
+            Ok(response)
+        }
+        _ => client.get(url).timeout(REQUEST_TIMEOUT).send().await,
+    };
+
+    let checked_at = Utc::now().to_rfc3339();
+
+    match response {
+        Ok(response) => {
+            // reqwest follows redirects, so a different final URL means we were redirected
+            let final_url = response.url().to_string();
+            let redirected = url::Url::parse(url)
+                .map(|original| original.as_str() != final_url)
+                .unwrap_or(true);
+
+            LinkHealth {
+                link_id: link_id.to_string(),
+                status_code: Some(response.status().as_u16() as i64),
+                final_url: redirected.then_some(final_url),
+                error: None,
+                checked_at,
+            }
+        }
+        Err(e) => {
+            tracing::debug!("Health check failed for {}: {:?}", url, e);
+            LinkHealth {
+                link_id: link_id.to_string(),
+                status_code: e.status().map(|s| s.as_u16() as i64),
+                final_url: None,
+                error: Some(describe_error(&e)),
+                checked_at,
+            }
+        }
+    }
+}
+
+fn describe_error(e: &reqwest::Error) -> String {
+    if e.is_timeout() {
+        "timed out".to_string()
+    } else if e.is_connect() {
+        "connection failed".to_string()
+    } else if e.is_redirect() {
+        "too many redirects".to_string()
+    } else {
+        e.to_string()
+    }
+}
+
+/// Check every link that hasn't been checked recently
+pub async fn check_due_links(client: &Client, database: &Database) -> anyhow::Result<usize> {
+    let checked_before = (Utc::now() - RECHECK_AFTER).to_rfc3339();
+    let links = database
+        .get_links_due_for_check(&checked_before, BATCH_SIZE)
+        .await?;
+    let total = links.len();
+
+    let mut tasks = JoinSet::new();
+    for link in links {
+        if tasks.len() >= MAX_IN_FLIGHT {
+            record(database, tasks.join_next().await).await;
+        }
+        let client = client.clone();
+        tasks.spawn(async move { check_url(&client, &link.id, &link.url).await });
+    }
+    while let Some(result) = tasks.join_next().await {
+        record(database, Some(result)).await;
+    }
+
+    Ok(total)
+}
+
+async fn record(database: &Database, result: Option<Result<LinkHealth, tokio::task::JoinError>>) {
+    match result {
+        Some(Ok(health)) => {
+            if let Err(e) = database.record_link_health(&health).await {
+                tracing::error!("Failed to record link health: {:?}", e);
+            }
+        }
+        Some(Err(e)) => tracing::error!("Link health task failed: {:?}", e),
+        None => {}
+    }
+}
+
+/// Spawn the background checker on the current tokio runtime.
+/// The interval comes from LINK_HEALTH_INTERVAL_MINS, 0 disables it.
+pub fn spawn_checker(client: Client, database: Database) {
+    let interval_mins = std::env::var("LINK_HEALTH_INTERVAL_MINS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_INTERVAL_MINS);
+
+    if interval_mins == 0 {
+        tracing::info!("Link health checker disabled");
+        return;
+    }
+
+    tracing::info!(
+        "Starting link health checker, running every {} minutes",
+        interval_mins
+    );
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_mins * 60));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+
+            let mut checked = 0;
+            for _ in 0..MAX_BATCHES_PER_RUN {
+                match check_due_links(&client, &database).await {
+                    Ok(count) => {
+                        checked += count;
+                        if count < BATCH_SIZE as usize {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Link health check failed: {:?}", e);
+                        break;
+                    }
+                }
+            }
+
+            tracing::info!("Link health check finished for {} links", checked);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(
+        status_code: Option<i64>,
+        final_url: Option<&str>,
+        error: Option<&str>,
+    ) -> LinkHealth {
+        LinkHealth {
+            link_id: "l".to_string(),
+            status_code,
+            final_url: final_url.map(String::from),
+            error: error.map(String::from),
+            checked_at: "2026-10-18T00:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_health_filters() {
+        let ok = health(Some(200), None, None);
+        let not_found = health(Some(404), None, None);
+        let timed_out = health(None, None, Some("timed out"));
+        let moved = health(Some(200), Some("https://example.com/new"), None);
+
+        assert!(!is_broken(&ok) && !is_redirected(&ok));
+        assert!(is_broken(&not_found) && !is_redirected(&not_found));
+        assert!(is_broken(&timed_out));
+        assert!(is_redirected(&moved) && !is_broken(&moved));
+
+        assert!(!HealthFilter::Problems.matches(&ok));
+        assert!(HealthFilter::Problems.matches(&not_found));
+        assert!(HealthFilter::Problems.matches(&moved));
+        assert!(HealthFilter::Broken.matches(&timed_out));
+        assert!(!HealthFilter::Broken.matches(&moved));
+        assert!(HealthFilter::Redirected.matches(&moved));
+        assert!(!HealthFilter::Redirected.matches(&not_found));
+        assert!(HealthFilter::All.matches(&ok));
+    }
+}
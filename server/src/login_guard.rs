@@ -0,0 +1,223 @@
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Outcomes stored in the login_attempts audit table. Only failures count
+/// towards backoff and lockout.
+pub const OUTCOME_SUCCESS: &str = "success";
+pub const OUTCOME_FAILURE: &str = "failure";
+pub const OUTCOME_UNVERIFIED: &str = "unverified";
+pub const OUTCOME_BLOCKED: &str = "blocked";
+
+/// How long audit rows are kept
+pub const AUDIT_RETENTION_DAYS: i64 = 90;
+
+/// When an account or an IP address has to slow down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Failures allowed before backoff starts
+    pub free_attempts: u32,
+    /// Failures after which every attempt waits the full lockout
+    pub lockout_after: u32,
+}
+
+/// Brute-force protection settings, read from LOGIN_* variables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Thresholds {
+    pub account: Limits,
+    /// An IP address tries many accounts, so it gets more room
+    pub ip: Limits,
+    /// Wait after the first failure past the free attempts, doubling with
+    /// every further failure
+    pub backoff_base: Duration,
+    /// Longest wait, also the length of a lockout
+    pub lockout: Duration,
+    /// Failures older than this are forgotten
+    pub window: Duration,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            account: Limits {
+                free_attempts: 5,
+                lockout_after: 10,
+            },
+            ip: Limits {
+                free_attempts: 20,
+                lockout_after: 50,
+            },
+            backoff_base: Duration::seconds(1),
+            lockout: Duration::minutes(15),
+            window: Duration::hours(1),
+        }
+    }
+}
+
+impl Thresholds {
+    /// Read thresholds through `var`, which returns the value of an
+    /// environment variable. Missing or invalid values keep their default.
+    pub fn parse(var: impl Fn(&str) -> Option<String>) -> Thresholds {
+        let number = |name: &str, default: i64| {
+            var(name)
+                .and_then(|value| value.trim().parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(default)
+        };
+        let defaults = Thresholds::default();
+
+        Thresholds {
+            account: Limits {
+                free_attempts: number("LOGIN_FREE_ATTEMPTS", defaults.account.free_attempts as i64)
+                    as u32,
+                lockout_after: number("LOGIN_LOCKOUT_AFTER", defaults.account.lockout_after as i64)
+                    as u32,
+            },
+            ip: Limits {
+                free_attempts: number("LOGIN_IP_FREE_ATTEMPTS", defaults.ip.free_attempts as i64)
+                    as u32,
+                lockout_after: number("LOGIN_IP_LOCKOUT_AFTER", defaults.ip.lockout_after as i64)
+                    as u32,
+            },
+            backoff_base: Duration::seconds(number(
+                "LOGIN_BACKOFF_BASE_SECS",
+                defaults.backoff_base.num_seconds(),
+            )),
+            lockout: Duration::seconds(number(
+                "LOGIN_LOCKOUT_SECS",
+                defaults.lockout.num_seconds(),
+            )),
+            window: Duration::minutes(number("LOGIN_WINDOW_MINS", defaults.window.num_minutes())),
+        }
+    }
+
+    pub fn from_env() -> Thresholds {
+        Thresholds::parse(|name| std::env::var(name).ok())
+    }
+
+    /// How long to wait after the last of `failures` failures before the
+    /// next attempt
+    pub fn wait(&self, limits: Limits, failures: u32) -> Option<Duration> {
+        if failures >= limits.lockout_after {
+            return Some(self.lockout);
+        }
+        if failures < limits.free_attempts {
+            return None;
+        }
+        // 2^16 seconds is already far past any sensible lockout
+        let doublings = (failures - limits.free_attempts).min(16);
+        Some((self.backoff_base * 2_i32.pow(doublings)).min(self.lockout))
+    }
+
+    /// Time left before another attempt is allowed, None if it's allowed now
+    pub fn retry_after(
+        &self,
+        limits: Limits,
+        failures: u32,
+        last_failure: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<Duration> {
+        let remaining = last_failure? + self.wait(limits, failures)? - now;
+        (remaining > Duration::zero()).then_some(remaining)
+    }
+}
+
+/// Attempts in progress, so logins for the same account or from the same
+/// address are checked, verified and recorded one after another. Without
+/// it a burst of concurrent attempts all pass the check before the first
+/// failure is written.
+#[derive(Clone, Default)]
+pub struct AttemptLocks {
+    locks: Arc<Mutex<HashMap<String, Arc<AsyncMutex<()>>>>>,
+}
+
+impl AttemptLocks {
+    /// Wait for the attempts ahead for `key` to finish, holding it until
+    /// the guard is dropped
+    pub async fn lock(&self, key: String) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap();
+            // Nobody holds or waits for a lock only the map still has
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            locks.entry(key).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+}
+
+/// Emails are tracked case-insensitively, however the account spells it
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Whole seconds for a Retry-After header, rounded up so clients don't
+/// retry a moment too early
+pub fn retry_after_secs(wait: Duration) -> i64 {
+    (wait.num_milliseconds() + 999) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let thresholds = Thresholds::default();
+        let account = thresholds.account;
+
+        assert_eq!(thresholds.wait(account, 4), None);
+        assert_eq!(thresholds.wait(account, 5), Some(Duration::seconds(1)));
+        assert_eq!(thresholds.wait(account, 7), Some(Duration::seconds(4)));
+        assert_eq!(thresholds.wait(account, 10), Some(Duration::minutes(15)));
+        assert_eq!(thresholds.wait(account, 500), Some(Duration::minutes(15)));
+
+        let now = Utc::now();
+        assert_eq!(
+            thresholds.retry_after(account, 7, Some(now - Duration::seconds(1)), now),
+            Some(Duration::seconds(3))
+        );
+        assert_eq!(
+            thresholds.retry_after(account, 7, Some(now - Duration::seconds(5)), now),
+            None
+        );
+        assert_eq!(thresholds.retry_after(account, 7, None, now), None);
+        assert_eq!(retry_after_secs(Duration::milliseconds(1500)), 2);
+    }
+
+    #[tokio::test]
+    async fn test_attempt_locks() {
+        let locks = AttemptLocks::default();
+        let account = locks.lock("a@example.com".to_string()).await;
+
+        let waiting = tokio::spawn({
+            let locks = locks.clone();
+            async move { locks.lock("a@example.com".to_string()).await }
+        });
+        // Other keys don't wait
+        drop(locks.lock("b@example.com".to_string()).await);
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        drop(account);
+        drop(waiting.await.unwrap());
+        drop(locks.lock("c@example.com".to_string()).await);
+        assert_eq!(locks.locks.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_thresholds() {
+        let thresholds = Thresholds::parse(|name| match name {
+            "LOGIN_LOCKOUT_AFTER" => Some("3".to_string()),
+            "LOGIN_LOCKOUT_SECS" => Some("60".to_string()),
+            "LOGIN_FREE_ATTEMPTS" => Some("-1".to_string()),
+            _ => None,
+        });
+        assert_eq!(thresholds.account.lockout_after, 3);
+        assert_eq!(thresholds.account.free_attempts, 5);
+        assert_eq!(thresholds.lockout, Duration::seconds(60));
+        assert_eq!(thresholds.ip, Thresholds::default().ip);
+    }
+}
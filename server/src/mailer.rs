@@ -0,0 +1,403 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::resend::ResendClient;
+
+/// Sender used when MAIL_FROM isn't set
+const DEFAULT_FROM: &str = "evan@updates.omega-tab.evanrobertson.dev";
+
+/// Give up on an SMTP server after this long instead of hanging the request
+const SMTP_TIMEOUT_SECS: u64 = 30;
+
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// Something that can deliver an HTML email
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, to: &'a str, subject: &'a str, html: &'a str) -> SendFuture<'a>;
+}
+
+/// How a connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Plain connection upgraded with STARTTLS, usually port 587
+    StartTls,
+    /// TLS from the first byte, usually port 465
+    Tls,
+    /// No encryption, only for relays on localhost or a private network
+    None,
+}
+
+impl SmtpTls {
+    fn default_port(&self) -> u16 {
+        match self {
+            SmtpTls::StartTls => 587,
+            SmtpTls::Tls => 465,
+            SmtpTls::None => 25,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    /// Username and password, if the server wants them
+    pub credentials: Option<(String, String)>,
+}
+
+/// Where outgoing mail goes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    Resend {
+        api_key: String,
+    },
+    Smtp(SmtpConfig),
+    /// Write every message as an .eml file, for development
+    File(PathBuf),
+    /// Only log messages, tokens and all. Never picked without asking for
+    /// it.
+    Log,
+    /// Nothing configured, every send fails
+    Unset,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailConfig {
+    pub from: String,
+    pub transport: Transport,
+}
+
+/// Read the mail configuration through `var`, which returns the value of an
+/// environment variable. MAIL_TRANSPORT picks resend, smtp, file or log. When
+/// it's missing Resend is used if RESEND_API_KEY is set, no mail is sent
+/// otherwise.
+pub fn parse_config(var: impl Fn(&str) -> Option<String>) -> Result<MailConfig> {
+    let var = |name: &str| {
+        var(name)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let from = var("MAIL_FROM").unwrap_or_else(|| DEFAULT_FROM.to_string());
+    from.parse::<lettre::message::Mailbox>()
+        .map_err(|e| anyhow!("MAIL_FROM {:?} is not a valid address: {}", from, e))?;
+
+    let resend_key = var("RESEND_API_KEY");
+    let transport = match var("MAIL_TRANSPORT") {
+        Some(transport) => transport.to_lowercase(),
+        None if resend_key.is_some() => "resend".to_string(),
+        None => {
+            return Ok(MailConfig {
+                from,
+                transport: Transport::Unset,
+            });
+        }
+    };
+
+    let transport = match transport.as_str() {
+        "resend" => Transport::Resend {
+            api_key: resend_key
+                .ok_or_else(|| anyhow!("MAIL_TRANSPORT is resend but RESEND_API_KEY is not set"))?,
+        },
+        "smtp" => {
+            let host = var("SMTP_HOST")
+                .ok_or_else(|| anyhow!("MAIL_TRANSPORT is smtp but SMTP_HOST is not set"))?;
+            let tls = match var("SMTP_TLS").map(|tls| tls.to_lowercase()).as_deref() {
+                None | Some("starttls") => SmtpTls::StartTls,
+                Some("tls") => SmtpTls::Tls,
+                Some("none") => SmtpTls::None,
+                Some(other) => {
+                    return Err(anyhow!(
+                        "SMTP_TLS must be starttls, tls or none, got {:?}",
+                        other
+                    ));
+                }
+            };
+            let port = match var("SMTP_PORT") {
+                Some(port) => port
+                    .parse()
+                    .map_err(|_| anyhow!("SMTP_PORT {:?} is not a port number", port))?,
+                None => tls.default_port(),
+            };
+            let credentials = match (var("SMTP_USERNAME"), var("SMTP_PASSWORD")) {
+                (Some(username), Some(password)) => Some((username, password)),
+                (None, None) => None,
+                _ => {
+                    return Err(anyhow!(
+                        "SMTP_USERNAME and SMTP_PASSWORD must be set together"
+                    ));
+                }
+            };
+            Transport::Smtp(SmtpConfig {
+                host,
+                port,
+                tls,
+                credentials,
+            })
+        }
+        "file" => Transport::File(
+            var("MAIL_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| crate::database::get_data_dir().join("mail")),
+        ),
+        "log" => Transport::Log,
+        other => {
+            return Err(anyhow!(
+                "MAIL_TRANSPORT must be resend, smtp, file or log, got {:?}",
+                other
+            ));
+        }
+    };
+
+    Ok(MailConfig { from, transport })
+}
+
+impl Mailer for ResendClient {
+    fn send<'a>(&'a self, to: &'a str, subject: &'a str, html: &'a str) -> SendFuture<'a> {
+        Box::pin(async move {
+            self.send_email(to, subject, html)
+                .await
+                .map_err(|e| anyhow!("Resend failed: {}", e))
+        })
+    }
+}
+
+/// Builds the message lettre sends, shared by the SMTP and file mailers
+fn build_message(from: &str, to: &str, subject: &str, html: &str) -> Result<Message> {
+    Message::builder()
+        .from(from.parse()?)
+        .to(to
+            .parse()
+            .map_err(|e| anyhow!("Invalid recipient {:?}: {}", to, e))?)
+        .subject(subject)
+        .header(ContentType::TEXT_HTML)
+        .body(html.to_string())
+        .map_err(|e| anyhow!("Failed to build message: {}", e))
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: String,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &SmtpConfig, from: String) -> Result<Self> {
+        let builder = match config.tls {
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        };
+        let mut builder = builder
+            .port(config.port)
+            .timeout(Some(Duration::from_secs(SMTP_TIMEOUT_SECS)));
+        if let Some((username, password)) = &config.credentials {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        tracing::info!(
+            "Sending mail through SMTP server {}:{} ({:?})",
+            config.host,
+            config.port,
+            config.tls
+        );
+        Ok(SmtpMailer {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(&'a self, to: &'a str, subject: &'a str, html: &'a str) -> SendFuture<'a> {
+        Box::pin(async move {
+            let message = build_message(&self.from, to, subject, html)?;
+            self.transport
+                .send(message)
+                .await
+                .map_err(|e| anyhow!("SMTP failed: {}", e))?;
+            tracing::info!("Sent mail to {} over SMTP", to);
+            Ok(())
+        })
+    }
+}
+
+/// Writes every message to `<dir>/<id>.eml` so it can be opened in a mail
+/// client, for development and testing
+pub struct FileMailer {
+    transport: AsyncFileTransport<Tokio1Executor>,
+    dir: PathBuf,
+    from: String,
+}
+
+impl FileMailer {
+    pub fn new(dir: PathBuf, from: String) -> Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| anyhow!("Failed to create mail directory {:?}: {}", dir, e))?;
+        tracing::info!("Writing outgoing mail to {:?}", dir);
+        Ok(FileMailer {
+            transport: AsyncFileTransport::new(&dir),
+            dir,
+            from,
+        })
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send<'a>(&'a self, to: &'a str, subject: &'a str, html: &'a str) -> SendFuture<'a> {
+        Box::pin(async move {
+            let message = build_message(&self.from, to, subject, html)?;
+            let id = self
+                .transport
+                .send(message)
+                .await
+                .map_err(|e| anyhow!("Failed to write mail to {:?}: {}", self.dir, e))?;
+            tracing::info!(
+                "Wrote mail to {} as {:?}",
+                to,
+                self.dir.join(format!("{}.eml", id))
+            );
+            Ok(())
+        })
+    }
+}
+
+/// Prints mail instead of sending it, for setups without a mail provider.
+/// Invite links can be copied from the server output. Bodies only go to
+/// stdout, the tracing log ends up in Sentry.
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send<'a>(&'a self, to: &'a str, subject: &'a str, html: &'a str) -> SendFuture<'a> {
+        Box::pin(async move {
+            tracing::info!("Printed mail to {} ({})", to, subject);
+            println!("Mail to {} ({}):\n{}", to, subject, html);
+            Ok(())
+        })
+    }
+}
+
+/// Stands in when the mail configuration is broken, so the server still
+/// starts and every send reports what's wrong
+struct BrokenMailer {
+    reason: String,
+}
+
+impl Mailer for BrokenMailer {
+    fn send<'a>(&'a self, _to: &'a str, _subject: &'a str, _html: &'a str) -> SendFuture<'a> {
+        Box::pin(async move { Err(anyhow!("Mail is not configured: {}", self.reason)) })
+    }
+}
+
+pub fn from_config(config: MailConfig) -> Result<Arc<dyn Mailer>> {
+    Ok(match config.transport {
+        Transport::Resend { api_key } => Arc::new(ResendClient::new(&api_key, config.from)),
+        Transport::Smtp(smtp) => Arc::new(SmtpMailer::new(&smtp, config.from)?),
+        Transport::File(dir) => Arc::new(FileMailer::new(dir, config.from)?),
+        Transport::Log => {
+            tracing::warn!("MAIL_TRANSPORT is log, outgoing mail is only printed");
+            Arc::new(LogMailer)
+        }
+        Transport::Unset => {
+            tracing::warn!("No mail transport configured, outgoing mail fails");
+            Arc::new(BrokenMailer {
+                reason: "set MAIL_TRANSPORT or RESEND_API_KEY".to_string(),
+            })
+        }
+    })
+}
+
+/// The mailer the environment asks for. Never fails, a bad configuration is
+/// logged and turns into errors when mail is sent.
+pub fn from_env() -> Arc<dyn Mailer> {
+    match parse_config(|name| std::env::var(name).ok()).and_then(from_config) {
+        Ok(mailer) => mailer,
+        Err(e) => {
+            tracing::error!("Invalid mail configuration: {}", e);
+            println!("Invalid mail configuration: {}", e);
+            Arc::new(BrokenMailer {
+                reason: e.to_string(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<MailConfig> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        parse_config(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!(parse(&[]).unwrap().transport, Transport::Unset);
+        assert_eq!(
+            parse(&[("MAIL_TRANSPORT", "log")]).unwrap().transport,
+            Transport::Log
+        );
+        assert_eq!(
+            parse(&[("RESEND_API_KEY", "re_123")]).unwrap().transport,
+            Transport::Resend {
+                api_key: "re_123".to_string()
+            }
+        );
+
+        let config = parse(&[
+            ("MAIL_TRANSPORT", "SMTP"),
+            ("MAIL_FROM", "Omega Tab <tabs@example.com>"),
+            ("SMTP_HOST", "mail.example.com"),
+            ("SMTP_TLS", "tls"),
+            ("SMTP_USERNAME", "tabs"),
+            ("SMTP_PASSWORD", "hunter2"),
+        ])
+        .unwrap();
+        assert_eq!(config.from, "Omega Tab <tabs@example.com>");
+        assert_eq!(
+            config.transport,
+            Transport::Smtp(SmtpConfig {
+                host: "mail.example.com".to_string(),
+                port: 465,
+                tls: SmtpTls::Tls,
+                credentials: Some(("tabs".to_string(), "hunter2".to_string())),
+            })
+        );
+
+        assert_eq!(
+            parse(&[("MAIL_TRANSPORT", "file"), ("MAIL_DIR", "/tmp/mail")])
+                .unwrap()
+                .transport,
+            Transport::File(PathBuf::from("/tmp/mail"))
+        );
+
+        // misconfigurations are errors rather than a silent fallback
+        assert!(parse(&[("MAIL_TRANSPORT", "resend")]).is_err());
+        assert!(parse(&[("MAIL_TRANSPORT", "smtp")]).is_err());
+        assert!(parse(&[("MAIL_TRANSPORT", "pigeon")]).is_err());
+        assert!(parse(&[("MAIL_FROM", "not an address")]).is_err());
+        assert!(
+            parse(&[
+                ("MAIL_TRANSPORT", "smtp"),
+                ("SMTP_HOST", "localhost"),
+                ("SMTP_USERNAME", "tabs"),
+            ])
+            .is_err()
+        );
+    }
+}
@@ -1,36 +1,77 @@
 // Hide console window on Windows in release builds
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod account_tokens;
+mod api_tokens;
+mod archive;
 mod assets;
+mod authz;
+mod bookmarks;
 mod brave;
+mod cache;
+mod columns;
 mod dashboard_icons;
+mod dashboards;
 mod database;
+mod golinks;
+mod icon_store;
+mod invitations;
+mod link_health;
+mod login_guard;
+mod mailer;
+mod metadata;
 mod middleware;
+mod rate_limit;
 mod resend;
+mod search;
+mod search_history;
+mod sessions;
+mod suggestions;
+mod tags;
+mod teams;
+mod tokens;
 mod tray;
 mod user_jwt;
+mod web_search;
 
+use account_tokens::{Purpose, Verification};
+use authz::{Access, Resource};
 use axum::{
     Router,
-    extract::{Extension, Json, Path, State},
-    http::{HeaderMap, HeaderValue, Method, StatusCode},
-    routing::{delete, get, post},
+    extract::{ConnectInfo, DefaultBodyLimit, Extension, Json, Path, Query, RawQuery, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+    routing::{delete, get, patch, post, put},
 };
 use base64::prelude::*;
-use brave::Brave;
 use chrono::Utc;
 use database::Database;
 use dotenv::dotenv;
+use login_guard::Thresholds;
+use mailer::Mailer;
 use middleware::{UserContext, authenticate_user};
-use resend::ResendClient;
-use serde::{Deserialize, Serialize};
+use rate_limit::{Key, Quota, RateLimitLayer};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json::json;
-use std::{collections::HashMap, env, sync::mpsc, thread};
+use sessions::Revocations;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    net::SocketAddr,
+    sync::{Arc, mpsc},
+    thread,
+    time::Duration,
+};
+use suggestions::{LocaleSettings, SuggestContext, Suggestions};
+use teams::Role;
 use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::prelude::*;
 use tray::TrayMessage;
 use url::Url;
 
+// Maximum request body size for bookmark imports and archive restores
+const IMPORT_BODY_LIMIT: usize = 32 * 1024 * 1024;
+
 #[derive(Deserialize)]
 pub struct CreateUserRequest {
     user_id: String,
@@ -45,8 +86,13 @@ pub struct CreateLinkRequest {
     next_order_index: i32,
     owner_type: String,
     owner_id: String,
+    /// Column name, used when column_id isn't given. Created if missing.
+    #[serde(default)]
     column_type: String,
+    column_id: Option<String>,
     icon: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
 }
 
 #[derive(Deserialize)]
@@ -57,19 +103,307 @@ pub struct UpdateLinkRequest {
     title: Option<String>,
     icon: Option<String>,
     column_type: Option<String>,
+    column_id: Option<String>,
+    /// Replaces the link's tags when present
+    tags: Option<Vec<String>>,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
-pub struct Metadata {
-    title: Option<String>,
-    description: Option<String>,
-    favicon: Option<String>,
-    mime_type: Option<String>,
+#[derive(Deserialize)]
+pub struct LinksQuery {
+    /// Comma separated tag names, links must have all of them
+    tags: Option<String>,
+    /// Defaults to the active dashboard
+    dashboard_id: Option<String>,
+    /// "team" or "organization" to list links shared with the user instead
+    owner_type: Option<String>,
+    owner_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateDashboardRequest {
+    name: String,
+    settings_overrides: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateDashboardRequest {
+    name: Option<String>,
+    settings_overrides: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+pub struct CloneDashboardRequest {
+    /// Defaults to "<name> copy"
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateColumnRequest {
+    name: String,
+    color: Option<String>,
+    #[serde(default)]
+    collapsed: bool,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateColumnRequest {
+    name: Option<String>,
+    /// An empty string clears the color
+    color: Option<String>,
+    collapsed: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteColumnQuery {
+    /// Column to move the deleted column's links to, needed unless it's empty
+    move_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReorderColumnsRequest {
+    column_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReorderLinksRequest {
+    links: Vec<database::LinkPosition>,
+    /// Set both to reorder a team's or organization's links
+    owner_type: Option<String>,
+    owner_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NameRequest {
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateTeamRequest {
+    name: String,
+    /// Admins of the organization get to manage the team too
+    organization_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AddMemberRequest {
+    email: String,
+    /// viewer, editor or admin
+    role: String,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMemberRequest {
+    role: String,
+}
+
+#[derive(Deserialize)]
+pub struct InviteRequest {
+    email: String,
+    /// viewer, editor or admin
+    role: String,
+}
+
+#[derive(Deserialize)]
+pub struct AcceptInvitationRequest {
+    token: String,
+}
+
+#[derive(Deserialize)]
+pub struct TagRequest {
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct SearchEngineRequest {
+    name: String,
+    /// URL template, `%s` is where the query goes
+    url: String,
+    /// Bang like "gh" or "!gh"
+    keyword: Option<String>,
+    icon: Option<String>,
+    #[serde(default)]
+    is_default: bool,
+}
+
+impl SearchEngineRequest {
+    /// Validate the request into a search engine, 400 for a missing name,
+    /// a URL without `%s` or a keyword with spaces or symbols
+    fn into_engine(self, id: String, user_id: &str) -> Result<database::SearchEngine, StatusCode> {
+        let name = self.name.trim();
+        if name.is_empty() || name.chars().count() > 64 {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let url = self.url.trim();
+        if !url.contains("%s") || !web_search::is_valid_engine(url) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let keyword = match self.keyword.as_deref().map(str::trim) {
+            None | Some("") => None,
+            Some(keyword) => {
+                Some(web_search::normalize_keyword(keyword).ok_or(StatusCode::BAD_REQUEST)?)
+            }
+        };
+
+        Ok(database::SearchEngine {
+            id,
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            url: url.to_string(),
+            keyword,
+            icon: self.icon.filter(|icon| !icon.trim().is_empty()),
+            is_default: self.is_default,
+            order_index: 0,
+            created_at: Utc::now().to_rfc3339(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SearchHistoryQuery {
+    /// Only searches starting with this
+    q: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct RecordSearchRequest {
+    query: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResolveQuery {
+    q: String,
+}
+
+/// Where the search bar should send a query
+#[derive(Serialize)]
+pub struct ResolveResponse {
+    url: String,
+    /// "url" for a link or address, "search" for an engine
+    kind: &'static str,
+}
+
+#[derive(Deserialize)]
+pub struct GoPath {
+    alias: String,
+    /// Path after the alias, added to the link's URL
+    #[serde(default)]
+    rest: String,
+}
+
+/// Where a go link takes the user
+#[derive(Serialize)]
+pub struct GoResponse {
+    url: String,
+    /// None when nothing has the alias and `url` is the search page
+    link: Option<database::GoLink>,
+}
+
+#[derive(Deserialize)]
+pub struct AliasCheckQuery {
+    /// Set both to check a team's or organization's aliases
+    owner_type: Option<String>,
+    owner_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AliasCheckResponse {
+    /// The alias as it would be stored, None when it isn't valid
+    alias: Option<String>,
+    valid: bool,
+    /// Whether the owner can reserve it
+    available: bool,
+    /// Where the alias currently takes the user
+    link: Option<database::GoLink>,
+}
+
+#[derive(Deserialize)]
+pub struct LinkAliasRequest {
+    alias: String,
+}
+
+#[derive(Deserialize)]
+pub struct MergeTagsRequest {
+    /// Tag that survives the merge
+    target: String,
+    /// Tags whose links move to the target, these are deleted
+    sources: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportLinksRequest {
+    content: String,
+    format: Option<bookmarks::BookmarkFormat>,
+    default_column: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportIssue {
+    title: String,
+    url: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportLinksResponse {
+    imported: Vec<database::Link>,
+    duplicates: Vec<ImportIssue>,
+    invalid: Vec<ImportIssue>,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreRequest {
+    #[serde(default)]
+    mode: archive::RestoreMode,
+    archive: archive::Archive,
+}
+
+#[derive(Serialize)]
+pub struct RestoreResponse {
+    links_restored: usize,
+    links_skipped: usize,
+    settings_restored: bool,
+}
+
+#[derive(Deserialize)]
+pub struct LinkHealthQuery {
+    #[serde(default)]
+    status: link_health::HealthFilter,
+}
+
+#[derive(Serialize)]
+pub struct LinkHealthReport {
+    link: database::Link,
+    health: database::LinkHealth,
+}
+
+#[derive(Deserialize)]
+pub struct LinkSearchQuery {
+    q: String,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct MetadataQuery {
+    url: String,
+}
+
+#[derive(Deserialize)]
+pub struct WebSearchQuery {
+    q: Option<String>,
+    /// Personal access token with the search scope, browsers can't send
+    /// headers to a search engine
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct OpenSearchQuery {
+    token: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct SuggestionResponse {
-    suggestions: Vec<brave::Suggestion>,
+    suggestions: Vec<suggestions::Suggestion>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -81,6 +415,42 @@ pub struct UserSettingsRequest {
     linear_api: bool,
     new_tabs: bool,
     metadata: bool,
+    /// Language for suggestions like "en-GB", the browser's when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    locale: Option<String>,
+    /// Two letter country code for suggestions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
+    /// Days searches are kept for, the server's default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    search_history_days: Option<i64>,
+}
+
+impl UserSettingsRequest {
+    /// Empty locale fields mean unset, anything else has to parse
+    fn normalize(&mut self) -> Result<(), StatusCode> {
+        self.locale = self
+            .locale
+            .take()
+            .filter(|locale| !locale.trim().is_empty());
+        self.region = self
+            .region
+            .take()
+            .filter(|region| !region.trim().is_empty());
+        if let Some(locale) = &self.locale {
+            suggestions::parse_locale(locale).ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+        }
+        if let Some(region) = &self.region {
+            self.region =
+                Some(suggestions::parse_region(region).ok_or(StatusCode::UNPROCESSABLE_ENTITY)?);
+        }
+        if let Some(days) = &self.search_history_days
+            && !search_history::is_valid_retention(days)
+        {
+            return Err(StatusCode::UNPROCESSABLE_ENTITY);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -93,6 +463,9 @@ pub struct FeedbackRequest {
 pub struct UserDataResponse {
     user: database::User,
     settings: Option<database::UserSettings>,
+    /// The active dashboard, columns and links are the ones on it
+    dashboard: database::Dashboard,
+    columns: Vec<database::Column>,
     links: Vec<database::Link>,
 }
 
@@ -111,10 +484,59 @@ pub struct RegisterRequest {
 
 #[derive(Serialize)]
 pub struct AuthResponse {
-    token: String,
+    /// Missing when the account has to confirm its email before signing in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    /// Trade for a new token at /refresh once the token expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
     user: database::User,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct RefreshRequest {
+    refresh_token: String,
+}
+
+#[derive(Serialize)]
+pub struct RefreshResponse {
+    token: String,
+    refresh_token: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateApiTokenRequest {
+    name: String,
+    scopes: Vec<String>,
+    /// Never expires when missing
+    expires_in_days: Option<i64>,
+}
+
+/// The only time the token itself is shown
+#[derive(Serialize)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    api_token: database::ApiToken,
+    token: String,
+}
+
+/// Asks for a password reset or a new verification email
+#[derive(Deserialize, Debug)]
+pub struct EmailRequest {
+    email: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ResetPasswordRequest {
+    token: String,
+    password: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VerifyEmailRequest {
+    token: String,
+}
+
 // New struct for staging login request
 #[derive(Deserialize, Debug)]
 pub struct StagingLoginRequest {
@@ -125,6 +547,14 @@ pub struct StagingLoginRequest {
 pub struct AppState {
     pub client: reqwest::Client,
     pub database: Database,
+    pub mailer: Arc<dyn Mailer>,
+    pub verification: Verification,
+    pub revocations: Revocations,
+    pub login_thresholds: Thresholds,
+    pub login_locks: login_guard::AttemptLocks,
+    pub suggestions: Arc<Suggestions>,
+    /// Days searches are kept for users who haven't picked a retention
+    pub history_retention_days: i64,
 }
 
 fn main() {
@@ -231,10 +661,12 @@ async fn runtime(shutdown_rx: mpsc::Receiver<()>) {
                     Method::GET,
                     Method::POST,
                     Method::PUT,
+                    Method::PATCH,
                     Method::DELETE,
                     Method::OPTIONS,
                 ])
-                .allow_headers(Any),
+                .allow_headers(Any)
+                .expose_headers(rate_limit::exposed_headers()),
             "staging" => CorsLayer::new()
                 .allow_origin(
                     "https://staging.omega-tab.evanrobertson.dev"
@@ -245,10 +677,12 @@ async fn runtime(shutdown_rx: mpsc::Receiver<()>) {
                     Method::GET,
                     Method::POST,
                     Method::PUT,
+                    Method::PATCH,
                     Method::DELETE,
                     Method::OPTIONS,
                 ])
-                .allow_headers(Any),
+                .allow_headers(Any)
+                .expose_headers(rate_limit::exposed_headers()),
             _ => {
                 // Development mode - also allow localhost for single binary
                 CorsLayer::new()
@@ -261,10 +695,12 @@ async fn runtime(shutdown_rx: mpsc::Receiver<()>) {
                         Method::GET,
                         Method::POST,
                         Method::PUT,
+                        Method::PATCH,
                         Method::DELETE,
                         Method::OPTIONS,
                     ])
                     .allow_headers(Any)
+                    .expose_headers(rate_limit::exposed_headers())
             }
         }
     };
@@ -282,18 +718,99 @@ async fn runtime(shutdown_rx: mpsc::Receiver<()>) {
         }
     };
 
-    let app_state = AppState { client, database };
+    // Periodically recheck links in the background
+    link_health::spawn_checker(client.clone(), database.clone());
+
+    if let Err(e) = database.delete_stale_sessions().await {
+        tracing::warn!("Failed to delete stale sessions: {:?}", e);
+    }
+    let history_retention_days = search_history::default_retention_days();
+    if let Err(e) = database
+        .prune_search_history(None, history_retention_days)
+        .await
+    {
+        tracing::warn!("Failed to prune search history: {:?}", e);
+    }
+    let audit_cutoff = Utc::now() - chrono::Duration::days(login_guard::AUDIT_RETENTION_DAYS);
+    if let Err(e) = database
+        .delete_login_attempts_before(&audit_cutoff.to_rfc3339())
+        .await
+    {
+        tracing::warn!("Failed to delete old login attempts: {:?}", e);
+    }
+    let revocations = match database.get_revoked_tokens().await {
+        Ok(revoked) => Revocations::new(revoked),
+        Err(e) => {
+            tracing::error!("Error loading revoked tokens: {:?}", e);
+            eprintln!("Error loading revoked tokens: {:?}", e);
+            return;
+        }
+    };
+
+    let suggestions = Arc::new(Suggestions::from_env(&database));
+    let app_state = AppState {
+        client,
+        database,
+        mailer: mailer::from_env(),
+        verification: Verification::from_env(),
+        revocations,
+        login_thresholds: Thresholds::from_env(),
+        login_locks: login_guard::AttemptLocks::default(),
+        suggestions,
+        history_retention_days,
+    };
 
     // Build API router with /api prefix
+    let email_rate_limit =
+        RateLimitLayer::new(Quota::new(Key::Ip, 5, Duration::from_secs(60 * 60)));
+    // most suggestions come from the cache, so the app-wide limit is
+    // above what Brave allows and its own 429s are passed on
+    let suggest_rate_limit = RateLimitLayer::new(Quota::new(
+        Key::Route,
+        10,
+        Duration::from_secs(1),
+    ))
+    .and(Quota::new(Key::User, 60, Duration::from_secs(60)));
+
     let api_routes = Router::new()
         // Authentication routes (public - no middleware)
         .route("/register", post(register_handler))
         .route("/login", post(login_handler))
+        // both send mail to any address, so they're limited per client
+        .route(
+            "/password_reset",
+            post(request_password_reset_handler).layer(email_rate_limit.clone()),
+        )
+        .route("/password_reset/confirm", post(reset_password_handler))
+        .route("/verify_email", post(verify_email_handler))
+        .route(
+            "/verify_email/resend",
+            post(resend_verification_handler).layer(email_rate_limit),
+        )
+        .route("/refresh", post(refresh_handler))
+        // sessions of the signed-in user
+        .route("/logout", post(logout_handler))
+        .route(
+            "/sessions",
+            get(sessions_handler).delete(revoke_other_sessions_handler),
+        )
+        .route("/session/{session_id}", delete(revoke_session_handler))
+        // personal access tokens for scripts and extensions
+        .route(
+            "/api_tokens",
+            get(api_tokens_handler).post(create_api_token_handler),
+        )
+        .route("/api_token/{token_id}", delete(delete_api_token_handler))
         .route("/health", get(health_check))
         // create and update links
         .route("/link", post(create_link).put(update_link))
         // read links
         .route("/user/links", get(links_handler))
+        // import browser bookmark exports, these can be a few MB
+        .route(
+            "/links/import",
+            post(import_links_handler).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
         // delete link
         .route(
             "/link/{link_id}",
@@ -301,6 +818,112 @@ async fn runtime(shutdown_rx: mpsc::Receiver<()>) {
                 delete_link(state, path, user_context)
             }),
         )
+        // full-text link search
+        .route("/links/search", get(search_links_handler))
+        .route("/links/order", patch(reorder_links_handler))
+        // dashboards, each with its own columns and links
+        .route(
+            "/dashboards",
+            get(dashboards_handler).post(create_dashboard_handler),
+        )
+        .route(
+            "/dashboard/{dashboard_id}",
+            put(update_dashboard_handler).delete(delete_dashboard_handler),
+        )
+        .route(
+            "/dashboard/{dashboard_id}/clone",
+            post(clone_dashboard_handler),
+        )
+        .route(
+            "/dashboard/{dashboard_id}/activate",
+            post(activate_dashboard_handler),
+        )
+        // columns on the active dashboard, links live in exactly one
+        .route("/columns", get(columns_handler).post(create_column_handler))
+        .route("/columns/order", put(reorder_columns_handler))
+        .route(
+            "/column/{column_id}",
+            put(update_column_handler).delete(delete_column_handler),
+        )
+        // tags, many-to-many with links
+        .route("/tags", get(tags_handler).post(create_tag_handler))
+        .route("/tags/merge", post(merge_tags_handler))
+        .route(
+            "/tag/{tag_id}",
+            put(rename_tag_handler).delete(delete_tag_handler),
+        )
+        // search engines and bangs, `!keyword terms` searches an engine
+        .route(
+            "/search_engines",
+            get(search_engines_handler).post(create_search_engine_handler),
+        )
+        .route("/search_engines/resolve", get(resolve_search_handler))
+        .route("/opensearch.xml", get(personal_opensearch_handler))
+        .route(
+            "/search_engine/{engine_id}",
+            put(update_search_engine_handler).delete(delete_search_engine_handler),
+        )
+        .route("/bangs", get(bangs_handler))
+        // synced search history, best matches first
+        .route(
+            "/search_history",
+            get(search_history_handler)
+                .post(record_search_handler)
+                .delete(clear_search_history_handler),
+        )
+        .route(
+            "/search_history/{entry_id}",
+            delete(delete_search_history_entry_handler),
+        )
+        // go links, aliases resolve to the user's link, then their teams', then
+        // their organizations'
+        .route("/go/{alias}", get(go_handler))
+        .route("/go/{alias}/{*rest}", get(go_handler))
+        .route("/aliases", get(go_links_handler))
+        .route("/aliases/{alias}", get(check_alias_handler))
+        .route(
+            "/link/{link_id}/alias",
+            put(set_link_alias_handler).delete(delete_link_alias_handler),
+        )
+        // teams and organizations, links they own are shared with their members
+        .route(
+            "/organizations",
+            get(organizations_handler).post(create_organization_handler),
+        )
+        .route(
+            "/organization/{organization_id}",
+            put(rename_organization_handler).delete(delete_organization_handler),
+        )
+        .route("/teams", get(teams_handler).post(create_team_handler))
+        .route(
+            "/team/{team_id}",
+            put(rename_team_handler).delete(delete_team_handler),
+        )
+        .route(
+            "/members/{entity_type}/{entity_id}",
+            get(members_handler).post(add_member_handler),
+        )
+        .route(
+            "/member/{entity_type}/{entity_id}/{member_id}",
+            put(update_member_handler).delete(remove_member_handler),
+        )
+        // email invitations, accepted by the invited user once they're signed in
+        .route("/invitations/accept", post(accept_invitation_handler))
+        .route(
+            "/invitations/{entity_type}/{entity_id}",
+            get(invitations_handler).post(create_invitation_handler),
+        )
+        .route(
+            "/invitation/{invitation_id}",
+            delete(revoke_invitation_handler),
+        )
+        // link health reports and fixes
+        .route("/links/health", get(link_health_handler))
+        .route("/link/{link_id}/check", post(check_link_handler))
+        .route(
+            "/link/{link_id}/follow_redirect",
+            post(follow_redirect_handler),
+        )
         // create user
         .route("/create_user", post(create_user_handler))
         // get user
@@ -308,23 +931,52 @@ async fn runtime(shutdown_rx: mpsc::Receiver<()>) {
         // get suggestion
         .route(
             "/suggest/{query}",
-            get(move |path, user_context, headers| suggest_handler(path, user_context, headers)),
+            get(suggest_handler).layer(suggest_rate_limit.clone()),
+        )
+        // browser search engine, public so the address bar can use it,
+        // personal tokens with the search scope go in the query string
+        .route("/search", get(web_search_handler))
+        .route(
+            "/search/suggest",
+            get(web_search_suggest_handler).layer(suggest_rate_limit),
+        )
+        // one message a day, failed sends don't count
+        .route(
+            "/feedback",
+            post(feedback_handler).layer(
+                RateLimitLayer::new(Quota::new(Key::User, 1, Duration::from_secs(24 * 60 * 60)))
+                    .refund_errors(),
+            ),
         )
-        .route("/feedback", post(feedback_handler))
         .route(
             "/settings",
             post(create_settings).put(update_settings).get(get_settings),
         )
         .route("/user_data", get(get_user_data_handler))
+        // account backup and restore
+        .route("/export", get(export_handler))
+        .route(
+            "/restore",
+            post(restore_handler).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
         // Add staging login route - doesn't need authentication
         .route("/staging_login", post(staging_login_handler))
+        // Page metadata preview for the add link form
+        .route("/metadata", get(metadata_handler))
         // Dashboard icons search
         .route("/icons/search/{query}", get(search_icons_handler))
-        .with_state(app_state)
-        .layer(axum::middleware::from_fn(authenticate_user));
+        // Stored favicons (public so <img> tags can load them)
+        .route("/icons/{hash}", get(icon_handler))
+        .with_state(app_state.clone())
+        .layer(axum::middleware::from_fn_with_state(
+            app_state,
+            authenticate_user,
+        ));
 
     // Main router with API routes nested under /api and static file fallback
     let app = Router::new()
+        // OpenSearch description browsers discover from index.html
+        .route("/opensearch.xml", get(opensearch_handler))
         .nest("/api", api_routes)
         .layer(cors)
         // Fallback to static file serving for SPA
@@ -337,17 +989,21 @@ async fn runtime(shutdown_rx: mpsc::Receiver<()>) {
     tracing::info!("Server running on http://127.0.0.1:3000");
 
     // Serve with graceful shutdown
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            // Wait for shutdown signal in a tokio-compatible way
-            tokio::task::spawn_blocking(move || {
-                let _ = shutdown_rx.recv();
-            })
-            .await
-            .ok();
+    // Peer addresses are needed for per-IP login limits
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        // Wait for shutdown signal in a tokio-compatible way
+        tokio::task::spawn_blocking(move || {
+            let _ = shutdown_rx.recv();
         })
         .await
-        .unwrap();
+        .ok();
+    })
+    .await
+    .unwrap();
 }
 
 // Health check endpoint
@@ -358,8 +1014,9 @@ async fn health_check() -> StatusCode {
 // Register handler
 async fn register_handler(
     State(app_state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<RegisterRequest>,
-) -> Result<Json<AuthResponse>, StatusCode> {
+) -> Result<(StatusCode, Json<AuthResponse>), StatusCode> {
     tracing::info!("Processing registration request for: {}", payload.email);
 
     let database = &app_state.database;
@@ -377,87 +1034,333 @@ async fn register_handler(
             }
         })?;
 
-    // Generate JWT token
-    let token = user_jwt::generate_jwt(&user.id, &user.email).map_err(|e| {
-        tracing::error!("Failed to generate JWT: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    if app_state.verification.sends_email() {
+        // The account exists either way, a failed send can be retried
+        // through /verify_email/resend
+        if let Err(status) = send_account_email(&app_state, &user, Purpose::VerifyEmail).await {
+            tracing::error!(
+                "Failed to send verification email to {}: {}",
+                user.email,
+                status
+            );
+        }
+    }
+
+    if app_state.verification.is_required() {
+        tracing::info!(
+            "Registered user {}, waiting for email verification",
+            user.email
+        );
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(AuthResponse {
+                token: None,
+                refresh_token: None,
+                user,
+            }),
+        ));
+    }
+
+    let (token, refresh_token) = start_session(&app_state, &user, &headers).await?;
 
     // Set auth_token in user object
     user.auth_token = Some(token.clone());
 
     tracing::info!("Successfully registered user: {}", user.email);
 
-    Ok(Json(AuthResponse { token, user }))
+    Ok((
+        StatusCode::OK,
+        Json(AuthResponse {
+            token: Some(token),
+            refresh_token: Some(refresh_token),
+            user,
+        }),
+    ))
 }
 
 // Login handler
 async fn login_handler(
     State(app_state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<AuthResponse>, StatusCode> {
+) -> Result<Json<AuthResponse>, Response> {
     tracing::info!("Processing login request for: {}", payload.email);
 
     let database = &app_state.database;
+    let email = login_guard::normalize_email(&payload.email);
+    let ip = middleware::client_ip(&headers, Some(peer));
+
+    // Concurrent attempts for the account or from the address queue here,
+    // each one sees the failures of those before it. Email before address
+    // everywhere, so two attempts never wait on each other.
+    let _account_lock = app_state.login_locks.lock(format!("email:{}", email)).await;
+    let _address_lock = match &ip {
+        Some(ip) => Some(app_state.login_locks.lock(format!("ip:{}", ip)).await),
+        None => None,
+    };
+
+    // Checked before the password so a locked out client doesn't cost a
+    // bcrypt verification either
+    if let Some(wait) = login_retry_after(&app_state, &email, ip.as_deref()).await {
+        tracing::warn!(
+            "Login for {} from {:?} blocked for {}s",
+            email,
+            ip,
+            wait.num_seconds()
+        );
+        record_login_attempt(
+            &app_state,
+            &email,
+            ip.as_deref(),
+            login_guard::OUTCOME_BLOCKED,
+        )
+        .await;
+        let retry_after = login_guard::retry_after_secs(wait);
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            Json(json!({
+                "error": "Too many failed login attempts",
+                "retry_after": retry_after,
+            })),
+        )
+            .into_response());
+    }
 
     // Verify password
-    let mut user = database
-        .verify_password(&payload.email, &payload.password)
-        .await
-        .map_err(|e| {
+    let verified = database
+        .verify_password(
+            &payload.email,
+            &payload.password,
+            app_state.verification.is_required(),
+        )
+        .await;
+
+    let mut user = match verified {
+        Ok(user) => {
+            record_login_attempt(
+                &app_state,
+                &email,
+                ip.as_deref(),
+                login_guard::OUTCOME_SUCCESS,
+            )
+            .await;
+            user
+        }
+        Err(e) => {
             tracing::warn!("Login failed for {}: {:?}", payload.email, e);
-            StatusCode::UNAUTHORIZED
-        })?;
+            let (outcome, status) = match e.to_string().as_str() {
+                "403" => (login_guard::OUTCOME_UNVERIFIED, StatusCode::FORBIDDEN),
+                "404" | "Invalid credentials" => {
+                    (login_guard::OUTCOME_FAILURE, StatusCode::UNAUTHORIZED)
+                }
+                _ => {
+                    tracing::error!("Error verifying password: {:?}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+                }
+            };
+            record_login_attempt(&app_state, &email, ip.as_deref(), outcome).await;
+            return Err(status.into_response());
+        }
+    };
 
-    // Generate JWT token
-    let token = user_jwt::generate_jwt(&user.id, &user.email).map_err(|e| {
-        tracing::error!("Failed to generate JWT: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let (token, refresh_token) = start_session(&app_state, &user, &headers)
+        .await
+        .map_err(IntoResponse::into_response)?;
 
     // Set auth_token in user object
     user.auth_token = Some(token.clone());
 
     tracing::info!("Successfully logged in user: {}", user.email);
 
-    Ok(Json(AuthResponse { token, user }))
+    Ok(Json(AuthResponse {
+        token: Some(token),
+        refresh_token: Some(refresh_token),
+        user,
+    }))
 }
 
-// Staging login handler
-async fn staging_login_handler(
-    Json(payload): Json<StagingLoginRequest>,
-) -> Result<StatusCode, StatusCode> {
-    println!("Processing staging login request");
+/// How long a login for `email` from `ip` has to wait, if at all. The
+/// account and the address are limited separately.
+async fn login_retry_after(
+    app_state: &AppState,
+    email: &str,
+    ip: Option<&str>,
+) -> Option<chrono::Duration> {
+    let thresholds = &app_state.login_thresholds;
+    let now = Utc::now();
+    let since = (now - thresholds.window).to_rfc3339();
+    let parse = |last: Option<String>| {
+        last.and_then(|last| chrono::DateTime::parse_from_rfc3339(&last).ok())
+            .map(|last| last.with_timezone(&Utc))
+    };
 
-    // Get the staging password from environment variables
-    let staging_password = match env::var("STAGING_PASSWORD") {
-        Ok(pwd) => pwd,
-        Err(_) => {
-            println!("STAGING_PASSWORD environment variable not set");
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    let account = match app_state
+        .database
+        .get_account_login_failures(email, &since)
+        .await
+    {
+        Ok((failures, last)) => {
+            thresholds.retry_after(thresholds.account, failures, parse(last), now)
+        }
+        Err(e) => {
+            tracing::error!("Failed to count login failures for {}: {:?}", email, e);
+            None
         }
     };
 
-    // Simple password validation
-    if payload.password == staging_password {
-        println!("Staging login successful");
-        return Ok(StatusCode::OK);
-    } else {
-        println!("Invalid staging password provided");
-        return Err(StatusCode::FORBIDDEN);
+    let address = match ip {
+        Some(ip) => match app_state.database.get_ip_login_failures(ip, &since).await {
+            Ok((failures, last)) => {
+                thresholds.retry_after(thresholds.ip, failures, parse(last), now)
+            }
+            Err(e) => {
+                tracing::error!("Failed to count login failures from {}: {:?}", ip, e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    account.max(address)
+}
+
+/// Audit a login attempt. Failing to write the audit row doesn't fail the
+/// login.
+async fn record_login_attempt(app_state: &AppState, email: &str, ip: Option<&str>, outcome: &str) {
+    if let Err(e) = app_state
+        .database
+        .record_login_attempt(email, ip, outcome)
+        .await
+    {
+        tracing::error!("Failed to record login attempt for {}: {:?}", email, e);
     }
 }
 
-async fn create_user_handler(
+/// Open a session for a user who just signed in, returning an access token
+/// and the refresh token that keeps the session going
+async fn start_session(
+    app_state: &AppState,
+    user: &database::User,
+    headers: &HeaderMap,
+) -> Result<(String, String), StatusCode> {
+    let refresh_token = tokens::generate();
+    let user_agent = sessions::describe_client(
+        headers
+            .get(header::USER_AGENT)
+            .and_then(|user_agent| user_agent.to_str().ok()),
+    );
+
+    let session = app_state
+        .database
+        .create_session(
+            &user.id,
+            &tokens::hash(&refresh_token),
+            user_agent.as_deref(),
+            &sessions::session_expires_at(Utc::now()).to_rfc3339(),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create session for {}: {:?}", user.id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let (token, _) = user_jwt::generate_jwt(&user.id, &user.email, &session.id).map_err(|e| {
+        tracing::error!("Failed to generate JWT: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok((token, refresh_token))
+}
+
+/// Trade a refresh token for a new access token and a new refresh token.
+/// Each refresh token works once.
+async fn refresh_handler(
+    State(app_state): State<AppState>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<RefreshResponse>, StatusCode> {
+    let database = &app_state.database;
+    let refresh_token = tokens::generate();
+
+    let session = database
+        .rotate_session(
+            &tokens::hash(&payload.refresh_token),
+            &tokens::hash(&refresh_token),
+            &sessions::session_expires_at(Utc::now()).to_rfc3339(),
+        )
+        .await
+        .map_err(|e| match e.to_string().as_str() {
+            "404" => {
+                tracing::info!("Refused unknown or expired refresh token");
+                StatusCode::UNAUTHORIZED
+            }
+            _ => {
+                tracing::error!("Failed to refresh session: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    if session.revoked_at.is_some() {
+        // A refresh token was used twice, whoever holds the newer one loses
+        // the session as well
+        let until = sessions::revoked_until(Utc::now());
+        app_state.revocations.revoke(&session.id, until);
+        if let Err(e) = database.revoke_token(&session.id, until).await {
+            tracing::error!("Failed to store revocation of {}: {:?}", session.id, e);
+        }
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let user = database.get_user(&session.user_id).await.map_err(|e| {
+        tracing::error!("Failed to fetch user of session {}: {:?}", session.id, e);
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let (token, _) = user_jwt::generate_jwt(&user.id, &user.email, &session.id).map_err(|e| {
+        tracing::error!("Failed to generate JWT: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(RefreshResponse {
+        token,
+        refresh_token,
+    }))
+}
+
+/// Revoke sessions and put them on the in-memory revocation list, which the
+/// middleware checks on every request
+async fn revoke_sessions(
+    app_state: &AppState,
+    user_id: &str,
+    session_id: Option<&str>,
+    keep: Option<&str>,
+) -> Result<usize, StatusCode> {
+    let until = sessions::revoked_until(Utc::now());
+    let revoked = app_state
+        .database
+        .revoke_sessions(user_id, session_id, keep, until)
+        .await
+        .map_err(|e| match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => {
+                tracing::error!("Failed to revoke sessions of {}: {:?}", user_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    for id in &revoked {
+        app_state.revocations.revoke(id, until);
+    }
+    Ok(revoked.len())
+}
+
+async fn logout_handler(
     State(app_state): State<AppState>,
     Extension(user_context): Extension<UserContext>,
-    Json(payload): Json<CreateUserRequest>,
-) -> Result<Json<database::User>, StatusCode> {
+) -> Result<StatusCode, StatusCode> {
     let user_email = user_context.email.clone();
     let user_id = user_context.user_id.clone();
-    let database = &app_state.database;
-
-    println!("Creating new user: {}", payload.email);
 
     sentry::configure_scope(|scope| {
         scope.set_user(Some(sentry::User {
@@ -468,35 +1371,36 @@ async fn create_user_handler(
         scope.set_tag("http.method", "POST");
     });
 
-    tracing::info!("Creating new user: {}", payload.email);
+    tracing::info!(
+        "Logging out session {} of {}",
+        user_context.session_id,
+        user_id
+    );
 
-    let user = database::User {
-        id: payload.user_id,
-        email: payload.email,
-        created_at: Utc::now().to_rfc3339(),
-        password_hash: String::new(), // Legacy endpoint - password not used
-        auth_token: None,
-    };
+    // The token itself goes on the list too, in case its session was
+    // already gone
+    let until = sessions::revoked_until(Utc::now());
+    app_state.revocations.revoke(&user_context.token_id, until);
+    if let Err(e) = app_state
+        .database
+        .revoke_token(&user_context.token_id, until)
+        .await
+    {
+        tracing::error!("Failed to store revocation of token: {:?}", e);
+    }
 
-    match database.create_user(user.clone()).await {
-        Ok(_) => Ok(Json(user)),
-        Err(e) => {
-            tracing::error!("Failed to create user: {}", e);
-            match e.to_string().as_str() {
-                "409" => Err(StatusCode::BAD_REQUEST),
-                _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
-            }
-        }
+    match revoke_sessions(&app_state, &user_id, Some(&user_context.session_id), None).await {
+        Ok(_) | Err(StatusCode::NOT_FOUND) => Ok(StatusCode::NO_CONTENT),
+        Err(status) => Err(status),
     }
 }
 
-async fn links_handler(
+async fn sessions_handler(
     State(app_state): State<AppState>,
     Extension(user_context): Extension<UserContext>,
-) -> Result<Json<Vec<database::Link>>, StatusCode> {
+) -> Result<Json<Vec<database::Session>>, StatusCode> {
     let user_email = user_context.email.clone();
     let user_id = user_context.user_id.clone();
-    println!("Fetching links for user: {}", user_id);
 
     sentry::configure_scope(|scope| {
         scope.set_user(Some(sentry::User {
@@ -507,39 +1411,3269 @@ async fn links_handler(
         scope.set_tag("http.method", "GET");
     });
 
-    tracing::info!("Fetching links for user: {}", user_id);
+    let mut sessions = app_state
+        .database
+        .get_sessions(&user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch sessions of {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    for session in &mut sessions {
+        session.current = session.id == user_context.session_id;
+    }
+
+    Ok(Json(sessions))
+}
+
+async fn revoke_session_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    tracing::info!("User {} revoking session {}", user_id, session_id);
+
+    // Scoped to the user, someone else's session id is a 404
+    revoke_sessions(&app_state, &user_id, Some(&session_id), None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sign out everywhere except the session making the request
+async fn revoke_other_sessions_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    let revoked =
+        revoke_sessions(&app_state, &user_id, None, Some(&user_context.session_id)).await?;
+
+    tracing::info!("User {} revoked {} other sessions", user_id, revoked);
+
+    Ok(Json(json!({ "revoked": revoked })))
+}
+
+async fn api_tokens_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<Vec<database::ApiToken>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let api_tokens = app_state
+        .database
+        .get_api_tokens(&user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch API tokens of {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(api_tokens))
+}
+
+async fn create_api_token_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<CreateApiTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedApiToken>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
 
-    // Use app_state's database instance
     let database = &app_state.database;
 
-    let links = database.get_links(&user_id, "user").await.map_err(|e| {
-        tracing::error!("Failed to fetch links for user {}: {:?}", user_id, e);
+    let name = api_tokens::normalize_name(&payload.name).ok_or_else(|| {
+        tracing::error!("Invalid API token name: {:?}", payload.name);
+        StatusCode::BAD_REQUEST
+    })?;
+    let scopes = api_tokens::parse_scopes(&payload.scopes).ok_or_else(|| {
+        tracing::error!("Invalid API token scopes: {:?}", payload.scopes);
+        StatusCode::BAD_REQUEST
+    })?;
+    if payload
+        .expires_in_days
+        .is_some_and(|days| !(1..=api_tokens::MAX_EXPIRES_IN_DAYS).contains(&days))
+    {
+        tracing::error!(
+            "Invalid API token lifetime: {:?} days",
+            payload.expires_in_days
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let existing = database.get_api_tokens(&user_id).await.map_err(|e| {
+        tracing::error!("Failed to fetch API tokens of {}: {:?}", user_id, e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    if existing.len() >= api_tokens::MAX_TOKENS_PER_USER {
+        tracing::warn!("User {} has too many API tokens", user_id);
+        return Err(StatusCode::CONFLICT);
+    }
 
-    tracing::info!(
-        "Successfully fetched {} links for user {}",
-        links.len(),
-        user_id
-    );
-    Ok(Json(links))
+    let token = format!("{}{}", api_tokens::TOKEN_PREFIX, tokens::generate());
+    let now = Utc::now();
+    let api_token = database
+        .create_api_token(database::ApiToken {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.clone(),
+            name,
+            token_hash: tokens::hash(&token),
+            scopes: api_tokens::join_scopes(&scopes),
+            last_used_at: None,
+            expires_at: payload
+                .expires_in_days
+                .map(|days| (now + chrono::Duration::days(days)).to_rfc3339()),
+            created_at: now.to_rfc3339(),
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create API token for {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiToken { api_token, token }),
+    ))
+}
+
+async fn delete_api_token_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(token_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    // Scoped to the user, someone else's token id is a 404
+    app_state
+        .database
+        .delete_api_token(&user_id, &token_id)
+        .await
+        .map_err(|e| match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => {
+                tracing::error!("Failed to delete API token {}: {:?}", token_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Create a single-use token for `purpose` and email its link to the user
+async fn send_account_email(
+    app_state: &AppState,
+    user: &database::User,
+    purpose: Purpose,
+) -> Result<(), StatusCode> {
+    let token = tokens::generate();
+    app_state
+        .database
+        .create_email_token(
+            &user.id,
+            purpose,
+            &tokens::hash(&token),
+            &purpose.expires_at(Utc::now()).to_rfc3339(),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to create {} token for {}: {:?}",
+                purpose.as_str(),
+                user.id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    app_state
+        .mailer
+        .send(
+            &user.email,
+            purpose.email_subject(),
+            &purpose.email_body(&purpose.url(&token)),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to send {} email to {}: {:?}",
+                purpose.as_str(),
+                user.email,
+                e
+            );
+            StatusCode::BAD_GATEWAY
+        })
+}
+
+/// Redeem an emailed token, returning the user it was issued to
+async fn redeem_account_token(
+    database: &Database,
+    token: &str,
+    purpose: Purpose,
+) -> Result<String, StatusCode> {
+    let (user_id, expires_at) = database
+        .redeem_email_token(&tokens::hash(token), purpose)
+        .await
+        .map_err(|e| match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => {
+                tracing::error!("Failed to redeem {} token: {:?}", purpose.as_str(), e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    if invitations::is_expired(&expires_at, Utc::now()) {
+        tracing::info!("Expired {} token for user {}", purpose.as_str(), user_id);
+        return Err(StatusCode::GONE);
+    }
+
+    Ok(user_id)
+}
+
+// Password reset request handler. Always answers 202, before looking the
+// address up, so neither the status nor the response time tells which
+// addresses have accounts.
+async fn request_password_reset_handler(
+    State(app_state): State<AppState>,
+    Json(payload): Json<EmailRequest>,
+) -> StatusCode {
+    tracing::info!("Processing password reset request for: {}", payload.email);
+
+    tokio::spawn(async move {
+        match app_state
+            .database
+            .get_user_by_email(payload.email.trim())
+            .await
+        {
+            Ok(user) => {
+                if let Err(status) =
+                    send_account_email(&app_state, &user, Purpose::PasswordReset).await
+                {
+                    tracing::error!(
+                        "Failed to send password reset to {}: {}",
+                        user.email,
+                        status
+                    );
+                }
+            }
+            Err(e) => {
+                tracing::info!("No password reset for {}: {:?}", payload.email, e);
+            }
+        }
+    });
+
+    StatusCode::ACCEPTED
+}
+
+async fn reset_password_handler(
+    State(app_state): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<StatusCode, StatusCode> {
+    tracing::info!("Processing password reset");
+
+    if payload.password.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let database = &app_state.database;
+    let user_id = redeem_account_token(database, &payload.token, Purpose::PasswordReset).await?;
+
+    database
+        .reset_password(&user_id, &payload.password)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to reset password for {}: {:?}", user_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    // Whoever knew the old password shouldn't stay signed in
+    revoke_sessions(&app_state, &user_id, None, None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn verify_email_handler(
+    State(app_state): State<AppState>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<StatusCode, StatusCode> {
+    tracing::info!("Processing email verification");
+
+    let database = &app_state.database;
+    let user_id = redeem_account_token(database, &payload.token, Purpose::VerifyEmail).await?;
+
+    database.mark_email_verified(&user_id).await.map_err(|e| {
+        tracing::error!("Failed to verify email for {}: {:?}", user_id, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Resend the verification email. Like password resets it answers 202 right
+// away whether or not the address has an account.
+async fn resend_verification_handler(
+    State(app_state): State<AppState>,
+    Json(payload): Json<EmailRequest>,
+) -> StatusCode {
+    tracing::info!("Processing verification resend for: {}", payload.email);
+
+    if !app_state.verification.sends_email() {
+        return StatusCode::ACCEPTED;
+    }
+
+    tokio::spawn(async move {
+        match app_state
+            .database
+            .get_user_by_email(payload.email.trim())
+            .await
+        {
+            Ok(user) if user.email_verified_at.is_none() => {
+                if let Err(status) =
+                    send_account_email(&app_state, &user, Purpose::VerifyEmail).await
+                {
+                    tracing::error!(
+                        "Failed to resend verification to {}: {}",
+                        user.email,
+                        status
+                    );
+                }
+            }
+            Ok(_) => tracing::info!("{} is already verified", payload.email),
+            Err(e) => tracing::info!("No verification for {}: {:?}", payload.email, e),
+        }
+    });
+
+    StatusCode::ACCEPTED
+}
+
+// Staging login handler
+async fn staging_login_handler(
+    Json(payload): Json<StagingLoginRequest>,
+) -> Result<StatusCode, StatusCode> {
+    println!("Processing staging login request");
+
+    // Get the staging password from environment variables
+    let staging_password = match env::var("STAGING_PASSWORD") {
+        Ok(pwd) => pwd,
+        Err(_) => {
+            println!("STAGING_PASSWORD environment variable not set");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Simple password validation
+    if payload.password == staging_password {
+        println!("Staging login successful");
+        return Ok(StatusCode::OK);
+    } else {
+        println!("Invalid staging password provided");
+        return Err(StatusCode::FORBIDDEN);
+    }
+}
+
+async fn create_user_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<CreateUserRequest>,
+) -> Result<Json<database::User>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+    let database = &app_state.database;
+
+    println!("Creating new user: {}", payload.email);
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    tracing::info!("Creating new user: {}", payload.email);
+
+    // Only the account the token was issued for can be created
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: "user",
+            owner_id: &payload.user_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    let user = database::User {
+        id: payload.user_id,
+        email: payload.email,
+        created_at: Utc::now().to_rfc3339(),
+        password_hash: String::new(), // Legacy endpoint - password not used
+        email_verified_at: None,
+        auth_token: None,
+    };
+
+    match database.create_user(user.clone()).await {
+        Ok(_) => Ok(Json(user)),
+        Err(e) => {
+            tracing::error!("Failed to create user: {}", e);
+            match e.to_string().as_str() {
+                "409" => Err(StatusCode::BAD_REQUEST),
+                _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
+            }
+        }
+    }
+}
+
+async fn links_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Query(query): Query<LinksQuery>,
+) -> Result<Json<Vec<database::Link>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+    println!("Fetching links for user: {}", user_id);
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    tracing::info!("Fetching links for user: {}", user_id);
+
+    // Use app_state's database instance
+    let database = &app_state.database;
+
+    let tag_filter = tags::parse_tag_filter(query.tags.as_deref());
+
+    if let (Some(owner_type), Some(owner_id)) = (&query.owner_type, &query.owner_id) {
+        if !teams::is_shared_owner_type(owner_type) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        authz::authorize(
+            database,
+            &user_context,
+            Resource::Owner {
+                owner_type,
+                owner_id,
+            },
+            Access::Read,
+        )
+        .await?;
+
+        let links = database
+            .get_links(owner_id, owner_type, None, &tag_filter)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to fetch links for {} {}: {:?}",
+                    owner_type,
+                    owner_id,
+                    e
+                );
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        return Ok(Json(links));
+    }
+
+    let dashboard = match query.dashboard_id {
+        Some(dashboard_id) => database
+            .get_dashboard(&dashboard_id, &user_id)
+            .await
+            .map_err(|e| match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            })?,
+        None => active_dashboard(database, &user_id).await?,
+    };
+
+    let links = database
+        .get_links(&user_id, "user", Some(&dashboard.id), &tag_filter)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch links for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    tracing::info!(
+        "Successfully fetched {} links for user {}",
+        links.len(),
+        user_id
+    );
+    Ok(Json(links))
+}
+
+async fn create_link(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateLinkRequest>,
+) -> Result<(StatusCode, Json<database::Link>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+    let client = &app_state.client;
+    let database = &app_state.database;
+
+    println!(
+        "Creating new link for owner {}: {}",
+        payload.owner_id, payload.url
+    );
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    tracing::info!(
+        "Creating new link for owner {}: {}",
+        payload.owner_id,
+        payload.url
+    );
+
+    let url = if !payload.url.starts_with("https://") {
+        format!("https://{}", payload.url)
+    } else {
+        payload.url
+    };
+
+    // Validate URL format before proceeding
+    if let Err(e) = Url::parse(&url) {
+        tracing::error!("Invalid URL format: {} - {:?}", url, e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let link_tags = tags::normalize_tags(payload.tags);
+    if link_tags.len() > tags::MAX_TAGS_PER_LINK {
+        tracing::error!("Too many tags on new link: {}", link_tags.len());
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // The link needs a column, either an existing one by id or one by name
+    let column_type = columns::normalize_column_name(&payload.column_type).unwrap_or_default();
+    if payload.column_id.is_none() && column_type.is_empty() {
+        tracing::error!("New link has no column");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Links go to the user themselves, or a team they're an editor of
+    let owner = authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: &payload.owner_type,
+            owner_id: &payload.owner_id,
+        },
+        Access::Write,
+    )
+    .await?;
+
+    let metadata_on = headers
+        .get("X-Fetch-Metadata")
+        .and_then(|m| m.to_str().ok())
+        .map(|s| s.to_lowercase() == "true")
+        .unwrap_or(false);
+
+    // Session requests also carry the token in a custom header, personal
+    // access tokens were already checked by the middleware
+    if user_context.api_token_id.is_none() {
+        // Check for the custom authorization header
+        let auth_token = headers
+            .get("X-User-Authorization")
+            .ok_or_else(|| {
+                println!("Missing X-User-Authorization header");
+                StatusCode::UNAUTHORIZED
+            })?
+            .to_str()
+            .map_err(|e| {
+                println!("Invalid X-User-Authorization header: {:?}", e);
+                StatusCode::BAD_REQUEST
+            })?;
+
+        // Validate the JWT token
+        let user_claims = match user_jwt::validate_jwt(auth_token) {
+            Ok(claims) => claims,
+            Err(e) => {
+                println!("Invalid JWT token: {:?}", e);
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+        };
+
+        // Verify the user ID in the token matches the request user ID
+        if user_claims.user_id != user_id {
+            println!("Token user ID does not match request user ID");
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    // init metadata, retrieve from link's URL, else use defaults
+    let default_metadata = metadata::PageMetadata {
+        title: Some(url.clone()),
+        ..Default::default()
+    };
+    let metadata = if metadata_on {
+        match metadata::fetch(client, &url).await {
+            Ok(metadata) => metadata,
+            Err(e @ metadata::MetadataError::Unreachable(_)) => {
+                // If the site can't be reached at all, return it directly to the client
+                tracing::info!("Failed to fetch metadata for {}: {}", url, e);
+                return Err(StatusCode::BAD_GATEWAY);
+            }
+            Err(e) => {
+                // For any other errors, use default metadata
+                tracing::info!("Failed to fetch metadata for {}: {}", url, e);
+                default_metadata
+            }
+        }
+    } else {
+        default_metadata
+    };
+
+    // use the user's title, if empty use metadata, metadata will be
+    // the URL if metadata is not fetched
+    let title = if payload.title.as_deref() == Some("") {
+        metadata.title.clone().unwrap_or_else(|| url.clone())
+    } else {
+        payload
+            .title
+            .unwrap_or_else(|| metadata.title.clone().unwrap_or_else(|| url.clone()))
+    };
+
+    // grab the favicon if meta data is turned on the user
+    // did not provide one or just pass an empty string
+    let favicon = if payload.icon.is_none() && metadata_on {
+        get_favicon(State(client), database, &url, &metadata.icons)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting favicon: {:?}", e);
+            })
+            .unwrap_or_else(|_| "".to_string())
+    } else if payload.icon.is_some() {
+        store_inline_icon(database, payload.icon)
+            .await?
+            .unwrap_or_default()
+    } else {
+        "".to_string()
+    };
+
+    // use the user's description, or the metadata description
+    let description = if payload.description.as_deref() == Some("") {
+        metadata.description.unwrap_or_else(|| "".to_string())
+    } else {
+        payload
+            .description
+            .unwrap_or_else(|| metadata.description.unwrap_or_default())
+    };
+
+    let link = database::Link {
+        id: uuid::Uuid::new_v4().to_string(),
+        url: url,
+        description: Some(description),
+        created_at: Utc::now().to_rfc3339(),
+        title: title,
+        icon: Some(favicon),
+        order_index: payload.next_order_index,
+        owner_type: owner.owner_type,
+        owner_id: owner.owner_id,
+        column_type,
+        column_id: payload.column_id,
+        alias: None,
+        tags: link_tags,
+    };
+
+    let link = match database.create_link(link).await {
+        Ok(link) => link,
+        Err(e) => {
+            tracing::error!("Failed to create link in database: {:?}", e);
+            println!("Failed to create link in database: {:?}", e);
+            return Err(match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        }
+    };
+
+    tracing::info!("Successfully created link with ID: {}", link.id);
+    Ok((StatusCode::CREATED, Json(link)))
+}
+
+async fn import_links_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<ImportLinksRequest>,
+) -> Result<Json<ImportLinksResponse>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+    println!("Importing bookmarks for user: {}", user_id);
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    tracing::info!("Importing bookmarks for user: {}", user_id);
+
+    let bookmarks = bookmarks::parse(
+        &payload.content,
+        payload.format.unwrap_or(bookmarks::BookmarkFormat::Auto),
+    )
+    .map_err(|e| {
+        tracing::warn!("Failed to parse bookmark import: {:?}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let database = &app_state.database;
+
+    // Imports land in the active dashboard, only its links count as duplicates
+    let dashboard = active_dashboard(database, &user_id).await?;
+
+    let existing = database
+        .get_links(&user_id, "user", Some(&dashboard.id), &[])
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch links for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // Trivial differences (trailing slash, host casing) still count as
+    // duplicates
+    let mut seen: HashSet<String> = existing
+        .iter()
+        .map(|l| bookmarks::dedupe_key(&l.url))
+        .collect();
+
+    // New links go to the end of their column
+    let mut next_order = next_order_indexes(&existing);
+
+    let default_column = payload
+        .default_column
+        .filter(|c| !c.trim().is_empty())
+        .unwrap_or_else(|| "default".to_string());
+
+    let mut imported = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut invalid = Vec::new();
+
+    for bookmark in bookmarks {
+        let parsed = match Url::parse(&bookmark.url) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+            Ok(url) => {
+                invalid.push(ImportIssue {
+                    title: bookmark.title,
+                    url: bookmark.url,
+                    reason: format!("unsupported scheme: {}", url.scheme()),
+                });
+                continue;
+            }
+            Err(e) => {
+                invalid.push(ImportIssue {
+                    title: bookmark.title,
+                    url: bookmark.url,
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let url = parsed.to_string();
+        if !seen.insert(bookmarks::dedupe_key(&url)) {
+            duplicates.push(ImportIssue {
+                title: bookmark.title,
+                url,
+                reason: "link already exists".to_string(),
+            });
+            continue;
+        }
+
+        let column_type = bookmark.folder.unwrap_or_else(|| default_column.clone());
+        let order_index = next_order.entry(column_type.clone()).or_insert(0);
+
+        imported.push(database::Link {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: if bookmark.title.is_empty() {
+                url.clone()
+            } else {
+                bookmark.title
+            },
+            url,
+            // An icon we can't store shouldn't cost the user the bookmark
+            icon: store_inline_icon(database, bookmark.icon)
+                .await
+                .unwrap_or_default(),
+            order_index: *order_index,
+            owner_type: "user".to_string(),
+            owner_id: user_id.clone(),
+            created_at: Utc::now().to_rfc3339(),
+            description: bookmark.description,
+            column_type,
+            column_id: None,
+            alias: None,
+            tags: Vec::new(),
+        });
+        *order_index += 1;
+    }
+
+    if let Err(e) = database.create_links(&imported).await {
+        tracing::error!("Failed to import links: {:?}", e);
+        println!("Failed to import links: {:?}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    tracing::info!(
+        "Imported {} links for user {} ({} duplicates, {} invalid)",
+        imported.len(),
+        user_id,
+        duplicates.len(),
+        invalid.len()
+    );
+
+    Ok(Json(ImportLinksResponse {
+        imported,
+        duplicates,
+        invalid,
+    }))
+}
+
+async fn update_link(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<UpdateLinkRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+    println!("Updating link: {}", payload.id);
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    tracing::info!("Updating link: {}", payload.id);
+
+    // Validate URL format if provided
+    if let Some(ref url) = payload.url {
+        if let Err(e) = Url::parse(url) {
+            tracing::error!("Invalid URL format in update: {} - {:?}", url, e);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let link_tags = payload.tags.map(tags::normalize_tags);
+    if link_tags
+        .as_ref()
+        .is_some_and(|t| t.len() > tags::MAX_TAGS_PER_LINK)
+    {
+        tracing::error!("Too many tags in update for link: {}", payload.id);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let column_type = payload
+        .column_type
+        .as_deref()
+        .and_then(columns::normalize_column_name)
+        .unwrap_or_default();
+    if payload.column_id.is_none() && column_type.is_empty() {
+        tracing::error!("Update for link {} has no column", payload.id);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Use app_state's database instance
+    let database = &app_state.database;
+
+    // Only the owner, or an editor of the team that owns the link, may change it
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Link(&payload.id),
+        Access::Write,
+    )
+    .await?;
+
+    let link = database::Link {
+        id: payload.id.clone(),
+        url: payload.url.clone().unwrap_or_else(|| "".to_string()),
+        description: payload.description.clone(),
+        title: payload.title.clone().unwrap(),
+        icon: store_inline_icon(database, payload.icon.clone()).await?,
+        column_type,
+        column_id: payload.column_id.clone(),
+        created_at: Utc::now().to_rfc3339(),
+        // Edits keep the link's position, PATCH /links/order moves it
+        order_index: 0,
+        owner_type: "".to_string(),
+        owner_id: "".to_string(),
+        alias: None,
+        tags: Vec::new(),
+    };
+
+    if let Err(e) = database.update_link(link, link_tags.as_deref()).await {
+        tracing::error!("Failed to update link: {:?}", e);
+        println!("Failed to update link: {:?}", e);
+        return Err(match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        });
+    }
+
+    tracing::info!("Successfully updated link {}", payload.id);
+    Ok(StatusCode::OK)
+}
+
+async fn reorder_links_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(mut payload): Json<ReorderLinksRequest>,
+) -> Result<Json<Vec<database::Link>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PATCH");
+    });
+
+    tracing::info!(
+        "Reordering {} links for user {}",
+        payload.links.len(),
+        user_id
+    );
+
+    let mut seen = HashSet::new();
+    for position in &mut payload.links {
+        if !seen.insert(position.link_id.clone()) {
+            tracing::error!("Link {} is listed more than once", position.link_id);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        if let Some(column_type) = &position.column_type {
+            position.column_type =
+                Some(columns::normalize_column_name(column_type).ok_or(StatusCode::BAD_REQUEST)?);
+        }
+    }
+
+    let owner = match (&payload.owner_type, &payload.owner_id) {
+        (Some(owner_type), Some(owner_id)) => {
+            authz::authorize(
+                &app_state.database,
+                &user_context,
+                Resource::Owner {
+                    owner_type,
+                    owner_id,
+                },
+                Access::Write,
+            )
+            .await?
+        }
+        (None, None) => authz::Owner {
+            owner_type: "user".to_string(),
+            owner_id: user_id.clone(),
+        },
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let links = app_state
+        .database
+        .reorder_links(&owner.owner_id, &owner.owner_type, &payload.links)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to reorder links for user {}: {:?}", user_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    tracing::info!("Successfully reordered links for user {}", user_id);
+    Ok(Json(links))
+}
+
+async fn delete_link(
+    State(app_state): State<AppState>,
+    Path(link_id): Path<String>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    // Use app_state's database instance
+    let database = &app_state.database;
+
+    // Only the owner, or an editor of the team that owns the link, may remove it
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Link(&link_id),
+        Access::Write,
+    )
+    .await?;
+
+    // Delete the link
+    if let Err(e) = database.delete_link(&link_id).await {
+        tracing::error!("Error deleting link: {:?}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    tracing::info!("Successfully deleted link: {}", link_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn search_links_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Query(query): Query<LinkSearchQuery>,
+) -> Result<Json<Vec<database::LinkSearchHit>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    tracing::info!("Searching links for user {}: {}", user_id, query.q);
+
+    // Nothing searchable in the query, nothing can match
+    let Some(fts_query) = search::fts_query(&query.q) else {
+        return Ok(Json(Vec::new()));
+    };
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    let mut hits = app_state
+        .database
+        .search_links(&user_id, "user", &fts_query, limit)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to search links for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    for hit in &mut hits {
+        hit.title_highlight = search::render_highlight(&hit.title_highlight);
+        hit.url_highlight = search::render_highlight(&hit.url_highlight);
+        hit.snippet = search::render_highlight(&hit.snippet);
+    }
+
+    Ok(Json(hits))
+}
+
+async fn dashboards_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<Vec<database::Dashboard>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let database = &app_state.database;
+
+    // Makes sure there's at least the default dashboard to list
+    active_dashboard(database, &user_id).await?;
+
+    let dashboards = database.get_dashboards(&user_id).await.map_err(|e| {
+        tracing::error!("Failed to fetch dashboards for user {}: {:?}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(dashboards))
+}
+
+async fn create_dashboard_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<CreateDashboardRequest>,
+) -> Result<(StatusCode, Json<database::Dashboard>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    let name =
+        dashboards::normalize_dashboard_name(&payload.name).ok_or(StatusCode::BAD_REQUEST)?;
+    let settings_overrides = match payload.settings_overrides {
+        Some(overrides) => {
+            dashboards::parse_settings_overrides(&overrides).ok_or(StatusCode::BAD_REQUEST)?
+        }
+        None => "{}".to_string(),
+    };
+
+    tracing::info!("Creating dashboard for user {}: {}", user_id, name);
+
+    let dashboard = database::Dashboard {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.clone(),
+        name,
+        settings_overrides,
+        created_at: Utc::now().to_rfc3339(),
+        active: false,
+    };
+
+    let dashboard = app_state
+        .database
+        .create_dashboard(dashboard)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create dashboard for user {}: {:?}", user_id, e);
+            match e.to_string().as_str() {
+                "409" => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok((StatusCode::CREATED, Json(dashboard)))
+}
+
+async fn update_dashboard_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(dashboard_id): Path<String>,
+    Json(payload): Json<UpdateDashboardRequest>,
+) -> Result<Json<database::Dashboard>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    tracing::info!("Updating dashboard {} for user {}", dashboard_id, user_id);
+
+    let database = &app_state.database;
+
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: "dashboard",
+            owner_id: &dashboard_id,
+        },
+        Access::Write,
+    )
+    .await?;
+
+    let to_status = |e: anyhow::Error| {
+        tracing::error!("Failed to update dashboard {}: {:?}", dashboard_id, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            "409" => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    };
+
+    let mut dashboard = database
+        .get_dashboard(&dashboard_id, &user_id)
+        .await
+        .map_err(to_status)?;
+
+    if let Some(name) = payload.name {
+        dashboard.name =
+            dashboards::normalize_dashboard_name(&name).ok_or(StatusCode::BAD_REQUEST)?;
+    }
+    if let Some(overrides) = payload.settings_overrides {
+        dashboard.settings_overrides =
+            dashboards::parse_settings_overrides(&overrides).ok_or(StatusCode::BAD_REQUEST)?;
+    }
+
+    database
+        .update_dashboard(&dashboard)
+        .await
+        .map_err(to_status)?;
+
+    Ok(Json(dashboard))
+}
+
+async fn clone_dashboard_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(dashboard_id): Path<String>,
+    Json(payload): Json<CloneDashboardRequest>,
+) -> Result<(StatusCode, Json<database::Dashboard>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    tracing::info!("Cloning dashboard {} for user {}", dashboard_id, user_id);
+
+    let database = &app_state.database;
+
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: "dashboard",
+            owner_id: &dashboard_id,
+        },
+        Access::Read,
+    )
+    .await?;
+
+    let to_status = |e: anyhow::Error| {
+        tracing::error!("Failed to clone dashboard {}: {:?}", dashboard_id, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            "409" => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    };
+
+    let name = match payload.name {
+        Some(name) => dashboards::normalize_dashboard_name(&name).ok_or(StatusCode::BAD_REQUEST)?,
+        None => {
+            let existing = database.get_dashboards(&user_id).await.map_err(to_status)?;
+            let source = existing
+                .iter()
+                .find(|d| d.id == dashboard_id)
+                .ok_or(StatusCode::NOT_FOUND)?;
+            let taken: Vec<String> = existing.iter().map(|d| d.name.clone()).collect();
+            dashboards::copy_name(&source.name, &taken)
+        }
+    };
+
+    let dashboard = database
+        .clone_dashboard(&dashboard_id, &user_id, &name)
+        .await
+        .map_err(to_status)?;
+
+    Ok((StatusCode::CREATED, Json(dashboard)))
+}
+
+async fn activate_dashboard_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(dashboard_id): Path<String>,
+) -> Result<Json<database::Dashboard>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    tracing::info!("Switching user {} to dashboard {}", user_id, dashboard_id);
+
+    let database = &app_state.database;
+
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: "dashboard",
+            owner_id: &dashboard_id,
+        },
+        Access::Read,
+    )
+    .await?;
+
+    let to_status = |e: anyhow::Error| {
+        tracing::error!("Failed to switch to dashboard {}: {:?}", dashboard_id, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    };
+
+    database
+        .set_active_dashboard(&user_id, &dashboard_id)
+        .await
+        .map_err(to_status)?;
+    let dashboard = database
+        .get_dashboard(&dashboard_id, &user_id)
+        .await
+        .map_err(to_status)?;
+
+    Ok(Json(dashboard))
+}
+
+async fn delete_dashboard_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(dashboard_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    tracing::info!("Deleting dashboard {} for user {}", dashboard_id, user_id);
+
+    authz::authorize(
+        &app_state.database,
+        &user_context,
+        Resource::Owner {
+            owner_type: "dashboard",
+            owner_id: &dashboard_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    // Deleting the active dashboard is fine, the user falls back to their oldest one
+    app_state
+        .database
+        .delete_dashboard(&dashboard_id, &user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete dashboard {}: {:?}", dashboard_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                "409" => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The user's active dashboard, created on first use
+async fn active_dashboard(
+    database: &Database,
+    user_id: &str,
+) -> Result<database::Dashboard, StatusCode> {
+    database.get_active_dashboard(user_id).await.map_err(|e| {
+        tracing::error!(
+            "Failed to fetch active dashboard for user {}: {:?}",
+            user_id,
+            e
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn columns_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<Vec<database::Column>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let dashboard = active_dashboard(&app_state.database, &user_id).await?;
+
+    let columns = app_state
+        .database
+        .get_columns(&dashboard.id, "dashboard")
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch columns for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(columns))
+}
+
+async fn create_column_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<CreateColumnRequest>,
+) -> Result<(StatusCode, Json<database::Column>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    let name = columns::normalize_column_name(&payload.name).ok_or(StatusCode::BAD_REQUEST)?;
+    if payload
+        .color
+        .as_deref()
+        .is_some_and(|c| !columns::is_valid_color(c))
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    tracing::info!("Creating column for user {}: {}", user_id, name);
+
+    let dashboard = active_dashboard(&app_state.database, &user_id).await?;
+
+    let column = database::Column {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        // appended after the dashboard's other columns
+        position: 0,
+        color: payload.color,
+        collapsed: payload.collapsed,
+        owner_type: "dashboard".to_string(),
+        owner_id: dashboard.id,
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    let column = app_state
+        .database
+        .create_column(column)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create column for user {}: {:?}", user_id, e);
+            match e.to_string().as_str() {
+                "409" => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok((StatusCode::CREATED, Json(column)))
+}
+
+async fn update_column_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(column_id): Path<String>,
+    Json(payload): Json<UpdateColumnRequest>,
+) -> Result<Json<database::Column>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    tracing::info!("Updating column {} for user {}", column_id, user_id);
+
+    let database = &app_state.database;
+
+    let to_status = |e: anyhow::Error| {
+        tracing::error!("Failed to update column {}: {:?}", column_id, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            "409" => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    };
+
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Column(&column_id),
+        Access::Write,
+    )
+    .await?;
+
+    let mut column = database
+        .get_column_by_id(&column_id)
+        .await
+        .map_err(to_status)?;
+
+    if let Some(name) = payload.name {
+        column.name = columns::normalize_column_name(&name).ok_or(StatusCode::BAD_REQUEST)?;
+    }
+    match payload.color {
+        Some(color) if color.is_empty() => column.color = None,
+        Some(color) if columns::is_valid_color(&color) => column.color = Some(color),
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+        None => {}
+    }
+    if let Some(collapsed) = payload.collapsed {
+        column.collapsed = collapsed;
+    }
+
+    database.update_column(&column).await.map_err(to_status)?;
+
+    Ok(Json(column))
+}
+
+async fn delete_column_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(column_id): Path<String>,
+    Query(query): Query<DeleteColumnQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    tracing::info!("Deleting column {} for user {}", column_id, user_id);
+
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Column(&column_id),
+        Access::Write,
+    )
+    .await?;
+
+    let column = database.get_column_by_id(&column_id).await.map_err(|e| {
+        tracing::error!("Failed to fetch column {}: {:?}", column_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Links can only move to another column of the same owner
+    database
+        .delete_column(
+            &column_id,
+            &column.owner_id,
+            &column.owner_type,
+            query.move_to.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete column {}: {:?}", column_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                "409" => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn reorder_columns_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<ReorderColumnsRequest>,
+) -> Result<Json<Vec<database::Column>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    tracing::info!("Reordering columns for user {}", user_id);
+
+    let dashboard = active_dashboard(&app_state.database, &user_id).await?;
+
+    let columns = app_state
+        .database
+        .reorder_columns(&dashboard.id, "dashboard", &payload.column_ids)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to reorder columns for user {}: {:?}", user_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(Json(columns))
+}
+
+async fn tags_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<Vec<database::Tag>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let tags = app_state
+        .database
+        .get_tags(&user_id, "user")
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch tags for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(tags))
+}
+
+async fn create_tag_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<TagRequest>,
+) -> Result<(StatusCode, Json<database::Tag>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    let name = tags::normalize_tag_name(&payload.name).ok_or(StatusCode::BAD_REQUEST)?;
+    tracing::info!("Creating tag for user {}: {}", user_id, name);
+
+    let tag = database::Tag {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        owner_type: "user".to_string(),
+        owner_id: user_id.clone(),
+        created_at: Utc::now().to_rfc3339(),
+        link_count: 0,
+    };
+
+    let tag = app_state.database.create_tag(tag).await.map_err(|e| {
+        tracing::error!("Failed to create tag for user {}: {:?}", user_id, e);
+        match e.to_string().as_str() {
+            "409" => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    })?;
+
+    Ok((StatusCode::CREATED, Json(tag)))
+}
+
+async fn rename_tag_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(tag_id): Path<String>,
+    Json(payload): Json<TagRequest>,
+) -> Result<Json<database::Tag>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    let name = tags::normalize_tag_name(&payload.name).ok_or(StatusCode::BAD_REQUEST)?;
+    tracing::info!("Renaming tag {} for user {}: {}", tag_id, user_id, name);
+
+    let database = &app_state.database;
+
+    // A name clash means the user wants a merge, which is a separate call
+    let to_status = |e: anyhow::Error| {
+        tracing::error!("Failed to rename tag {}: {:?}", tag_id, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            "409" => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    };
+
+    database
+        .rename_tag(&tag_id, &user_id, "user", &name)
+        .await
+        .map_err(to_status)?;
+    let tag = database
+        .get_tag(&tag_id, &user_id, "user")
+        .await
+        .map_err(to_status)?;
+
+    Ok(Json(tag))
+}
+
+async fn merge_tags_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<MergeTagsRequest>,
+) -> Result<Json<database::Tag>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    tracing::info!(
+        "Merging tags {:?} into {} for user {}",
+        payload.sources,
+        payload.target,
+        user_id
+    );
+
+    let database = &app_state.database;
+
+    let to_status = |e: anyhow::Error| {
+        tracing::error!("Failed to merge tags into {}: {:?}", payload.target, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    };
+
+    database
+        .merge_tags(&payload.target, &payload.sources, &user_id, "user")
+        .await
+        .map_err(to_status)?;
+    let tag = database
+        .get_tag(&payload.target, &user_id, "user")
+        .await
+        .map_err(to_status)?;
+
+    Ok(Json(tag))
+}
+
+async fn delete_tag_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(tag_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    tracing::info!("Deleting tag {} for user {}", tag_id, user_id);
+
+    // Links keep existing, they just lose the tag
+    app_state
+        .database
+        .delete_tag(&tag_id, &user_id, "user")
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete tag {}: {:?}", tag_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn search_engines_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<Vec<database::SearchEngine>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let engines = app_state
+        .database
+        .get_search_engines(&user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to fetch search engines for user {}: {:?}",
+                user_id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(engines))
+}
+
+async fn create_search_engine_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<SearchEngineRequest>,
+) -> Result<(StatusCode, Json<database::SearchEngine>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    let engine = payload.into_engine(uuid::Uuid::new_v4().to_string(), &user_id)?;
+    tracing::info!(
+        "Creating search engine for user {}: {}",
+        user_id,
+        engine.name
+    );
+
+    let engine = app_state
+        .database
+        .create_search_engine(&engine)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to create search engine for user {}: {:?}",
+                user_id,
+                e
+            );
+            match e.to_string().as_str() {
+                "409" => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok((StatusCode::CREATED, Json(engine)))
+}
+
+async fn update_search_engine_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(engine_id): Path<String>,
+    Json(payload): Json<SearchEngineRequest>,
+) -> Result<Json<database::SearchEngine>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    let engine = payload.into_engine(engine_id.clone(), &user_id)?;
+    tracing::info!("Updating search engine {} for user {}", engine_id, user_id);
+
+    let engine = app_state
+        .database
+        .update_search_engine(&engine)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update search engine {}: {:?}", engine_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                "409" => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(Json(engine))
+}
+
+async fn delete_search_engine_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(engine_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    tracing::info!("Deleting search engine {} for user {}", engine_id, user_id);
+
+    app_state
+        .database
+        .delete_search_engine(&engine_id, &user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete search engine {}: {:?}", engine_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn bangs_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<Vec<database::Bang>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let bangs = app_state.database.get_bangs().await.map_err(|e| {
+        tracing::error!("Failed to fetch bangs: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(bangs))
+}
+
+/// Resolve a search bar query, bangs included, so every device sends it to
+/// the same place
+async fn resolve_search_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Query(params): Query<ResolveQuery>,
+) -> Result<Json<ResolveResponse>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    if params.q.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let target = web_search::resolve_query(&app_state.database, Some(&user_id), &params.q)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error resolving search {:?}: {:?}", params.q, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let kind = match target {
+        web_search::Target::Url(_) => "url",
+        web_search::Target::Search(_) => "search",
+    };
+    Ok(Json(ResolveResponse {
+        url: target.url().to_string(),
+        kind,
+    }))
+}
+
+/// Follow a go link, `/go/alias/rest?query` with the rest of the path and
+/// the query passed through. Unknown aliases go to the search page.
+async fn go_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(path): Path<GoPath>,
+    RawQuery(query): RawQuery,
+) -> Result<Json<GoResponse>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let link = match golinks::normalize_alias(&path.alias) {
+        Some(alias) => app_state
+            .database
+            .resolve_alias(&user_id, &alias)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to resolve alias {:?}: {:?}", alias, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?,
+        None => None,
+    };
+
+    let url = match &link {
+        Some(link) => golinks::target_url(&link.url, &path.rest, query.as_deref()),
+        None => {
+            tracing::info!("No go link {:?} for user {}", path.alias, user_id);
+            golinks::fallback_url(&invitations::app_url(), &path.alias, &path.rest)
+        }
+    };
+    Ok(Json(GoResponse { url, link }))
+}
+
+async fn go_links_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<Vec<database::GoLink>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let go_links = app_state
+        .database
+        .get_go_links(&user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch go links for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(go_links))
+}
+
+/// Whether an alias is valid and free for the user, or for one of their
+/// teams or organizations
+async fn check_alias_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(alias): Path<String>,
+    Query(params): Query<AliasCheckQuery>,
+) -> Result<Json<AliasCheckResponse>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let database = &app_state.database;
+    let owner = match (&params.owner_type, &params.owner_id) {
+        (Some(owner_type), Some(owner_id)) => {
+            authz::authorize(
+                database,
+                &user_context,
+                Resource::Owner {
+                    owner_type,
+                    owner_id,
+                },
+                Access::Read,
+            )
+            .await?
+        }
+        (None, None) => authz::Owner {
+            owner_type: "user".to_string(),
+            owner_id: user_id.clone(),
+        },
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let Some(alias) = golinks::normalize_alias(&alias) else {
+        return Ok(Json(AliasCheckResponse {
+            alias: None,
+            valid: false,
+            available: false,
+            link: None,
+        }));
+    };
+
+    let lookup = async {
+        let taken = database
+            .find_alias(&owner.owner_type, &owner.owner_id, &alias)
+            .await?;
+        let link = database.resolve_alias(&user_id, &alias).await?;
+        anyhow::Ok((taken, link))
+    };
+    let (taken, link) = lookup.await.map_err(|e| {
+        tracing::error!("Failed to check alias {:?}: {:?}", alias, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(AliasCheckResponse {
+        alias: Some(alias),
+        valid: true,
+        available: taken.is_none(),
+        link,
+    }))
+}
+
+/// Reserve an alias for a link, unique among its owner's links
+async fn set_link_alias_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(link_id): Path<String>,
+    Json(payload): Json<LinkAliasRequest>,
+) -> Result<Json<database::Link>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    authz::authorize(
+        &app_state.database,
+        &user_context,
+        Resource::Link(&link_id),
+        Access::Write,
+    )
+    .await?;
+
+    let alias = golinks::normalize_alias(&payload.alias).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let link = app_state
+        .database
+        .set_link_alias(&link_id, Some(&alias))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to set alias of link {}: {:?}", link_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                "409" => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(Json(link))
+}
+
+async fn delete_link_alias_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(link_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    authz::authorize(
+        &app_state.database,
+        &user_context,
+        Resource::Link(&link_id),
+        Access::Write,
+    )
+    .await?;
+
+    app_state
+        .database
+        .set_link_alias(&link_id, None)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to remove alias of link {}: {:?}", link_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn search_history_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Query(params): Query<SearchHistoryQuery>,
+) -> Result<Json<Vec<database::SearchHistoryEntry>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let prefix = suggestions::normalize_query(params.q.as_deref().unwrap_or_default());
+    let limit = params
+        .limit
+        .unwrap_or(50)
+        .clamp(1, search_history::MAX_ENTRIES);
+
+    let entries = app_state
+        .database
+        .get_search_history(&user_id, &prefix, app_state.history_retention_days, limit)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to fetch search history for user {}: {:?}",
+                user_id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(entries))
+}
+
+/// Record a search from the search bar, 204 when history is turned off
+async fn record_search_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<RecordSearchRequest>,
+) -> Result<Response, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    if payload.query.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let entry = record_search(&app_state, &user_id, &payload.query)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to record search for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(match entry {
+        Some(entry) => (StatusCode::CREATED, Json(entry)).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    })
+}
+
+async fn delete_search_history_entry_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(entry_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    app_state
+        .database
+        .delete_search_history_entry(&entry_id, &user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to delete search history entry {}: {:?}",
+                entry_id,
+                e
+            );
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn clear_search_history_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    app_state
+        .database
+        .clear_search_history(&user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to clear search history for user {}: {:?}",
+                user_id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn organizations_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<Vec<database::Organization>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let organizations = app_state
+        .database
+        .get_organizations(&user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to fetch organizations for user {}: {:?}",
+                user_id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(organizations))
+}
+
+async fn create_organization_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<NameRequest>,
+) -> Result<(StatusCode, Json<database::Organization>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    let name = teams::normalize_name(&payload.name).ok_or(StatusCode::BAD_REQUEST)?;
+
+    tracing::info!("Creating organization for user {}: {}", user_id, name);
+
+    let database = &app_state.database;
+    let plan_id = creator_plan_id(database, &user_id).await;
+
+    let organization_id = database
+        .create_organization(&name, &user_id, &plan_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to create organization for user {}: {:?}",
+                user_id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let organization = database
+        .get_organizations(&user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to fetch organizations for user {}: {:?}",
+                user_id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .find(|o| o.id == organization_id)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(organization)))
+}
+
+async fn rename_organization_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(organization_id): Path<String>,
+    Json(payload): Json<NameRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    let name = teams::normalize_name(&payload.name).ok_or(StatusCode::BAD_REQUEST)?;
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: "organization",
+            owner_id: &organization_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    database
+        .rename_organization(&organization_id, &name)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to rename organization {}: {:?}", organization_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn delete_organization_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(organization_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    tracing::info!(
+        "Deleting organization {} for user {}",
+        organization_id,
+        user_id
+    );
+
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: "organization",
+            owner_id: &organization_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    // Takes the organization's teams and all their shared links with it
+    database
+        .delete_organization(&organization_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete organization {}: {:?}", organization_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn teams_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<Vec<database::Team>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    let teams = app_state.database.get_teams(&user_id).await.map_err(|e| {
+        tracing::error!("Failed to fetch teams for user {}: {:?}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(teams))
+}
+
+async fn create_team_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<CreateTeamRequest>,
+) -> Result<(StatusCode, Json<database::Team>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    let name = teams::normalize_name(&payload.name).ok_or(StatusCode::BAD_REQUEST)?;
+
+    tracing::info!("Creating team for user {}: {}", user_id, name);
+
+    let database = &app_state.database;
+
+    // Only organization admins can add teams to it
+    if let Some(organization_id) = &payload.organization_id {
+        authz::authorize(
+            database,
+            &user_context,
+            Resource::Owner {
+                owner_type: "organization",
+                owner_id: organization_id,
+            },
+            Access::Admin,
+        )
+        .await?;
+    }
+
+    let plan_id = creator_plan_id(database, &user_id).await;
+    let team_id = database
+        .create_team(
+            &name,
+            &user_id,
+            &plan_id,
+            payload.organization_id.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create team for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let team = database
+        .get_teams(&user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch teams for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .find(|t| t.id == team_id)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(team)))
+}
+
+async fn rename_team_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(team_id): Path<String>,
+    Json(payload): Json<NameRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    let name = teams::normalize_name(&payload.name).ok_or(StatusCode::BAD_REQUEST)?;
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: "team",
+            owner_id: &team_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    database.rename_team(&team_id, &name).await.map_err(|e| {
+        tracing::error!("Failed to rename team {}: {:?}", team_id, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    })?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn delete_team_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(team_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    tracing::info!("Deleting team {} for user {}", team_id, user_id);
+
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: "team",
+            owner_id: &team_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    database.delete_team(&team_id).await.map_err(|e| {
+        tracing::error!("Failed to delete team {}: {:?}", team_id, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn members_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path((entity_type, entity_id)): Path<(String, String)>,
+) -> Result<Json<Vec<database::Member>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    if !teams::is_shared_owner_type(&entity_type) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: &entity_type,
+            owner_id: &entity_id,
+        },
+        Access::Read,
+    )
+    .await?;
+
+    let members = database
+        .get_members(&entity_type, &entity_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch members of {}: {:?}", entity_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(members))
+}
+
+async fn add_member_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path((entity_type, entity_id)): Path<(String, String)>,
+    Json(payload): Json<AddMemberRequest>,
+) -> Result<(StatusCode, Json<database::Member>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    if !teams::is_shared_owner_type(&entity_type) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let role = Role::parse(&payload.role).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: &entity_type,
+            owner_id: &entity_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    tracing::info!(
+        "Adding {} as {} to {} {}",
+        payload.email,
+        role.as_str(),
+        entity_type,
+        entity_id
+    );
+
+    // Members have to have an account already
+    let member = database
+        .get_user_by_email(payload.email.trim())
+        .await
+        .map_err(|e| match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    database
+        .add_member(database::UserMembership {
+            user_id: member.id.clone(),
+            entity_id: entity_id.clone(),
+            entity_type: entity_type.clone(),
+            role: role.as_str().to_string(),
+            created_at: Utc::now().to_rfc3339(),
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to add member to {}: {:?}", entity_id, e);
+            match e.to_string().as_str() {
+                "409" => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(database::Member {
+            user_id: member.id,
+            email: member.email,
+            role: role.as_str().to_string(),
+            created_at: Utc::now().to_rfc3339(),
+        }),
+    ))
+}
+
+async fn update_member_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path((entity_type, entity_id, member_id)): Path<(String, String, String)>,
+    Json(payload): Json<UpdateMemberRequest>,
+) -> Result<Json<database::UserMembership>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "PUT");
+    });
+
+    if !teams::is_shared_owner_type(&entity_type) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let role = Role::parse(&payload.role).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: &entity_type,
+            owner_id: &entity_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    if role != Role::Admin {
+        ensure_admin_remains(database, &entity_type, &entity_id, &member_id).await?;
+    }
+
+    tracing::info!(
+        "Changing role of {} in {} {} to {}",
+        member_id,
+        entity_type,
+        entity_id,
+        role.as_str()
+    );
+
+    let membership = database
+        .update_member_role(&member_id, &entity_id, role.as_str())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update member {}: {:?}", member_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(Json(membership))
+}
+
+async fn remove_member_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path((entity_type, entity_id, member_id)): Path<(String, String, String)>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    if !teams::is_shared_owner_type(&entity_type) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Anyone can leave, removing others takes an admin
+    let database = &app_state.database;
+    let required = if member_id == user_id {
+        Access::Read
+    } else {
+        Access::Admin
+    };
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: &entity_type,
+            owner_id: &entity_id,
+        },
+        required,
+    )
+    .await?;
+    ensure_admin_remains(database, &entity_type, &entity_id, &member_id).await?;
+
+    tracing::info!("Removing {} from {} {}", member_id, entity_type, entity_id);
+
+    database
+        .remove_member(&member_id, &entity_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to remove member {}: {:?}", member_id, e);
+            match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn invitations_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path((entity_type, entity_id)): Path<(String, String)>,
+) -> Result<Json<Vec<database::Invitation>>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    if !teams::is_shared_owner_type(&entity_type) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: &entity_type,
+            owner_id: &entity_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    let invitations = database
+        .get_invitations(&entity_type, &entity_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch invitations for {}: {:?}", entity_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(invitations))
+}
+
+async fn create_invitation_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path((entity_type, entity_id)): Path<(String, String)>,
+    Json(payload): Json<InviteRequest>,
+) -> Result<(StatusCode, Json<database::Invitation>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    if !teams::is_shared_owner_type(&entity_type) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let email = invitations::normalize_email(&payload.email).ok_or(StatusCode::BAD_REQUEST)?;
+    let role = Role::parse(&payload.role).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let database = &app_state.database;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: &entity_type,
+            owner_id: &entity_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    tracing::info!("Inviting {} to {} {}", email, entity_type, entity_id);
+
+    let members = database
+        .get_members(&entity_type, &entity_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch members of {}: {:?}", entity_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if members.iter().any(|m| m.email.eq_ignore_ascii_case(&email)) {
+        tracing::info!("{} is already a member of {}", email, entity_id);
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let entity_name = database
+        .get_entity_name(&entity_type, &entity_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch name of {}: {:?}", entity_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let token = tokens::generate();
+    let now = Utc::now();
+    let invitation = database
+        .create_invitation(database::Invitation {
+            id: uuid::Uuid::new_v4().to_string(),
+            email: email.clone(),
+            entity_id: entity_id.clone(),
+            entity_type: entity_type.clone(),
+            role: role.as_str().to_string(),
+            token_hash: tokens::hash(&token),
+            invited_by: Some(user_id.clone()),
+            expires_at: invitations::expires_at(now).to_rfc3339(),
+            created_at: now.to_rfc3339(),
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create invitation for {}: {:?}", email, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let body = invitations::email_body(
+        &user_email,
+        &entity_name,
+        role.as_str(),
+        &invitations::accept_url(&token),
+    );
+    if let Err(e) = app_state
+        .mailer
+        .send(&email, &invitations::email_subject(&entity_name), &body)
+        .await
+    {
+        // An invitation nobody received can't be accepted, don't leave it around
+        tracing::error!("Failed to send invitation to {}: {:?}", email, e);
+        let _ = database.delete_invitation(&invitation.id).await;
+        return Err(StatusCode::BAD_GATEWAY);
+    }
+
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+async fn revoke_invitation_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Path(invitation_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "DELETE");
+    });
+
+    tracing::info!("Revoking invitation {} for user {}", invitation_id, user_id);
+
+    let database = &app_state.database;
+    let to_status = |e: anyhow::Error| {
+        tracing::error!("Failed to revoke invitation {}: {:?}", invitation_id, e);
+        match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    };
+
+    let invitation = database
+        .get_invitation(&invitation_id)
+        .await
+        .map_err(to_status)?;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Owner {
+            owner_type: &invitation.entity_type,
+            owner_id: &invitation.entity_id,
+        },
+        Access::Admin,
+    )
+    .await?;
+
+    database
+        .delete_invitation(&invitation_id)
+        .await
+        .map_err(to_status)?;
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
-async fn create_link(
+async fn accept_invitation_handler(
     State(app_state): State<AppState>,
     Extension(user_context): Extension<UserContext>,
-    headers: HeaderMap,
-    Json(payload): Json<CreateLinkRequest>,
-) -> Result<(StatusCode, Json<database::Link>), StatusCode> {
+    Json(payload): Json<AcceptInvitationRequest>,
+) -> Result<Json<database::UserMembership>, StatusCode> {
     let user_email = user_context.email.clone();
     let user_id = user_context.user_id.clone();
-    let client = &app_state.client;
-    let database = &app_state.database;
-
-    println!(
-        "Creating new link for owner {}: {}",
-        payload.owner_id, payload.url
-    );
 
     sentry::configure_scope(|scope| {
         scope.set_user(Some(sentry::User {
@@ -550,155 +4684,113 @@ async fn create_link(
         scope.set_tag("http.method", "POST");
     });
 
-    tracing::info!(
-        "Creating new link for owner {}: {}",
-        payload.owner_id,
-        payload.url
-    );
+    let database = &app_state.database;
 
-    let url = if !payload.url.starts_with("https://") {
-        format!("https://{}", payload.url)
-    } else {
-        payload.url
-    };
+    let invitation = database
+        .get_invitation_by_token_hash(&tokens::hash(&payload.token))
+        .await
+        .map_err(|e| match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => {
+                tracing::error!("Failed to look up invitation: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
 
-    // Validate URL format before proceeding
-    if let Err(e) = Url::parse(&url) {
-        tracing::error!("Invalid URL format: {} - {:?}", url, e);
-        return Err(StatusCode::BAD_REQUEST);
+    if invitations::is_expired(&invitation.expires_at, Utc::now()) {
+        tracing::info!("Invitation {} has expired", invitation.id);
+        let _ = database.delete_invitation(&invitation.id).await;
+        return Err(StatusCode::GONE);
     }
 
-    // Check for the custom authorization header
-    let auth_token = headers
-        .get("X-User-Authorization")
-        .ok_or_else(|| {
-            println!("Missing X-User-Authorization header");
-            StatusCode::UNAUTHORIZED
-        })?
-        .to_str()
-        .map_err(|e| {
-            println!("Invalid X-User-Authorization header: {:?}", e);
-            StatusCode::BAD_REQUEST
-        })?;
+    // Invite links get forwarded, only the invited address can use them
+    if !invitation.email.eq_ignore_ascii_case(&user_email) {
+        tracing::warn!(
+            "User {} tried to accept invitation {} for {}",
+            user_id,
+            invitation.id,
+            invitation.email
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
 
-    let metadata_on = headers
-        .get("X-Fetch-Metadata")
-        .and_then(|m| m.to_str().ok())
-        .map(|s| s.to_lowercase() == "true")
-        .unwrap_or(false);
+    tracing::info!(
+        "User {} accepting invitation to {} {}",
+        user_id,
+        invitation.entity_type,
+        invitation.entity_id
+    );
 
-    // Validate the JWT token
-    let user_claims = match user_jwt::validate_jwt(auth_token) {
-        Ok(claims) => claims,
-        Err(e) => {
-            println!("Invalid JWT token: {:?}", e);
-            return Err(StatusCode::UNAUTHORIZED);
-        }
+    let membership = database::UserMembership {
+        user_id: user_id.clone(),
+        entity_id: invitation.entity_id.clone(),
+        entity_type: invitation.entity_type.clone(),
+        role: invitation.role.clone(),
+        created_at: Utc::now().to_rfc3339(),
     };
+    let added = database.add_member(membership.clone()).await;
 
-    // Verify the user ID in the token matches the request user ID
-    if user_claims.user_id != user_id {
-        println!("Token user ID does not match request user ID");
-        return Err(StatusCode::UNAUTHORIZED);
+    // Single use, whether or not the user was a member already
+    if let Err(e) = database.delete_invitation(&invitation.id).await {
+        tracing::error!("Failed to delete invitation {}: {:?}", invitation.id, e);
     }
 
-    // init metadata, retrieve from link's URL, else use defaults
-    let metadata = if metadata_on {
-        match get_metadata(State(client.clone()), &url).await {
-            Ok(metadata) => metadata,
-            Err(StatusCode::BAD_GATEWAY) => {
-                // If we get BAD_GATEWAY from get_metadata, return it directly to the client
-                return Err(StatusCode::BAD_GATEWAY);
-            }
-            Err(_) => {
-                // For any other errors, use default metadata
-                Metadata {
-                    title: Some(url.clone()),
-                    description: None,
-                    favicon: None,
-                    mime_type: None,
-                }
-            }
-        }
-    } else {
-        Metadata {
-            title: Some(url.clone()),
-            description: None,
-            favicon: None,
-            mime_type: None,
+    added.map_err(|e| {
+        tracing::error!("Failed to add member from invitation: {:?}", e);
+        match e.to_string().as_str() {
+            "409" => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
-    };
+    })?;
 
-    // use the user's title, if empty use metadata, metadata will be
-    // the URL if metadata is not fetched
-    let title = if payload.title.as_deref() == Some("") {
-        metadata.title.unwrap_or_else(|| "".to_string())
-    } else {
-        payload
-            .title
-            .unwrap_or_else(|| metadata.title.unwrap().clone())
-    };
+    Ok(Json(membership))
+}
 
-    // grab the favicon if meta data is turned on the user
-    // did not provide one or just pass an empty string
-    let favicon = if payload.icon.is_none() && metadata_on {
-        get_favicon(
-            State(client),
-            &url,
-            metadata.favicon.clone(),
-            metadata.mime_type.clone(),
-        )
+/// 409 if `member_id` is the last admin, a team or organization without one
+/// can't be managed anymore
+async fn ensure_admin_remains(
+    database: &Database,
+    entity_type: &str,
+    entity_id: &str,
+    member_id: &str,
+) -> Result<(), StatusCode> {
+    let members = database
+        .get_members(entity_type, entity_id)
         .await
         .map_err(|e| {
-            tracing::error!("Error getting favicon: {:?}", e);
-        })
-        .unwrap_or_else(|_| "".to_string())
-    } else if payload.icon.is_some() {
-        payload.icon.unwrap()
-    } else {
-        "".to_string()
-    };
-
-    // use the user's description, or the metadata description
-    let description = if payload.description.as_deref() == Some("") {
-        metadata.description.unwrap_or_else(|| "".to_string())
-    } else {
-        payload
-            .description
-            .unwrap_or_else(|| metadata.description.unwrap().clone())
-    };
-
-    let link = database::Link {
-        id: uuid::Uuid::new_v4().to_string(),
-        url: url,
-        description: Some(description),
-        created_at: Utc::now().to_rfc3339(),
-        title: title,
-        icon: Some(favicon),
-        order_index: payload.next_order_index,
-        owner_type: payload.owner_type,
-        owner_id: payload.owner_id,
-        column_type: payload.column_type,
-    };
+            tracing::error!("Failed to fetch members of {}: {:?}", entity_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
-    if let Err(e) = database.create_link(link.clone()).await {
-        tracing::error!("Failed to create link in database: {:?}", e);
-        println!("Failed to create link in database: {:?}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    let admins: Vec<&str> = members
+        .iter()
+        .filter(|m| m.role == Role::Admin.as_str())
+        .map(|m| m.user_id.as_str())
+        .collect();
+    if admins == [member_id] {
+        tracing::info!("{} is the last admin of {}", member_id, entity_id);
+        return Err(StatusCode::CONFLICT);
     }
+    Ok(())
+}
 
-    tracing::info!("Successfully created link with ID: {}", link.id);
-    Ok((StatusCode::CREATED, Json(link)))
+/// Shared workspaces start on their creator's plan
+async fn creator_plan_id(database: &Database, user_id: &str) -> String {
+    database
+        .get_user_subscription(user_id)
+        .await
+        .map(|s| s.plan_id)
+        .unwrap_or_else(|_| teams::FREE_PLAN_ID.to_string())
 }
 
-async fn update_link(
+async fn link_health_handler(
     State(app_state): State<AppState>,
     Extension(user_context): Extension<UserContext>,
-    Json(payload): Json<UpdateLinkRequest>,
-) -> Result<StatusCode, StatusCode> {
+    Query(query): Query<LinkHealthQuery>,
+) -> Result<Json<Vec<LinkHealthReport>>, StatusCode> {
     let user_email = user_context.email.clone();
     let user_id = user_context.user_id.clone();
-    println!("Updating link: {}", payload.id);
+    println!("Fetching link health for user: {}", user_id);
 
     sentry::configure_scope(|scope| {
         scope.set_user(Some(sentry::User {
@@ -706,50 +4798,97 @@ async fn update_link(
             id: Some(user_id.clone()),
             ..Default::default()
         }));
-        scope.set_tag("http.method", "PUT");
+        scope.set_tag("http.method", "GET");
     });
 
-    tracing::info!("Updating link: {}", payload.id);
+    let database = &app_state.database;
 
-    // Validate URL format if provided
-    if let Some(ref url) = payload.url {
-        if let Err(e) = Url::parse(url) {
-            tracing::error!("Invalid URL format in update: {} - {:?}", url, e);
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    }
+    let links = database
+        .get_links(&user_id, "user", None, &[])
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch links for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let mut health: HashMap<String, database::LinkHealth> = database
+        .get_link_health(&user_id, "user")
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch link health for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .map(|h| (h.link_id.clone(), h))
+        .collect();
+
+    let reports: Vec<LinkHealthReport> = links
+        .into_iter()
+        .filter_map(|link| {
+            let health = health.remove(&link.id)?;
+            query
+                .status
+                .matches(&health)
+                .then_some(LinkHealthReport { link, health })
+        })
+        .collect();
+
+    tracing::info!(
+        "Found {} link health reports for user {}",
+        reports.len(),
+        user_id
+    );
+    Ok(Json(reports))
+}
+
+async fn check_link_handler(
+    State(app_state): State<AppState>,
+    Path(link_id): Path<String>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<Json<database::LinkHealth>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
 
-    // Use app_state's database instance
     let database = &app_state.database;
 
-    let link = database::Link {
-        id: payload.id.clone(),
-        url: payload.url.clone().unwrap_or_else(|| "".to_string()),
-        description: payload.description.clone(),
-        title: payload.title.clone().unwrap(),
-        icon: payload.icon.clone(),
-        column_type: payload.column_type.clone().unwrap(),
-        created_at: Utc::now().to_rfc3339(),
-        order_index: 0,
-        owner_type: "".to_string(),
-        owner_id: "".to_string(),
-    };
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Link(&link_id),
+        Access::Write,
+    )
+    .await?;
 
-    if let Err(e) = database.update_link(link).await {
-        tracing::error!("Failed to update link: {:?}", e);
-        println!("Failed to update link: {:?}", e);
+    let link = database.get_link_by_id(&link_id).await.map_err(|e| {
+        tracing::error!("Failed to fetch link {}: {:?}", link_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let health = link_health::check_url(&app_state.client, &link.id, &link.url).await;
+
+    if let Err(e) = database.record_link_health(&health).await {
+        tracing::error!("Failed to record link health: {:?}", e);
         return Err(StatusCode::INTERNAL_SERVER_ERROR);
     }
 
-    tracing::info!("Successfully updated link {}", payload.id);
-    Ok(StatusCode::OK)
+    tracing::info!("Checked link {}: {:?}", link_id, health.status_code);
+    Ok(Json(health))
 }
 
-async fn delete_link(
+async fn follow_redirect_handler(
     State(app_state): State<AppState>,
     Path(link_id): Path<String>,
     Extension(user_context): Extension<UserContext>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<Json<database::Link>, StatusCode> {
     let user_email = user_context.email.clone();
     let user_id = user_context.user_id.clone();
 
@@ -759,41 +4898,57 @@ async fn delete_link(
             id: Some(user_id.clone()),
             ..Default::default()
         }));
-        scope.set_tag("http.method", "DELETE");
+        scope.set_tag("http.method", "POST");
     });
 
-    // Use app_state's database instance
     let database = &app_state.database;
 
-    // First, verify that the link exists and belongs to the user
-    let link = database.get_link(&link_id, &user_id).await.map_err(|e| {
-        tracing::warn!("Link not found or unauthorized: {:?}", e);
-        if e.to_string().contains("404") {
-            StatusCode::NOT_FOUND
-        } else {
-            StatusCode::FORBIDDEN
-        }
+    let owner = authz::authorize(
+        database,
+        &user_context,
+        Resource::Link(&link_id),
+        Access::Write,
+    )
+    .await?;
+
+    let mut link = database.get_link_by_id(&link_id).await.map_err(|e| {
+        tracing::error!("Failed to fetch link {}: {:?}", link_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    // Verify ownership
-    if link.owner_id != user_id {
-        tracing::warn!(
-            "User {} attempted to delete link {} owned by {}",
-            user_id,
-            link_id,
-            link.owner_id
-        );
-        return Err(StatusCode::FORBIDDEN);
-    }
+    let final_url = database
+        .get_link_health(&owner.owner_id, &owner.owner_type)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch link health: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .find(|h| h.link_id == link_id)
+        .and_then(|h| h.final_url)
+        .ok_or_else(|| {
+            tracing::info!("Link {} has no recorded redirect", link_id);
+            StatusCode::CONFLICT
+        })?;
 
-    // Delete the link
-    if let Err(e) = database.delete_link(&link_id).await {
-        tracing::error!("Error deleting link: {:?}", e);
+    if let Err(e) = database.update_link_url(&link_id, &final_url).await {
+        tracing::error!("Failed to rewrite link URL: {:?}", e);
         return Err(StatusCode::INTERNAL_SERVER_ERROR);
     }
 
-    tracing::info!("Successfully deleted link: {}", link_id);
-    Ok(StatusCode::NO_CONTENT)
+    tracing::info!("Rewrote link {} to {}", link_id, final_url);
+    link.url = final_url;
+    Ok(Json(link))
+}
+
+/// Next free order_index for each column, so appended links go to the end
+fn next_order_indexes(links: &[database::Link]) -> HashMap<String, i32> {
+    let mut next_order: HashMap<String, i32> = HashMap::new();
+    for link in links {
+        let next = next_order.entry(link.column_type.clone()).or_insert(0);
+        *next = (*next).max(link.order_index + 1);
+    }
+    next_order
 }
 
 async fn get_user_handler(
@@ -826,73 +4981,45 @@ async fn get_user_handler(
     Ok(Json(user))
 }
 
-async fn get_metadata(client: State<reqwest::Client>, url: &str) -> Result<Metadata, StatusCode> {
-    tracing::info!("Fetching metadata for URL: {}", url);
+async fn metadata_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Query(query): Query<MetadataQuery>,
+) -> Result<Json<metadata::PageMetadata>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
 
-    println!("Fetching metadata for URL: {}", url);
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
 
-    // Attempt to fetch the URL with proper error handling
-    let response = match client.get(url).send().await {
-        Ok(response) => response,
-        Err(e) => {
-            tracing::info!("Failed to fetch URL {}: {:?}", url, e);
-            println!("Error fetching metadata: {:?}", e);
-            return Err(StatusCode::BAD_GATEWAY);
+    let url = match Url::parse(&query.url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+        _ => {
+            tracing::warn!("Invalid URL for metadata: {}", query.url);
+            return Err(StatusCode::BAD_REQUEST);
         }
     };
 
-    if !response.status().is_success() {
-        return Err(StatusCode::BAD_REQUEST);
+    match metadata::fetch(&app_state.client, url.as_str()).await {
+        Ok(metadata) => Ok(Json(metadata)),
+        Err(e) => {
+            tracing::info!("Failed to fetch metadata for {}: {}", url, e);
+            Err(StatusCode::BAD_GATEWAY)
+        }
     }
-
-    let document = response
-        .text()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let document = scraper::Html::parse_document(&document);
-
-    let title_selector = scraper::Selector::parse("title").unwrap();
-    let title = document
-        .select(&title_selector)
-        .next()
-        .map(|t| t.inner_html());
-
-    let description_selector = scraper::Selector::parse("meta[name='description']").unwrap();
-    let description = document
-        .select(&description_selector)
-        .next()
-        .and_then(|d| d.value().attr("content"))
-        .map(|d| d.to_string());
-
-    let icon_selector = scraper::Selector::parse("link[rel='icon']").unwrap();
-    let favicon = document
-        .select(&icon_selector)
-        .next()
-        .and_then(|i| i.value().attr("href"))
-        .map(|i| i.to_string());
-
-    let mime_type = document
-        .select(&icon_selector)
-        .next()
-        .and_then(|i| i.value().attr("type"))
-        .map(|i| i.to_string());
-
-    println!("favicon source: {:?}", favicon);
-
-    tracing::info!("Successfully fetched metadata for URL: {}", url);
-    Ok(Metadata {
-        title,
-        description,
-        favicon,
-        mime_type,
-    })
 }
 
 async fn get_favicon(
     client: State<&reqwest::Client>,
+    database: &Database,
     url: &str,
-    favicon_source: Option<String>,
-    mime_type: Option<String>,
+    candidates: &[metadata::IconCandidate],
 ) -> Result<String, StatusCode> {
     let parsed_url = match Url::parse(url) {
         Ok(url) => url,
@@ -901,78 +5028,137 @@ async fn get_favicon(
             return Err(StatusCode::BAD_REQUEST);
         }
     };
-    let domain = parsed_url.host_str().unwrap_or("").to_string();
 
-    let domain = if !domain.starts_with("https://") {
-        format!("https://{}", domain)
-    } else {
-        domain
+    // icons declared by the page (already resolved and ranked) come first,
+    // then we just try some well known locations
+    let fallback_paths = [
+        "/favicon.ico",
+        "/images/favicon.ico",
+        "/assets/favicon.ico",
+        "/static/favicon.ico",
+        "/public/favicon.ico",
+        "/icon/favicon.ico",
+        "/icons/favicon.ico",
+        "/icon.svg",
+        "/favicon-32x32.png",
+    ];
+    let favicon_urls: Vec<String> = candidates
+        .iter()
+        .map(|c| c.url.clone())
+        .chain(
+            fallback_paths
+                .iter()
+                .filter_map(|path| parsed_url.join(path).ok())
+                .map(|u| u.to_string()),
+        )
+        .collect();
+
+    for favicon_url in favicon_urls {
+        let Ok(fav_response) = client.get(&favicon_url).send().await else {
+            continue;
+        };
+        if !fav_response.status().is_success() {
+            continue;
+        }
+        let Ok(fav_bytes) = fav_response.bytes().await else {
+            continue;
+        };
+
+        // Plenty of sites answer missing favicons with an HTML page, storing
+        // sniffs the bytes and rejects anything that isn't an image
+        match database.store_icon(&fav_bytes).await {
+            Ok(hash) => return Ok(icon_store::icon_url(&hash)),
+            Err(e) => tracing::debug!("Skipping favicon {}: {}", favicon_url, e),
+        }
+    }
+
+    Ok("".to_string())
+}
+
+/// Move a user supplied `data:` URI icon into the icon store, other icons
+/// (URLs, mdi- names, already stored icons) are passed through untouched
+async fn store_inline_icon(
+    database: &Database,
+    icon: Option<String>,
+) -> Result<Option<String>, StatusCode> {
+    let Some(icon) = icon else {
+        return Ok(None);
     };
+    if !icon.starts_with("data:") {
+        return Ok(Some(icon));
+    }
 
-    let favicon_urls = vec![
-        format!("{}/favicon.ico", domain.trim_end_matches('/')),
-        format!("{}/images/favicon.ico", domain.trim_end_matches('/')),
-        format!("{}/assets/favicon.ico", domain.trim_end_matches('/')),
-        format!("{}/static/favicon.ico", domain.trim_end_matches('/')),
-        format!("{}/public/favicon.ico", domain.trim_end_matches('/')),
-        format!("{}/icon/favicon.ico", domain.trim_end_matches('/')),
-        format!("{}/icons/favicon.ico", domain.trim_end_matches('/')),
-        format!("{}/icon.svg", domain.trim_end_matches('/')),
-        format!("{}/favicon-32x32.png", domain.trim_end_matches('/')),
-    ];
+    let bytes = icon_store::decode_data_uri(&icon).ok_or_else(|| {
+        tracing::warn!("Malformed data URI icon");
+        StatusCode::BAD_REQUEST
+    })?;
 
-    let mut favicon: Option<String> = None;
+    let hash = database.store_icon(&bytes).await.map_err(|e| {
+        tracing::warn!("Failed to store icon: {:?}", e);
+        StatusCode::BAD_REQUEST
+    })?;
 
-    // if we found a source link tag while parsing the page's document, grab that
-    // otherwise we just try some backups
-    if favicon_source.is_some() {
-        let favicon_url = format!(
-            "{}{}",
-            domain.trim_end_matches('/'),
-            &favicon_source.unwrap()
-        );
-        let fav_response = client.get(favicon_url).send().await;
-
-        if let Ok(fav_response) = fav_response {
-            if fav_response.status().is_success() {
-                if let Ok(fav_bytes) = fav_response.bytes().await {
-                    favicon = Some(format!(
-                        "data:{};base64,{}",
-                        mime_type.unwrap_or_else(|| "unknown".to_string()),
-                        BASE64_STANDARD.encode(fav_bytes)
-                    ));
-                }
-            }
-        }
-    } else {
-        for favicon_url in favicon_urls {
-            if let Ok(fav_response) = client.get(&favicon_url).send().await {
-                if fav_response.status().is_success() {
-                    if let Ok(fav_bytes) = fav_response.bytes().await {
-                        favicon = Some(format!(
-                            "data:{};base64,{}",
-                            mime_type.unwrap_or_else(|| "unknown".to_string()),
-                            BASE64_STANDARD.encode(fav_bytes)
-                        ));
-                        break;
-                    }
-                }
-            }
-        }
+    Ok(Some(icon_store::icon_url(&hash)))
+}
+
+async fn icon_handler(
+    State(app_state): State<AppState>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    if !icon_store::is_valid_hash(&hash) {
+        return Err(StatusCode::NOT_FOUND);
     }
 
-    if favicon.is_none() {
-        favicon = Some("".to_string());
+    let etag = format!("\"{}\"", hash);
+
+    // Icons are content-addressed so a matching ETag can never be stale
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v == etag)
+    {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
     }
 
-    Ok(favicon.unwrap())
+    let icon =
+        app_state
+            .database
+            .get_icon(&hash)
+            .await
+            .map_err(|e| match e.to_string().as_str() {
+                "404" => StatusCode::NOT_FOUND,
+                _ => {
+                    tracing::error!("Failed to fetch icon {}: {:?}", hash, e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, icon.content_type),
+            (
+                header::CACHE_CONTROL,
+                "public, max-age=31536000, immutable".to_string(),
+            ),
+            (header::ETAG, etag),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (
+                header::CONTENT_SECURITY_POLICY,
+                icon_store::CONTENT_SECURITY_POLICY.to_string(),
+            ),
+        ],
+        icon.data,
+    )
+        .into_response())
 }
 
 async fn suggest_handler(
+    State(app_state): State<AppState>,
     Path(query): Path<String>,
     Extension(user_context): Extension<UserContext>,
     headers: HeaderMap,
-) -> Result<Json<SuggestionResponse>, StatusCode> {
+) -> Result<Json<SuggestionResponse>, Response> {
     let user_email = user_context.email.clone();
     let user_id = user_context.user_id.clone();
     println!("Suggesting: {}", query);
@@ -986,17 +5172,25 @@ async fn suggest_handler(
         scope.set_tag("http.method", "GET");
     });
 
+    if app_state.suggestions.is_empty() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": "Search suggestions aren't configured on this server" })),
+        )
+            .into_response());
+    }
+
     // Check for the custom authorization header
     let auth_token = headers
         .get("X-User-Authorization")
         .ok_or_else(|| {
             println!("Missing X-User-Authorization header");
-            StatusCode::UNAUTHORIZED
+            StatusCode::UNAUTHORIZED.into_response()
         })?
         .to_str()
         .map_err(|e| {
             println!("Invalid X-User-Authorization header: {:?}", e);
-            StatusCode::BAD_REQUEST
+            StatusCode::BAD_REQUEST.into_response()
         })?;
 
     // Validate the JWT token
@@ -1004,40 +5198,213 @@ async fn suggest_handler(
         Ok(claims) => claims,
         Err(e) => {
             println!("Invalid JWT token: {:?}", e);
-            return Err(StatusCode::UNAUTHORIZED);
+            return Err(StatusCode::UNAUTHORIZED.into_response());
         }
     };
 
     // Verify the user ID in the token matches the request user ID
     if user_claims.user_id != user_id {
         println!("Token user ID does not match request user ID");
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    }
+
+    // Users without settings get the browser's language
+    let locale: LocaleSettings = settings_blob(&app_state.database, &user_id).await;
+    let accept_language = headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok());
+    let context = SuggestContext::new(&user_id, &locale, accept_language);
+
+    let suggestions = app_state
+        .suggestions
+        .suggest(&query, context)
+        .await
+        .map_err(|e| {
+            // Brave's rate limit is passed on so the client backs off
+            if e.to_string() == "429" {
+                tracing::warn!("Rate limit exceeded for Brave API");
+                return StatusCode::TOO_MANY_REQUESTS.into_response();
+            }
+            tracing::error!("Error getting suggestions: {:?}", e);
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        })?;
+
+    Ok(Json(SuggestionResponse { suggestions }))
+}
+
+/// Part of the settings blob on the active dashboard, the defaults when the
+/// user has no settings or they can't be read
+async fn settings_blob<T: DeserializeOwned + Default>(database: &Database, user_id: &str) -> T {
+    match database.get_dashboard_settings(user_id).await {
+        Ok(settings) => serde_json::from_str(&settings.settings_blob).unwrap_or_default(),
+        Err(e) if e.to_string() == "404" => T::default(),
+        Err(e) => {
+            tracing::error!("Error fetching settings for {}: {:?}", user_id, e);
+            T::default()
+        }
+    }
+}
+
+/// Add a search to the user's history unless they turned history off, and
+/// drop what's past their retention. None when nothing was recorded.
+async fn record_search(
+    app_state: &AppState,
+    user_id: &str,
+    query: &str,
+) -> anyhow::Result<Option<database::SearchHistoryEntry>> {
+    let settings: search_history::HistorySettings =
+        settings_blob(&app_state.database, user_id).await;
+    if !settings.search_history {
+        return Ok(None);
+    }
+
+    let entry = app_state.database.record_search(user_id, query).await?;
+    app_state
+        .database
+        .prune_search_history(Some(user_id), app_state.history_retention_days)
+        .await?;
+    Ok(Some(entry))
+}
+
+/// The user behind a search engine request, None without a token
+async fn web_search_user(
+    app_state: &AppState,
+    token: Option<&str>,
+    path: &str,
+) -> Result<Option<UserContext>, StatusCode> {
+    let Some(token) = token.filter(|token| !token.is_empty()) else {
+        return Ok(None);
+    };
+    if !token.starts_with(api_tokens::TOKEN_PREFIX) {
         return Err(StatusCode::UNAUTHORIZED);
     }
+    let user_context =
+        middleware::authenticate_api_token(app_state, token, &Method::GET, path).await?;
 
-    println!("Suggesting: {}", query);
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_context.email.clone()),
+            id: Some(user_context.user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
 
-    let brave = Brave::new(
-        std::env::var("BRAVE_SUGGEST_URL").expect("BRAVE_URL must be set"),
-        std::env::var("BRAVE_API_KEY").expect("BRAVE_API_KEY must be set"),
-    )
-    .map_err(|e| {
-        println!("Error initializing Brave client: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    Ok(Some(user_context))
+}
 
-    let response = brave.get_suggestions(&query).await.map_err(|e| {
-        // Check for rate limit error specifically
-        if e.to_string().contains("429") {
-            println!("Rate limit exceeded for Brave API");
-            return StatusCode::TOO_MANY_REQUESTS;
+async fn web_search_handler(
+    State(app_state): State<AppState>,
+    Query(params): Query<WebSearchQuery>,
+) -> Result<Redirect, StatusCode> {
+    let query = params.q.unwrap_or_default();
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Redirect::to(&format!("{}/", invitations::app_url())));
+    }
+
+    let user_id = web_search_user(&app_state, params.token.as_deref(), "/search")
+        .await?
+        .map(|user_context| user_context.user_id);
+    let target = web_search::resolve_query(&app_state.database, user_id.as_deref(), query)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error resolving search {:?}: {:?}", query, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let Some(user_id) = user_id else {
+        return Ok(Redirect::to(target.url()));
+    };
+
+    if matches!(target, web_search::Target::Search(_))
+        && let Err(e) = record_search(&app_state, &user_id, query).await
+    {
+        tracing::error!("Error recording search: {:?}", e);
+    }
+
+    Ok(Redirect::to(target.url()))
+}
+
+/// Suggestions for the browser's address bar, an empty list when there
+/// aren't any so the browser doesn't show an error
+async fn web_search_suggest_handler(
+    State(app_state): State<AppState>,
+    Query(params): Query<WebSearchQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let query = params.q.unwrap_or_default();
+    let user_context =
+        web_search_user(&app_state, params.token.as_deref(), "/search/suggest").await?;
+
+    // Anonymous searches don't get suggestions, they'd spend the server's
+    // Brave quota for anyone who asks
+    let suggestions = match user_context {
+        Some(user_context) if !query.trim().is_empty() && !app_state.suggestions.is_empty() => {
+            let locale: LocaleSettings =
+                settings_blob(&app_state.database, &user_context.user_id).await;
+            let accept_language = headers
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok());
+            let context = SuggestContext::new(&user_context.user_id, &locale, accept_language);
+            app_state
+                .suggestions
+                .suggest(&query, context)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::error!("Error getting suggestions: {:?}", e);
+                    Vec::new()
+                })
         }
-        println!("Error getting suggestions: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+        _ => Vec::new(),
+    };
 
-    Ok(Json(SuggestionResponse {
-        suggestions: response.results,
-    }))
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-suggestions+json")],
+        web_search::suggestions_json(&query, &suggestions).to_string(),
+    )
+        .into_response())
+}
+
+fn opensearch_response(xml: String) -> Response {
+    (
+        [(
+            header::CONTENT_TYPE,
+            "application/opensearchdescription+xml",
+        )],
+        xml,
+    )
+        .into_response()
+}
+
+/// The public description never carries a token, anyone could link to one
+/// with a token of their own and collect the searches of whoever adds it
+async fn opensearch_handler() -> Response {
+    opensearch_response(web_search::description_xml(&invitations::app_url(), None))
+}
+
+/// Description searching as the signed-in user, only with a search token of
+/// their own
+async fn personal_opensearch_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Query(params): Query<OpenSearchQuery>,
+) -> Result<Response, StatusCode> {
+    let token = params.token.unwrap_or_default();
+    let Some(owner) = web_search_user(&app_state, Some(&token), "/search").await? else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    if owner.user_id != user_context.user_id {
+        tracing::warn!(
+            "User {} asked for a description with another user's token",
+            user_context.user_id
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(opensearch_response(web_search::description_xml(
+        &invitations::app_url(),
+        Some(&token),
+    )))
 }
 
 async fn search_icons_handler(
@@ -1090,27 +5457,14 @@ async fn feedback_handler(
     let user_email = user_context.email.clone();
     println!("Feedback for user: {}", user_id);
 
-    // Use app_state's database instance
-    let database = &app_state.database;
-
-    // Check if the user has sent feedback in the last 24 hours
-    let can_send_feedback = database
-        .check_feedback_timestamp(&user_id)
-        .await
-        .map_err(|e| {
-            println!("Error checking feedback timestamp: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+    let customer_support_email = std::env::var("CUSTOMER_SUPPORT_EMAIL")
+        .ok()
+        .filter(|email| !email.trim().is_empty())
+        .ok_or_else(|| {
+            tracing::error!("CUSTOMER_SUPPORT_EMAIL is not set, can't deliver feedback");
+            StatusCode::SERVICE_UNAVAILABLE
         })?;
 
-    if !can_send_feedback {
-        return Err(StatusCode::TOO_MANY_REQUESTS);
-    }
-
-    let resend_service = ResendClient::new();
-
-    let customer_support_email =
-        std::env::var("CUSTOMER_SUPPORT_EMAIL").expect("CUSTOMER_SUPPORT_EMAIL must be set");
-
     let email_body = format!(
         "<p>Feedback from user: {} | {}<br/><br/>Reasons: {:?}<br/><br/>Feedback: {}</p>",
         user_id,
@@ -1121,22 +5475,14 @@ async fn feedback_handler(
 
     let subject = format!("Feedback from: {}", user_email);
 
-    resend_service
-        .send_email(&customer_support_email, &subject, &email_body)
+    app_state
+        .mailer
+        .send(&customer_support_email, &subject, &email_body)
         .await
         .map_err(|e| {
             println!("Error sending email: {:?}", e);
             tracing::error!("Error sending email: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    // Create a feedback timestamp record
-    database
-        .create_feedback_timestamp(&user_id, &Utc::now())
-        .await
-        .map_err(|e| {
-            println!("Error creating feedback timestamp: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            StatusCode::BAD_GATEWAY
         })?;
 
     Ok(StatusCode::OK)
@@ -1145,7 +5491,7 @@ async fn feedback_handler(
 async fn create_settings(
     State(app_state): State<AppState>,
     Extension(user_context): Extension<UserContext>,
-    Json(payload): Json<UserSettingsRequest>,
+    Json(mut payload): Json<UserSettingsRequest>,
 ) -> Result<StatusCode, StatusCode> {
     let user_email = user_context.email.clone();
     let user_id = user_context.user_id.clone();
@@ -1162,10 +5508,19 @@ async fn create_settings(
 
     println!("Creating settings for user: {}", user_id);
     println!("Payload: {:?}", payload);
+    payload.normalize()?;
 
     // Use app_state's database instance
     let database = &app_state.database;
 
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Settings(&user_id),
+        Access::Write,
+    )
+    .await?;
+
     let settings = database::UserSettings {
         user_id: user_id.clone(),
         settings_blob: serde_json::to_string(&payload).unwrap_or_default(),
@@ -1183,7 +5538,7 @@ async fn create_settings(
 async fn update_settings(
     State(app_state): State<AppState>,
     Extension(user_context): Extension<UserContext>,
-    Json(payload): Json<UserSettingsRequest>,
+    Json(mut payload): Json<UserSettingsRequest>,
 ) -> Result<StatusCode, StatusCode> {
     let user_email = user_context.email.clone();
     let user_id = user_context.user_id.clone();
@@ -1200,16 +5555,28 @@ async fn update_settings(
 
     println!("Updating settings for user: {}", user_id);
     println!("Payload: {:?}", payload);
+    payload.normalize()?;
 
     // Use app_state's database instance
     let database = &app_state.database;
 
-    let mut updates = HashMap::new();
-    updates.insert("settings_blob".to_string(), json!(payload));
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Settings(&user_id),
+        Access::Write,
+    )
+    .await?;
 
-    if let Err(e) = database.update_user_settings(&user_id, updates).await {
+    if let Err(e) = database
+        .save_dashboard_settings(&user_id, &json!(payload))
+        .await
+    {
         println!("Error updating user settings: {:?}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        });
     }
 
     Ok(StatusCode::OK)
@@ -1237,14 +5604,21 @@ async fn get_settings(
     // Use app_state's database instance
     let database = &app_state.database;
 
-    let settings =
-        database
-            .get_user_settings(&user_id)
-            .await
-            .map_err(|e| match e.to_string().as_str() {
-                "404" => StatusCode::NOT_FOUND,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            })?;
+    authz::authorize(
+        database,
+        &user_context,
+        Resource::Settings(&user_id),
+        Access::Read,
+    )
+    .await?;
+
+    let settings = database
+        .get_dashboard_settings(&user_id)
+        .await
+        .map_err(|e| match e.to_string().as_str() {
+            "404" => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
 
     Ok(Json(settings))
 }
@@ -1286,6 +5660,7 @@ async fn get_user_data_handler(
                 id: user_id.clone(),
                 email: user_email.clone(),
                 created_at: Utc::now().to_rfc3339(),
+                email_verified_at: None,
                 auth_token: None,
                 password_hash: String::new(),
             };
@@ -1332,20 +5707,33 @@ async fn get_user_data_handler(
         };
     }
 
+    let dashboard = active_dashboard(database, &user_id).await?;
+    if let Some(settings) = &mut settings {
+        settings.settings_blob = dashboards::apply_settings_overrides(
+            &settings.settings_blob,
+            &dashboard.settings_overrides,
+        );
+    }
+
     tracing::info!("Fetching links for user {}", user_id);
     println!("Fetching links for user {}", user_id);
-    let links = database.get_links(&user_id, "user").await.map_err(|e| {
-        tracing::error!("Failed to fetch links: {:?}", e);
-        println!("Failed to fetch links: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let links = database
+        .get_links(&user_id, "user", Some(&dashboard.id), &[])
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch links: {:?}", e);
+            println!("Failed to fetch links: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
-    // Generate JWT token with user ID
-    let auth_token = user_jwt::generate_jwt(&user_id, &user_email).map_err(|e| {
-        tracing::error!("Failed to generate JWT token: {:?}", e);
-        println!("Failed to generate JWT token: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let columns = database
+        .get_columns(&dashboard.id, "dashboard")
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch columns: {:?}", e);
+            println!("Failed to fetch columns: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
     tracing::info!(
         "Successfully assembled user data response for {}",
@@ -1356,16 +5744,15 @@ async fn get_user_data_handler(
         user_email
     );
 
-    // Create response with user data
-    let mut response = UserDataResponse {
+    // No fresh access token here, tokens are only renewed through
+    // /refresh so sessions can expire and be rotated
+    Ok(Json(UserDataResponse {
         user,
         settings,
+        dashboard,
+        columns,
         links,
-    };
-
-    response.user.auth_token = Some(auth_token);
-
-    Ok(Json(response))
+    }))
 }
 
 async fn create_user_default_settings(
@@ -1385,6 +5772,9 @@ async fn create_user_default_settings(
         linear_api: false,
         new_tabs: false,
         metadata: false,
+        locale: None,
+        region: None,
+        search_history_days: None,
     };
 
     let user_settings = database::UserSettings {
@@ -1404,3 +5794,255 @@ async fn create_user_default_settings(
         return Err(StatusCode::FOUND);
     }
 }
+
+async fn export_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+) -> Result<([(header::HeaderName, String); 1], Json<archive::Archive>), StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+    println!("Exporting data for user: {}", user_id);
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "GET");
+    });
+
+    tracing::info!("Exporting data for user: {}", user_id);
+
+    let database = &app_state.database;
+
+    let user_data = database.get_user_data(&user_id).await.map_err(|e| {
+        tracing::error!("Failed to fetch user data for export: {:?}", e);
+        if e.to_string().contains("not found") {
+            StatusCode::NOT_FOUND
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    let mut archive = archive::Archive::from_user_data(user_data);
+
+    // Links only reference stored icons, bundle the bytes so the archive is self-contained
+    let icon_hashes: HashSet<&str> = archive
+        .links
+        .iter()
+        .filter_map(|l| {
+            l.icon
+                .as_deref()?
+                .strip_prefix(icon_store::ICON_ROUTE_PREFIX)
+        })
+        .collect();
+    let mut icons = Vec::new();
+    for hash in icon_hashes {
+        match database.get_icon(hash).await {
+            Ok(icon) => icons.push(archive::ArchiveIcon {
+                hash: icon.hash,
+                content_type: icon.content_type,
+                data: BASE64_STANDARD.encode(icon.data),
+            }),
+            Err(e) => tracing::warn!("Skipping missing icon {} in export: {:?}", hash, e),
+        }
+    }
+    archive.icons = icons;
+    let disposition = format!(
+        "attachment; filename=\"omega-tab-export-{}.json\"",
+        Utc::now().format("%Y%m%d")
+    );
+
+    tracing::info!(
+        "Exported {} links for user {}",
+        archive.links.len(),
+        user_id
+    );
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(archive)))
+}
+
+async fn restore_handler(
+    State(app_state): State<AppState>,
+    Extension(user_context): Extension<UserContext>,
+    Json(payload): Json<RestoreRequest>,
+) -> Result<Json<RestoreResponse>, StatusCode> {
+    let user_email = user_context.email.clone();
+    let user_id = user_context.user_id.clone();
+    println!("Restoring data for user: {}", user_id);
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            email: Some(user_email.clone()),
+            id: Some(user_id.clone()),
+            ..Default::default()
+        }));
+        scope.set_tag("http.method", "POST");
+    });
+
+    tracing::info!(
+        "Restoring data for user {} in {:?} mode",
+        user_id,
+        payload.mode
+    );
+
+    let archive = payload.archive;
+    if archive.version == 0 || archive.version > archive::ARCHIVE_VERSION {
+        tracing::warn!("Unsupported archive version: {}", archive.version);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let database = &app_state.database;
+    let replace = payload.mode == archive::RestoreMode::Replace;
+
+    // Icons are content-addressed, so they can be stored before anything else.
+    // Links find them by the archived hash, which has to match the bytes.
+    for icon in &archive.icons {
+        let stored = match BASE64_STANDARD.decode(&icon.data) {
+            Ok(bytes) if icon_store::hash(&bytes) != icon.hash => {
+                Err(anyhow::anyhow!("hash doesn't match the icon"))
+            }
+            Ok(bytes) => database.store_icon(&bytes).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = stored {
+            tracing::warn!("Skipping icon {} from archive: {:?}", icon.hash, e);
+        }
+    }
+
+    // In replace mode the current links are dropped, so there's nothing to merge with
+    let (existing, existing_locations) = if replace {
+        (Vec::new(), HashMap::new())
+    } else {
+        let data = database.get_user_data(&user_id).await.map_err(|e| {
+            tracing::error!("Failed to fetch data for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        let locations = archive::column_locations(&data.dashboards, &data.columns);
+        (data.links, locations)
+    };
+    let archived_locations = archive::column_locations(&archive.dashboards, &archive.columns);
+
+    // Links are matched by dashboard and column name, ids differ between installs.
+    // Links without a known column end up in the active dashboard.
+    let active_name = active_dashboard(database, &user_id).await?.name;
+    let location = |locations: &HashMap<String, (String, String)>, link: &database::Link| {
+        link.column_id
+            .as_ref()
+            .and_then(|id| locations.get(id))
+            .cloned()
+            .unwrap_or_else(|| (active_name.clone(), link.column_type.clone()))
+    };
+
+    let mut seen: HashSet<((String, String), String)> = HashSet::new();
+    let mut next_order: HashMap<(String, String), i32> = HashMap::new();
+    for link in &existing {
+        let location = location(&existing_locations, link);
+        let next = next_order.entry(location.clone()).or_insert(0);
+        *next = (*next).max(link.order_index + 1);
+        seen.insert((location, link.url.clone()));
+    }
+
+    let mut archived_links = archive.links;
+    archived_links.sort_by_key(|l| l.order_index);
+
+    let mut links = Vec::new();
+    let mut links_skipped = 0;
+    for link in archived_links {
+        let location = location(&archived_locations, &link);
+        if !seen.insert((location.clone(), link.url.clone())) {
+            links_skipped += 1;
+            continue;
+        }
+
+        let order_index = next_order.entry(location).or_insert(0);
+
+        // Fresh ids keep restores from colliding with links on this install
+        links.push(database::Link {
+            id: uuid::Uuid::new_v4().to_string(),
+            order_index: *order_index,
+            owner_type: "user".to_string(),
+            owner_id: user_id.clone(),
+            tags: tags::normalize_tags(link.tags),
+            ..link
+        });
+        *order_index += 1;
+    }
+
+    let settings = if replace {
+        archive.settings
+    } else {
+        // Only a missing row means there's nothing to merge with, anything
+        // else would let the archive overwrite settings we couldn't read
+        let current = match database.get_user_settings(&user_id).await {
+            Ok(settings) => serde_json::from_str(&settings.settings_blob).ok(),
+            Err(e) if e.to_string() == "404" => None,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to fetch settings of {} for restore: {:?}",
+                    user_id,
+                    e
+                );
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        };
+        archive::merge_settings(current, archive.settings)
+    };
+    let settings_blob = settings.map(|s| s.to_string());
+
+    let dashboards: Vec<database::Dashboard> = archive
+        .dashboards
+        .into_iter()
+        .filter_map(|dashboard| {
+            Some(database::Dashboard {
+                name: dashboards::normalize_dashboard_name(&dashboard.name)?,
+                settings_overrides: serde_json::from_str(&dashboard.settings_overrides)
+                    .ok()
+                    .and_then(|v| dashboards::parse_settings_overrides(&v))
+                    .unwrap_or_else(|| "{}".to_string()),
+                ..dashboard
+            })
+        })
+        .collect();
+
+    let columns: Vec<database::Column> = archive
+        .columns
+        .into_iter()
+        .filter_map(|column| {
+            Some(database::Column {
+                name: columns::normalize_column_name(&column.name)?,
+                color: column.color.filter(|c| columns::is_valid_color(c)),
+                ..column
+            })
+        })
+        .collect();
+
+    database
+        .restore_user_data(
+            &user_id,
+            &dashboards,
+            &columns,
+            &links,
+            settings_blob.as_deref(),
+            replace,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to restore user data: {:?}", e);
+            println!("Failed to restore user data: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    tracing::info!(
+        "Restored {} links for user {} ({} skipped)",
+        links.len(),
+        user_id,
+        links_skipped
+    );
+
+    Ok(Json(RestoreResponse {
+        links_restored: links.len(),
+        links_skipped,
+        settings_restored: settings_blob.is_some(),
+    }))
+}
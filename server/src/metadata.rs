@@ -0,0 +1,312 @@
+use anyhow::Result;
+use reqwest::Client;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use url::Url;
+
+// Icons are shown at roughly 32-48px, anything at least this big looks sharp on hi-dpi
+const PREFERRED_ICON_SIZE: u32 = 64;
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Everything we know about a page, with all URLs resolved to absolute form
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    /// Icon candidates, best first
+    pub icons: Vec<IconCandidate>,
+    pub manifest_url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IconCandidate {
+    pub url: String,
+    /// Largest declared dimension in pixels, 0 when unknown
+    pub size: u32,
+}
+
+#[derive(Debug)]
+pub enum MetadataError {
+    /// The page couldn't be fetched at all
+    Unreachable(reqwest::Error),
+    /// The page answered with a non-success status
+    Status(reqwest::StatusCode),
+    Body(reqwest::Error),
+}
+
+impl std::fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetadataError::Unreachable(e) => write!(f, "page unreachable: {}", e),
+            MetadataError::Status(status) => write!(f, "page returned {}", status),
+            MetadataError::Body(e) => write!(f, "failed to read page: {}", e),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct WebManifest {
+    #[serde(default)]
+    icons: Vec<ManifestIcon>,
+}
+
+#[derive(Deserialize)]
+struct ManifestIcon {
+    src: String,
+    #[serde(default)]
+    sizes: Option<String>,
+}
+
+/// Fetch a page and extract its metadata, including icons from its web app manifest
+pub async fn fetch(client: &Client, url: &str) -> Result<PageMetadata, MetadataError> {
+    tracing::info!("Fetching metadata for URL: {}", url);
+
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(MetadataError::Unreachable)?;
+
+    if !response.status().is_success() {
+        return Err(MetadataError::Status(response.status()));
+    }
+
+    // Resolve against where we ended up, not where we started
+    let page_url = response.url().clone();
+    let html = response.text().await.map_err(MetadataError::Body)?;
+
+    let mut metadata = extract(&html, &page_url);
+
+    if let Some(manifest_url) = metadata.manifest_url.clone() {
+        match fetch_manifest_icons(client, &manifest_url).await {
+            Ok(icons) => {
+                metadata.icons.extend(icons);
+                sort_icons(&mut metadata.icons);
+            }
+            Err(e) => tracing::debug!("Failed to read manifest {}: {:?}", manifest_url, e),
+        }
+    }
+
+    tracing::info!(
+        "Successfully fetched metadata for URL: {} ({} icon candidates)",
+        url,
+        metadata.icons.len()
+    );
+    Ok(metadata)
+}
+
+async fn fetch_manifest_icons(client: &Client, manifest_url: &str) -> Result<Vec<IconCandidate>> {
+    let manifest_base = Url::parse(manifest_url)?;
+    let manifest: WebManifest = client
+        .get(manifest_url)
+        .timeout(MANIFEST_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(manifest
+        .icons
+        .into_iter()
+        .filter_map(|icon| {
+            Some(IconCandidate {
+                url: manifest_base.join(icon.src.trim()).ok()?.to_string(),
+                size: parse_sizes(icon.sizes.as_deref()),
+            })
+        })
+        .collect())
+}
+
+/// Extract metadata from a document. Relative URLs are resolved against
+/// `<base href>` when present, otherwise against the page URL.
+pub fn extract(html: &str, page_url: &Url) -> PageMetadata {
+    let document = Html::parse_document(html);
+
+    let base_selector = Selector::parse("base[href]").unwrap();
+    let base_url = document
+        .select(&base_selector)
+        .next()
+        .and_then(|b| b.value().attr("href"))
+        .and_then(|href| page_url.join(href.trim()).ok())
+        .unwrap_or_else(|| page_url.clone());
+
+    let mut meta: Vec<(String, String)> = Vec::new();
+    let meta_selector = Selector::parse("meta[content]").unwrap();
+    for element in document.select(&meta_selector) {
+        let key = element
+            .value()
+            .attr("property")
+            .or_else(|| element.value().attr("name"));
+        if let (Some(key), Some(content)) = (key, element.value().attr("content")) {
+            meta.push((key.trim().to_lowercase(), content.to_string()));
+        }
+    }
+    let meta_content = |keys: &[&str]| {
+        keys.iter().find_map(|key| {
+            meta.iter()
+                .find(|(k, v)| k == key && !v.trim().is_empty())
+                .map(|(_, v)| clean_text(v))
+        })
+    };
+
+    let title_selector = Selector::parse("title").unwrap();
+    let title = meta_content(&["og:title", "twitter:title"]).or_else(|| {
+        document
+            .select(&title_selector)
+            .next()
+            .map(|t| clean_text(&t.text().collect::<String>()))
+            .filter(|t| !t.is_empty())
+    });
+
+    let description = meta_content(&["og:description", "twitter:description", "description"]);
+
+    let image = meta_content(&[
+        "og:image",
+        "og:image:url",
+        "twitter:image",
+        "twitter:image:src",
+    ])
+    .and_then(|src| base_url.join(&src).ok())
+    .map(|u| u.to_string());
+
+    let mut icons = Vec::new();
+    let mut manifest_url = None;
+    let link_selector = Selector::parse("link[rel][href]").unwrap();
+    for element in document.select(&link_selector) {
+        let rel = element.value().attr("rel").unwrap_or("").to_lowercase();
+        let Some(href) = element
+            .value()
+            .attr("href")
+            .and_then(|href| base_url.join(href.trim()).ok())
+        else {
+            continue;
+        };
+
+        let rels: Vec<&str> = rel.split_whitespace().collect();
+        if rels.contains(&"manifest") {
+            manifest_url.get_or_insert(href.to_string());
+            continue;
+        }
+
+        let is_touch_icon = rels
+            .iter()
+            .any(|r| *r == "apple-touch-icon" || *r == "apple-touch-icon-precomposed");
+        // "icon" also covers the legacy "shortcut icon"
+        if !is_touch_icon && !rels.contains(&"icon") {
+            continue;
+        }
+
+        let mut size = parse_sizes(element.value().attr("sizes"));
+        if size == 0 && is_touch_icon {
+            // Apple's default touch icon size
+            size = 180;
+        }
+        icons.push(IconCandidate {
+            url: href.to_string(),
+            size,
+        });
+    }
+    sort_icons(&mut icons);
+
+    PageMetadata {
+        title,
+        description,
+        image,
+        icons,
+        manifest_url,
+    }
+}
+
+/// Largest dimension from a `sizes` attribute like "16x16 32x32" or "any"
+fn parse_sizes(sizes: Option<&str>) -> u32 {
+    let Some(sizes) = sizes else {
+        return 0;
+    };
+
+    sizes
+        .split_whitespace()
+        .filter_map(|size| {
+            if size.eq_ignore_ascii_case("any") {
+                // Scalable (SVG), good at any size
+                return Some(PREFERRED_ICON_SIZE);
+            }
+            let (w, h) = size
+                .to_lowercase()
+                .split_once('x')
+                .map(|(w, h)| (w.parse::<u32>().unwrap_or(0), h.parse::<u32>().unwrap_or(0)))?;
+            Some(w.max(h))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Best icon first: the smallest icon that is at least the preferred size,
+/// then smaller icons from largest to smallest, then icons of unknown size
+fn sort_icons(icons: &mut [IconCandidate]) {
+    icons.sort_by_key(|icon| match icon.size {
+        0 => (2, 0),
+        size if size >= PREFERRED_ICON_SIZE => (0, size),
+        size => (1, u32::MAX - size),
+    });
+}
+
+/// Collapse whitespace. Entities are already decoded by the HTML parser.
+fn clean_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // OpenGraph wins over <title>, and every icon href resolves against <base href>
+    fn test_extract() {
+        let html = r#"<html><head>
+            <base href="/app/">
+            <title>Fallback &amp; Title</title>
+            <meta property="og:title" content="Rock &amp; Roll">
+            <meta name="twitter:description" content="Twitter description">
+            <meta property="og:image" content="//cdn.example.com/card.png">
+            <link rel="shortcut icon" href="favicon.ico">
+            <link rel="icon" sizes="16x16" href="https://static.example.com/16.png">
+            <link rel="icon" sizes="32x32 96x96" href="96.png">
+            <link rel="apple-touch-icon" href="/touch.png">
+            <link rel="manifest" href="site.webmanifest">
+        </head><body></body></html>"#;
+
+        let metadata = extract(html, &Url::parse("https://example.com/page").unwrap());
+
+        assert_eq!(metadata.title.as_deref(), Some("Rock & Roll"));
+        assert_eq!(metadata.description.as_deref(), Some("Twitter description"));
+        assert_eq!(
+            metadata.image.as_deref(),
+            Some("https://cdn.example.com/card.png")
+        );
+        assert_eq!(
+            metadata.manifest_url.as_deref(),
+            Some("https://example.com/app/site.webmanifest")
+        );
+
+        let icons: Vec<&str> = metadata.icons.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(
+            icons,
+            vec![
+                "https://example.com/app/96.png",
+                "https://example.com/touch.png",
+                "https://static.example.com/16.png",
+                "https://example.com/app/favicon.ico",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_title_entities() {
+        let html = "<title>\n  Tom &amp; Jerry &#8211; Home\n</title>";
+        let metadata = extract(html, &Url::parse("https://example.com").unwrap());
+        assert_eq!(metadata.title.as_deref(), Some("Tom & Jerry – Home"));
+    }
+}
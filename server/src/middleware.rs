@@ -1,21 +1,90 @@
-use crate::user_jwt;
-use axum::{http::Request, middleware::Next, response::Response};
+use crate::{AppState, api_tokens, tokens, user_jwt};
+use axum::{
+    extract::State,
+    http::{HeaderMap, Method, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
 
 #[derive(Clone, Debug)]
 pub struct UserContext {
     pub user_id: String,
     pub email: String,
+    /// Session the access token belongs to
+    pub session_id: String,
+    /// Id of the access token itself
+    pub token_id: String,
+    /// Set when the request came with a personal access token instead of a
+    /// session, `session_id` is empty then
+    pub api_token_id: Option<String>,
+}
+
+/// The address a request came from. Behind a reverse proxy every request
+/// comes from the proxy, so with TRUST_PROXY set the address the proxy added
+/// to X-Forwarded-For is used instead. TRUST_PROXY is "true" for one proxy
+/// or the number of proxies in front of the server.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<String> {
+    let hops = trusted_proxies(std::env::var("TRUST_PROXY").ok().as_deref());
+    if hops > 0 {
+        let forwarded: Vec<&str> = headers
+            .get_all("X-Forwarded-For")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        if let Some(ip) = forwarded_ip(&forwarded.join(","), hops) {
+            return Some(ip.to_string());
+        }
+    }
+    peer.map(|peer| peer.ip().to_string())
+}
+
+fn trusted_proxies(var: Option<&str>) -> usize {
+    match var.map(str::trim) {
+        Some("true") => 1,
+        Some(hops) => hops.parse().unwrap_or(0),
+        None => 0,
+    }
+}
+
+/// The client address as seen by the outermost of `hops` proxies. Proxies
+/// append to X-Forwarded-For, so anything left of their entries came from
+/// the client and can't be trusted.
+fn forwarded_ip(forwarded: &str, hops: usize) -> Option<IpAddr> {
+    let entries: Vec<&str> = forwarded.split(',').map(str::trim).collect();
+    let index = entries.len().checked_sub(hops)?;
+    entries[index].parse().ok()
+}
+
+/// Stored icons are loaded by <img> tags, which can't send a bearer token.
+/// They're addressed by content hash, so there's nothing to enumerate.
+fn is_public_icon_path(path: &str) -> bool {
+    path.strip_prefix("/icons/")
+        .is_some_and(|rest| !rest.is_empty() && !rest.contains('/'))
 }
 
 pub async fn authenticate_user(
+    State(app_state): State<AppState>,
     mut req: Request<axum::body::Body>,
     next: Next,
 ) -> Result<Response, axum::http::StatusCode> {
     tracing::debug!("Authenticating user");
 
     // Skip authentication for public paths
-    let public_paths = ["/login", "/register", "/staging_login", "/health"];
-    if public_paths.contains(&req.uri().path()) {
+    let public_paths = [
+        "/login",
+        "/register",
+        "/staging_login",
+        "/health",
+        "/password_reset",
+        "/password_reset/confirm",
+        "/verify_email",
+        "/verify_email/resend",
+        "/refresh",
+        "/search",
+        "/search/suggest",
+    ];
+    if public_paths.contains(&req.uri().path()) || is_public_icon_path(req.uri().path()) {
         tracing::debug!(
             "Skipping authentication for public path: {}",
             req.uri().path()
@@ -28,53 +97,133 @@ pub async fn authenticate_user(
         .get("Authorization")
         .and_then(|v| v.to_str().ok())
         .map(|v| v.trim_start_matches("Bearer ").to_string())
-        .ok_or(axum::http::StatusCode::UNAUTHORIZED)?;
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if token.starts_with(api_tokens::TOKEN_PREFIX) {
+        let user_context =
+            authenticate_api_token(&app_state, &token, req.method(), req.uri().path()).await?;
+        req.extensions_mut().insert(user_context);
+        return Ok(next.run(req).await);
+    }
 
     // Validate JWT token
     let claims = user_jwt::validate_jwt(&token).map_err(|e| {
         tracing::warn!("JWT validation failed: {:?}", e);
-        axum::http::StatusCode::UNAUTHORIZED
+        StatusCode::UNAUTHORIZED
     })?;
 
+    // Tokens from before sessions existed can't be revoked, make them sign
+    // in again
+    if claims.jti.is_empty() || claims.sid.is_empty() {
+        tracing::warn!("Rejecting token without session for {}", claims.user_id);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    if app_state.revocations.is_revoked(&claims.jti)
+        || app_state.revocations.is_revoked(&claims.sid)
+    {
+        tracing::warn!(
+            "Rejecting revoked token {} of session {}",
+            claims.jti,
+            claims.sid
+        );
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
     tracing::debug!("User authenticated: {}", claims.user_id);
 
     // Extract user context from JWT claims
     let user_context = UserContext {
-        user_id: claims.user_id.clone(),
-        email: claims.email.clone(),
+        user_id: claims.user_id,
+        email: claims.email,
+        session_id: claims.sid,
+        token_id: claims.jti,
+        api_token_id: None,
     };
     req.extensions_mut().insert(user_context);
 
-    // Check if the token needs to be refreshed (within 15 minutes of expiration)
-    match user_jwt::needs_refresh(&token) {
-        Ok(true) => {
-            tracing::debug!("JWT token needs refresh for user: {}", claims.user_id);
+    Ok(next.run(req).await)
+}
 
-            // Generate new token
-            if let Ok(new_token) = user_jwt::generate_jwt(&claims.user_id, &claims.email) {
-                tracing::debug!("Generated new JWT token for user: {}", claims.user_id);
+/// Check a personal access token, its expiry and that its scopes cover the
+/// request
+pub async fn authenticate_api_token(
+    app_state: &AppState,
+    token: &str,
+    method: &Method,
+    path: &str,
+) -> Result<UserContext, StatusCode> {
+    let (api_token, email) = app_state
+        .database
+        .get_api_token_by_hash(&tokens::hash(token))
+        .await
+        .map_err(|e| match e.to_string().as_str() {
+            "404" => {
+                tracing::warn!("Unknown API token");
+                StatusCode::UNAUTHORIZED
+            }
+            _ => {
+                tracing::error!("Failed to look up API token: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
 
-                // Run the next middleware and get the response
-                let mut response = next.run(req).await;
+    let expired = api_token.expires_at.as_deref().is_some_and(|expires_at| {
+        chrono::DateTime::parse_from_rfc3339(expires_at)
+            .map_or(true, |expires_at| expires_at <= chrono::Utc::now())
+    });
+    if expired {
+        tracing::warn!("Expired API token {}", api_token.id);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
 
-                // Add the new token to the response headers
-                response.headers_mut().insert(
-                    "X-New-Auth-Token",
-                    axum::http::HeaderValue::from_str(&new_token)
-                        .unwrap_or_else(|_| axum::http::HeaderValue::from_static("")),
-                );
+    let allowed = api_tokens::required_scope(method, path)
+        .is_some_and(|scope| api_tokens::split_scopes(&api_token.scopes).contains(&scope));
+    if !allowed {
+        tracing::warn!(
+            "API token {} not allowed to {} {}",
+            api_token.id,
+            method,
+            path
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
 
-                return Ok(response);
-            }
-        }
-        Ok(false) => {
-            // Token doesn't need refresh, continue
-        }
-        Err(e) => {
-            tracing::debug!("Error checking if token needs refresh: {:?}", e);
-            // Continue with request even if refresh check fails
-        }
+    if let Err(e) = app_state.database.touch_api_token(&api_token.id).await {
+        tracing::error!(
+            "Failed to record use of API token {}: {:?}",
+            api_token.id,
+            e
+        );
     }
 
-    Ok(next.run(req).await)
+    tracing::debug!("User authenticated with API token: {}", api_token.user_id);
+
+    Ok(UserContext {
+        user_id: api_token.user_id,
+        email,
+        session_id: String::new(),
+        token_id: api_token.id.clone(),
+        api_token_id: Some(api_token.id),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // clients can put anything in front of what the proxy appends
+    fn test_forwarded_ip() {
+        let spoofed = "1.2.3.4, 203.0.113.7";
+        assert_eq!(forwarded_ip(spoofed, 1), "203.0.113.7".parse().ok());
+        assert_eq!(forwarded_ip(spoofed, 2), "1.2.3.4".parse().ok());
+        assert_eq!(forwarded_ip("203.0.113.7", 2), None);
+        assert_eq!(forwarded_ip("1.2.3.4, junk", 1), None);
+
+        assert_eq!(trusted_proxies(Some("true")), 1);
+        assert_eq!(trusted_proxies(Some("2")), 2);
+        assert_eq!(trusted_proxies(Some("false")), 0);
+        assert_eq!(trusted_proxies(None), 0);
+    }
 }
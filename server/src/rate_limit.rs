@@ -0,0 +1,416 @@
+use crate::middleware::{UserContext, client_ip};
+use axum::{
+    Json,
+    body::Body,
+    extract::ConnectInfo,
+    http::{HeaderMap, HeaderName, HeaderValue, Request, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+
+/// Buckets that have refilled completely are dropped this often, a full
+/// bucket behaves the same as a missing one
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Who shares a bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// Every signed in user gets their own bucket. Requests without a user
+    /// fall back to their address.
+    User,
+    /// Every client address gets its own bucket
+    Ip,
+    /// One bucket for everyone, for upstream APIs with a global limit
+    Route,
+}
+
+/// A token bucket holding up to `capacity` requests, refilling one request
+/// every `refill`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub key: Key,
+    pub capacity: u32,
+    pub refill: Duration,
+}
+
+impl Quota {
+    /// `requests` per `window`, all of which can be used at once
+    pub fn new(key: Key, requests: u32, window: Duration) -> Quota {
+        let requests = requests.max(1);
+        Quota {
+            key,
+            capacity: requests,
+            refill: window / requests,
+        }
+    }
+
+    /// Time for an empty bucket to fill up again
+    pub fn window(&self) -> Duration {
+        self.refill * self.capacity
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(quota: &Quota, now: Instant) -> Bucket {
+        Bucket {
+            tokens: quota.capacity as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, quota: &Quota, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        let refilled = elapsed.as_secs_f64() / quota.refill.as_secs_f64();
+        self.tokens = (self.tokens + refilled).min(quota.capacity as f64);
+        self.updated = now;
+    }
+
+    fn is_full(&self, quota: &Quota, now: Instant) -> bool {
+        let mut bucket = *self;
+        bucket.refill(quota, now);
+        bucket.tokens >= quota.capacity as f64
+    }
+
+    /// Time until the bucket holds `tokens` again
+    fn time_until(&self, quota: &Quota, tokens: f64) -> Duration {
+        let missing = (tokens - self.tokens).max(0.0);
+        quota.refill.mul_f64(missing)
+    }
+}
+
+/// Outcome of a request against one quota, reported in RateLimit-* headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub quota: Quota,
+    pub remaining: u32,
+    /// Until the bucket is full again
+    pub reset: Duration,
+    /// Until the next request is allowed, zero if it's allowed now
+    pub retry_after: Duration,
+}
+
+impl Decision {
+    fn new(allowed: bool, quota: Quota, bucket: &Bucket) -> Decision {
+        Decision {
+            allowed,
+            quota,
+            remaining: bucket.tokens.floor() as u32,
+            reset: bucket.time_until(&quota, quota.capacity as f64),
+            retry_after: bucket.time_until(&quota, 1.0),
+        }
+    }
+
+    /// RateLimit-* headers as in the IETF draft, plus Retry-After when the
+    /// request was refused
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            ("ratelimit-limit", self.quota.capacity.to_string()),
+            ("ratelimit-remaining", self.remaining.to_string()),
+            ("ratelimit-reset", ceil_secs(self.reset).to_string()),
+            (
+                "ratelimit-policy",
+                format!(
+                    "{};w={}",
+                    self.quota.capacity,
+                    ceil_secs(self.quota.window())
+                ),
+            ),
+        ];
+        if !self.allowed {
+            headers.push(("retry-after", ceil_secs(self.retry_after).to_string()));
+        }
+        headers
+    }
+}
+
+/// Every header `Decision::headers` can set, for CORS to expose to the
+/// client
+pub fn exposed_headers() -> [HeaderName; 5] {
+    [
+        HeaderName::from_static("ratelimit-limit"),
+        HeaderName::from_static("ratelimit-remaining"),
+        HeaderName::from_static("ratelimit-reset"),
+        HeaderName::from_static("ratelimit-policy"),
+        HeaderName::from_static("retry-after"),
+    ]
+}
+
+/// Whole seconds, rounded up so clients don't retry a moment too early
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_millis().div_ceil(1000) as u64
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    buckets: HashMap<(usize, String), Bucket>,
+    pruned_at: Option<Instant>,
+}
+
+impl Buckets {
+    /// Take a token from every quota's bucket, or from none of them if any
+    /// is empty. `keys` holds the bucket key for each quota.
+    fn take(&mut self, quotas: &[Quota], keys: &[String], now: Instant) -> Decision {
+        self.prune(quotas, now);
+
+        let mut buckets = Vec::with_capacity(quotas.len());
+        for (index, (quota, key)) in quotas.iter().zip(keys).enumerate() {
+            let mut bucket = self
+                .buckets
+                .get(&(index, key.clone()))
+                .copied()
+                .unwrap_or_else(|| Bucket::full(quota, now));
+            bucket.refill(quota, now);
+            buckets.push(bucket);
+        }
+
+        let refused = quotas
+            .iter()
+            .zip(&buckets)
+            .filter(|(_, bucket)| bucket.tokens < 1.0)
+            .map(|(quota, bucket)| Decision::new(false, *quota, bucket))
+            .max_by_key(|decision| decision.retry_after);
+        if let Some(decision) = refused {
+            return decision;
+        }
+
+        let mut tightest: Option<Decision> = None;
+        for (index, (quota, mut bucket)) in quotas.iter().zip(buckets).enumerate() {
+            bucket.tokens -= 1.0;
+            self.buckets.insert((index, keys[index].clone()), bucket);
+            let decision = Decision::new(true, *quota, &bucket);
+            if tightest.is_none_or(|tightest| decision.remaining < tightest.remaining) {
+                tightest = Some(decision);
+            }
+        }
+        tightest.expect("a rate limit has at least one quota")
+    }
+
+    /// Put back the token a request took
+    fn refund(&mut self, quotas: &[Quota], keys: &[String]) {
+        for (index, (quota, key)) in quotas.iter().zip(keys).enumerate() {
+            if let Some(bucket) = self.buckets.get_mut(&(index, key.clone())) {
+                bucket.tokens = (bucket.tokens + 1.0).min(quota.capacity as f64);
+            }
+        }
+    }
+
+    fn prune(&mut self, quotas: &[Quota], now: Instant) {
+        if self
+            .pruned_at
+            .is_some_and(|pruned_at| now.duration_since(pruned_at) < PRUNE_INTERVAL)
+        {
+            return;
+        }
+        self.buckets
+            .retain(|(index, _), bucket| !bucket.is_full(&quotas[*index], now));
+        self.pruned_at = Some(now);
+    }
+}
+
+/// Token bucket rate limiting for the routes it wraps. Quotas are checked
+/// together, a request passes only if every one of them has room. Put it on
+/// a route with `.layer()` so it runs after authentication and can key
+/// buckets by user.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    quotas: Arc<[Quota]>,
+    refund_errors: bool,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimitLayer {
+    pub fn new(quota: Quota) -> RateLimitLayer {
+        RateLimitLayer {
+            quotas: Arc::from([quota]),
+            refund_errors: false,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Add another quota that has to have room as well
+    pub fn and(mut self, quota: Quota) -> RateLimitLayer {
+        let mut quotas = self.quotas.to_vec();
+        quotas.push(quota);
+        self.quotas = Arc::from(quotas);
+        self
+    }
+
+    /// Only count requests that succeed, for actions that are limited by
+    /// how often they happen rather than how often they're tried
+    pub fn refund_errors(mut self) -> RateLimitLayer {
+        self.refund_errors = true;
+        self
+    }
+
+    fn keys(&self, req: &Request<Body>) -> Vec<String> {
+        let peer = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| *peer);
+        let ip = || client_ip(req.headers(), peer).unwrap_or_else(|| "unknown".to_string());
+        let user = req
+            .extensions()
+            .get::<UserContext>()
+            .map(|user_context| user_context.user_id.clone());
+
+        self.quotas
+            .iter()
+            .map(|quota| match quota.key {
+                Key::User => user
+                    .as_ref()
+                    .map(|user_id| format!("user:{}", user_id))
+                    .unwrap_or_else(|| format!("ip:{}", ip())),
+                Key::Ip => format!("ip:{}", ip()),
+                Key::Route => "route".to_string(),
+            })
+            .collect()
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limits: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limits: RateLimitLayer,
+}
+
+impl<S> Service<Request<Body>> for RateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // The clone that was polled ready handles this request
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limits = self.limits.clone();
+
+        Box::pin(async move {
+            let keys = limits.keys(&req);
+            let decision =
+                limits
+                    .buckets
+                    .lock()
+                    .unwrap()
+                    .take(&limits.quotas, &keys, Instant::now());
+
+            if !decision.allowed {
+                tracing::warn!("Rate limit exceeded on {} for {:?}", req.uri().path(), keys);
+                let mut response = (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Json(json!({
+                        "error": "Too many requests",
+                        "retry_after": ceil_secs(decision.retry_after),
+                    })),
+                )
+                    .into_response();
+                insert_headers(response.headers_mut(), &decision);
+                return Ok(response);
+            }
+
+            let mut response = inner.call(req).await?;
+            let status = response.status();
+            if limits.refund_errors && (status.is_client_error() || status.is_server_error()) {
+                limits.buckets.lock().unwrap().refund(&limits.quotas, &keys);
+            } else {
+                insert_headers(response.headers_mut(), &decision);
+            }
+            Ok(response)
+        })
+    }
+}
+
+fn insert_headers(headers: &mut HeaderMap, decision: &Decision) {
+    for (name, value) in decision.headers() {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let per_user = Quota::new(Key::User, 2, Duration::from_secs(10));
+        let global = Quota::new(Key::Route, 3, Duration::from_secs(3));
+        let quotas = [per_user, global];
+        let alice = ["user:alice".to_string(), "route".to_string()];
+        let bob = ["user:bob".to_string(), "route".to_string()];
+        let mut buckets = Buckets::default();
+        let now = Instant::now();
+
+        let first = buckets.take(&quotas, &alice, now);
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 1);
+        assert_eq!(first.quota, per_user);
+        assert!(buckets.take(&quotas, &alice, now).allowed);
+
+        // Alice's own bucket is empty, the shared one isn't
+        let refused = buckets.take(&quotas, &alice, now);
+        assert!(!refused.allowed);
+        assert_eq!(refused.retry_after, Duration::from_secs(5));
+        assert_eq!(refused.headers().last().unwrap().1, "5");
+
+        // Refused requests take nothing, so Bob still gets the last one
+        assert!(buckets.take(&quotas, &bob, now).allowed);
+        let refused = buckets.take(&quotas, &bob, now);
+        assert_eq!(refused.quota, global);
+        assert_eq!(refused.retry_after, Duration::from_secs(1));
+
+        assert!(
+            buckets
+                .take(&quotas, &bob, now + Duration::from_secs(1))
+                .allowed
+        );
+        buckets.refund(&quotas, &bob);
+        assert!(
+            buckets
+                .take(&quotas, &bob, now + Duration::from_secs(1))
+                .allowed
+        );
+        assert!(
+            buckets
+                .take(&quotas, &alice, now + Duration::from_secs(6))
+                .allowed
+        );
+    }
+}
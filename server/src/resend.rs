@@ -3,13 +3,15 @@ use resend_rs::{Resend, Result};
 
 pub struct ResendClient {
     client: Resend,
+    from: String,
 }
 
 impl ResendClient {
-    pub fn new() -> Self {
+    pub fn new(api_key: &str, from: String) -> Self {
         tracing::info!("Initializing Resend client");
         ResendClient {
-            client: Resend::default(),
+            client: Resend::new(api_key),
+            from,
         }
     }
 
@@ -20,10 +22,9 @@ impl ResendClient {
         email_body: &str,
     ) -> Result<()> {
         tracing::info!("Sending email to: {}", customer_support_email);
-        let from = "evan@updates.omega-tab.evanrobertson.dev";
         let to = [customer_support_email];
 
-        let email = CreateEmailBaseOptions::new(from, to, subject).with_html(email_body);
+        let email = CreateEmailBaseOptions::new(&self.from, to, subject).with_html(email_body);
 
         match self.client.emails.send(email).await {
             Ok(_email) => {
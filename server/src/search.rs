@@ -0,0 +1,66 @@
+/// Markers wrapped around matches by highlight()/snippet(). Private use
+/// characters can't clash with real link text, and are swapped for <mark>
+/// after the text has been HTML escaped.
+pub const MATCH_START: &str = "\u{E000}";
+pub const MATCH_END: &str = "\u{E001}";
+
+/// Turn free text into an FTS5 query. Every term has to match, the last one
+/// as a prefix so results show up while the user is still typing.
+/// Terms are quoted, so FTS5 syntax (AND, NEAR, column filters) in user input is inert.
+pub fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| format!("\"{}\"", t.to_lowercase()))
+        .collect();
+
+    let (last, rest) = terms.split_last()?;
+    let mut query = rest.to_vec();
+    query.push(format!("{}*", last));
+    Some(query.join(" "))
+}
+
+/// HTML escape FTS5 output and turn the match markers into <mark> tags
+pub fn render_highlight(text: &str) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => rendered.push_str("&amp;"),
+            '<' => rendered.push_str("&lt;"),
+            '>' => rendered.push_str("&gt;"),
+            '"' => rendered.push_str("&quot;"),
+            '\'' => rendered.push_str("&#39;"),
+            _ => rendered.push(c),
+        }
+    }
+    rendered
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // punctuation splits terms and the last term matches as a prefix
+    fn test_fts_query() {
+        assert_eq!(
+            fts_query("rust docs.rs/std").as_deref(),
+            Some("\"rust\" \"docs\" \"rs\" \"std\"*")
+        );
+        assert_eq!(
+            fts_query("title:x OR NEAR").as_deref(),
+            Some("\"title\" \"x\" \"or\" \"near\"*")
+        );
+        assert_eq!(fts_query("  -- "), None);
+    }
+
+    #[test]
+    fn test_render_highlight() {
+        assert_eq!(
+            render_highlight("<b>\u{E000}Rust\u{E001}</b> & co"),
+            "&lt;b&gt;<mark>Rust</mark>&lt;/b&gt; &amp; co"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use serde::Deserialize;
+
+/// Days searches are kept for when SEARCH_HISTORY_RETENTION_DAYS isn't set
+const DEFAULT_RETENTION_DAYS: i64 = 365;
+
+/// Longest retention a user can pick, ten years
+pub const MAX_RETENTION_DAYS: i64 = 3650;
+
+/// Most entries one history request returns
+pub const MAX_ENTRIES: i64 = 500;
+
+/// History preferences kept in the settings blob
+#[derive(Debug, Default, Deserialize)]
+pub struct HistorySettings {
+    /// The privacy switch, nothing is recorded while it's off
+    #[serde(default)]
+    pub search_history: bool,
+}
+
+/// Retention for users who haven't picked one, from
+/// SEARCH_HISTORY_RETENTION_DAYS
+pub fn default_retention_days() -> i64 {
+    parse_retention(
+        std::env::var("SEARCH_HISTORY_RETENTION_DAYS")
+            .ok()
+            .as_deref(),
+    )
+}
+
+fn parse_retention(var: Option<&str>) -> i64 {
+    var.and_then(|days| days.trim().parse().ok())
+        .filter(is_valid_retention)
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+pub fn is_valid_retention(days: &i64) -> bool {
+    (1..=MAX_RETENTION_DAYS).contains(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retention() {
+        assert_eq!(parse_retention(Some("30")), 30);
+        assert_eq!(parse_retention(Some("0")), DEFAULT_RETENTION_DAYS);
+        assert_eq!(parse_retention(None), DEFAULT_RETENTION_DAYS);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Access tokens are only checked against the revocation list, so keep them
+/// short. Clients get a new one from their refresh token.
+pub const ACCESS_TOKEN_TTL_MINS: i64 = 15;
+
+/// How long a session survives without being used, unless SESSION_TTL_DAYS
+/// says otherwise
+const DEFAULT_SESSION_TTL_DAYS: i64 = 30;
+
+/// Longest user agent we keep to describe a session
+const MAX_USER_AGENT_LENGTH: usize = 200;
+
+pub fn access_token_ttl() -> Duration {
+    Duration::minutes(ACCESS_TOKEN_TTL_MINS)
+}
+
+pub fn session_ttl() -> Duration {
+    let days = std::env::var("SESSION_TTL_DAYS")
+        .ok()
+        .and_then(|days| days.trim().parse::<i64>().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_SESSION_TTL_DAYS);
+    Duration::days(days)
+}
+
+/// Sessions are refreshed on use, so they expire a full TTL after the last
+/// refresh
+pub fn session_expires_at(now: DateTime<Utc>) -> DateTime<Utc> {
+    now + session_ttl()
+}
+
+/// Until when a revoked id has to be remembered. Every access token minted
+/// before the revocation has expired by then.
+pub fn revoked_until(now: DateTime<Utc>) -> i64 {
+    (now + access_token_ttl()).timestamp()
+}
+
+pub fn describe_client(user_agent: Option<&str>) -> Option<String> {
+    let user_agent = user_agent?.trim();
+    if user_agent.is_empty() {
+        return None;
+    }
+    Some(user_agent.chars().take(MAX_USER_AGENT_LENGTH).collect())
+}
+
+/// Revoked access token ids and session ids, checked by the auth middleware
+/// on every request. Backed by the revoked_tokens table so revocations
+/// survive a restart.
+#[derive(Clone, Default)]
+pub struct Revocations {
+    ids: Arc<RwLock<HashMap<String, i64>>>,
+}
+
+impl Revocations {
+    pub fn new(revoked: Vec<(String, i64)>) -> Self {
+        Revocations {
+            ids: Arc::new(RwLock::new(revoked.into_iter().collect())),
+        }
+    }
+
+    /// Reject `id` until the unix timestamp `until`
+    pub fn revoke(&self, id: &str, until: i64) {
+        let mut ids = self.ids.write().unwrap_or_else(|e| e.into_inner());
+        let now = Utc::now().timestamp();
+        ids.retain(|_, until| *until > now);
+        ids.insert(id.to_string(), until);
+    }
+
+    pub fn is_revoked(&self, id: &str) -> bool {
+        let ids = self.ids.read().unwrap_or_else(|e| e.into_inner());
+        ids.get(id)
+            .is_some_and(|until| *until > Utc::now().timestamp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revocations() {
+        let now = Utc::now();
+        let revocations = Revocations::new(vec![("old".to_string(), now.timestamp() - 1)]);
+        assert!(!revocations.is_revoked("old"));
+
+        revocations.revoke("session", revoked_until(now));
+        assert!(revocations.is_revoked("session"));
+        assert!(!revocations.is_revoked("other"));
+
+        assert_eq!(describe_client(Some("  ")), None);
+        assert_eq!(
+            describe_client(Some(&"x".repeat(500))).map(|ua| ua.len()),
+            Some(MAX_USER_AGENT_LENGTH)
+        );
+    }
+}
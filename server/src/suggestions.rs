@@ -0,0 +1,569 @@
+use crate::brave::Brave;
+use crate::database::Database;
+use crate::search;
+use crate::search_history;
+use anyhow::Result;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Providers used when SUGGEST_PROVIDERS isn't set, earlier ones rank first.
+/// The opensearch provider sends every keystroke to a third party, so it's
+/// only used when named.
+const DEFAULT_PROVIDERS: &str = "history,links,brave";
+
+/// DuckDuckGo answers in the OpenSearch suggestions format, used when the
+/// opensearch provider is on without SUGGEST_OPENSEARCH_URL
+const DEFAULT_OPENSEARCH_URL: &str =
+    "https://duckduckgo.com/ac/?q=%s&type=list&kl={region}-{language}";
+
+/// Most suggestions shown for one query
+pub const MAX_SUGGESTIONS: usize = 10;
+
+/// Local providers only get a few rows so web results still fit
+const LOCAL_LIMIT: i64 = 3;
+
+const LOCAL_TIMEOUT: Duration = Duration::from_millis(500);
+const WEB_TIMEOUT: Duration = Duration::from_millis(1500);
+
+pub type SuggestFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<Suggestion>>> + Send + 'a>>;
+
+/// One suggestion, labelled with the provider it came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Suggestion {
+    pub query: String,
+    pub source: String,
+    /// Set when the suggestion opens a page rather than running a search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Who is asking, and in which language and region
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuggestContext {
+    pub user_id: String,
+    /// Lowercase language code, like "en"
+    pub language: String,
+    /// Uppercase country code, like "GB", None for no region
+    pub region: Option<String>,
+}
+
+/// Locale preferences kept in the settings blob
+#[derive(Debug, Default, Deserialize)]
+pub struct LocaleSettings {
+    pub locale: Option<String>,
+    pub region: Option<String>,
+}
+
+/// Something that can suggest queries as the user types
+pub trait SuggestionProvider: Send + Sync {
+    /// Label put on this provider's suggestions
+    fn source(&self) -> &str;
+
+    /// A provider that doesn't answer in time is left out
+    fn timeout(&self) -> Duration;
+
+    fn suggest<'a>(&'a self, query: &'a str, context: &'a SuggestContext) -> SuggestFuture<'a>;
+}
+
+/// Queries that only differ in case or spacing are the same query
+pub fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Split a locale like "en-GB" or "pt_br" into a language and a region
+pub fn parse_locale(locale: &str) -> Option<(String, Option<String>)> {
+    let mut parts = locale.trim().split(['-', '_']);
+    let language = parts.next()?;
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let region = match parts.next() {
+        Some(region) => Some(parse_region(region)?),
+        None => None,
+    };
+    Some((language.to_lowercase(), region))
+}
+
+/// Two letter country code, uppercased
+pub fn parse_region(region: &str) -> Option<String> {
+    let region = region.trim();
+    (region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()))
+        .then(|| region.to_uppercase())
+}
+
+impl SuggestContext {
+    /// The user's own locale and region win, then the browser's language,
+    /// then English without a region
+    pub fn new(
+        user_id: &str,
+        settings: &LocaleSettings,
+        accept_language: Option<&str>,
+    ) -> SuggestContext {
+        let browser_locale = accept_language
+            .and_then(|header| header.split([',', ';']).next())
+            .and_then(parse_locale);
+        let (language, locale_region) = settings
+            .locale
+            .as_deref()
+            .and_then(parse_locale)
+            .or(browser_locale)
+            .unwrap_or_else(|| ("en".to_string(), None));
+        let region = settings
+            .region
+            .as_deref()
+            .and_then(parse_region)
+            .or(locale_region);
+
+        SuggestContext {
+            user_id: user_id.to_string(),
+            language,
+            region,
+        }
+    }
+}
+
+/// Combine provider results in provider order, dropping repeats and
+/// keeping at most `limit`
+pub fn merge(results: Vec<Vec<Suggestion>>, limit: usize) -> Vec<Suggestion> {
+    let mut seen = HashSet::new();
+    results
+        .into_iter()
+        .flatten()
+        .filter(|suggestion| {
+            let key = match &suggestion.url {
+                Some(url) => url.clone(),
+                None => normalize_query(&suggestion.query),
+            };
+            !key.is_empty() && seen.insert(key)
+        })
+        .take(limit)
+        .collect()
+}
+
+impl SuggestionProvider for Brave {
+    fn source(&self) -> &str {
+        "brave"
+    }
+
+    fn timeout(&self) -> Duration {
+        WEB_TIMEOUT
+    }
+
+    fn suggest<'a>(&'a self, query: &'a str, context: &'a SuggestContext) -> SuggestFuture<'a> {
+        Box::pin(async move {
+            let country = context.region.as_deref().unwrap_or("ALL");
+            let suggestions = self
+                .get_suggestions(query, country, &context.language)
+                .await?;
+            Ok(suggestions
+                .into_iter()
+                .map(|suggestion| Suggestion {
+                    query: suggestion.query,
+                    source: self.source().to_string(),
+                    url: None,
+                })
+                .collect())
+        })
+    }
+}
+
+/// Any endpoint answering in the OpenSearch suggestions JSON format,
+/// `["query", ["suggestion", ...]]`, DuckDuckGo by default
+pub struct OpenSearchProvider {
+    client: Client,
+    /// URL with `%s` for the query, `{language}` and `{region}`
+    template: String,
+    /// The endpoint's host, used as the label
+    source: String,
+}
+
+impl OpenSearchProvider {
+    pub fn new(template: String) -> Result<Self> {
+        let source = url::Url::parse(&template.replace("%s", "q"))?
+            .host_str()
+            .map(|host| host.trim_start_matches("www.").to_string())
+            .ok_or_else(|| anyhow::anyhow!("Suggestion URL has no host"))?;
+        let client = Client::builder().timeout(WEB_TIMEOUT).build()?;
+        Ok(OpenSearchProvider {
+            client,
+            template,
+            source,
+        })
+    }
+
+    fn url(&self, query: &str, context: &SuggestContext) -> String {
+        let region = context
+            .region
+            .as_deref()
+            .map(str::to_lowercase)
+            // DuckDuckGo's code for no region
+            .unwrap_or_else(|| "wt".to_string());
+        self.template
+            .replace("{language}", &context.language)
+            .replace("{region}", &region)
+            .replace(
+                "%s",
+                &url::form_urlencoded::byte_serialize(query.as_bytes()).collect::<String>(),
+            )
+    }
+}
+
+/// The suggestions in an OpenSearch suggestions response
+pub fn parse_opensearch(body: &serde_json::Value) -> Option<Vec<String>> {
+    let suggestions = body.as_array()?.get(1)?.as_array()?;
+    Some(
+        suggestions
+            .iter()
+            .filter_map(|suggestion| suggestion.as_str().map(str::to_string))
+            .collect(),
+    )
+}
+
+impl SuggestionProvider for OpenSearchProvider {
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn timeout(&self) -> Duration {
+        WEB_TIMEOUT
+    }
+
+    fn suggest<'a>(&'a self, query: &'a str, context: &'a SuggestContext) -> SuggestFuture<'a> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .get(self.url(query, context))
+                .send()
+                .await?
+                .error_for_status()?;
+            let body: serde_json::Value = response.json().await?;
+            let suggestions = parse_opensearch(&body)
+                .ok_or_else(|| anyhow::anyhow!("Not an OpenSearch suggestions response"))?;
+            Ok(suggestions
+                .into_iter()
+                .map(|query| Suggestion {
+                    query,
+                    source: self.source.clone(),
+                    url: None,
+                })
+                .collect())
+        })
+    }
+}
+
+/// The user's own links, matched like the link search
+pub struct LinksProvider {
+    database: Database,
+}
+
+impl SuggestionProvider for LinksProvider {
+    fn source(&self) -> &str {
+        "links"
+    }
+
+    fn timeout(&self) -> Duration {
+        LOCAL_TIMEOUT
+    }
+
+    fn suggest<'a>(&'a self, query: &'a str, context: &'a SuggestContext) -> SuggestFuture<'a> {
+        Box::pin(async move {
+            let Some(fts_query) = search::fts_query(query) else {
+                return Ok(Vec::new());
+            };
+            let hits = self
+                .database
+                .search_links(&context.user_id, "user", &fts_query, LOCAL_LIMIT)
+                .await?;
+            Ok(hits
+                .into_iter()
+                .map(|hit| Suggestion {
+                    query: hit.link.title,
+                    source: self.source().to_string(),
+                    url: Some(hit.link.url),
+                })
+                .collect())
+        })
+    }
+}
+
+/// Earlier searches starting with the query, best frecency first
+pub struct HistoryProvider {
+    database: Database,
+    retention_days: i64,
+}
+
+impl SuggestionProvider for HistoryProvider {
+    fn source(&self) -> &str {
+        "history"
+    }
+
+    fn timeout(&self) -> Duration {
+        LOCAL_TIMEOUT
+    }
+
+    fn suggest<'a>(&'a self, query: &'a str, context: &'a SuggestContext) -> SuggestFuture<'a> {
+        Box::pin(async move {
+            let entries = self
+                .database
+                .get_search_history(
+                    &context.user_id,
+                    &normalize_query(query),
+                    self.retention_days,
+                    LOCAL_LIMIT,
+                )
+                .await?;
+            Ok(entries
+                .into_iter()
+                .map(|entry| Suggestion {
+                    query: entry.query,
+                    source: self.source().to_string(),
+                    url: None,
+                })
+                .collect())
+        })
+    }
+}
+
+/// The configured providers, asked all at once
+pub struct Suggestions {
+    providers: Vec<Arc<dyn SuggestionProvider>>,
+}
+
+impl Suggestions {
+    /// Providers named in SUGGEST_PROVIDERS, in order. Brave is skipped
+    /// without an API key, SUGGEST_OPENSEARCH_URL points the opensearch
+    /// provider somewhere other than DuckDuckGo.
+    pub fn from_env(database: &Database) -> Suggestions {
+        let names =
+            std::env::var("SUGGEST_PROVIDERS").unwrap_or_else(|_| DEFAULT_PROVIDERS.to_string());
+        let mut providers: Vec<Arc<dyn SuggestionProvider>> = Vec::new();
+
+        for name in names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            match name {
+                "history" => providers.push(Arc::new(HistoryProvider {
+                    database: database.clone(),
+                    retention_days: search_history::default_retention_days(),
+                })),
+                "links" => providers.push(Arc::new(LinksProvider {
+                    database: database.clone(),
+                })),
+                "brave" => {
+                    if let Some(brave) = Brave::from_env() {
+                        providers.push(Arc::new(brave));
+                    }
+                }
+                "opensearch" => {
+                    let template = std::env::var("SUGGEST_OPENSEARCH_URL")
+                        .ok()
+                        .filter(|url| !url.trim().is_empty())
+                        .unwrap_or_else(|| DEFAULT_OPENSEARCH_URL.to_string());
+                    match OpenSearchProvider::new(template) {
+                        Ok(provider) => providers.push(Arc::new(provider)),
+                        Err(e) => tracing::error!("Invalid SUGGEST_OPENSEARCH_URL: {:?}", e),
+                    }
+                }
+                other => tracing::warn!("Unknown suggestion provider {:?}", other),
+            }
+        }
+
+        tracing::info!(
+            "Suggestion providers: {:?}",
+            providers.iter().map(|p| p.source()).collect::<Vec<_>>()
+        );
+        Suggestions { providers }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    /// Ask every provider at once and merge what comes back in time. When
+    /// nothing comes back and a provider failed, that failure is returned,
+    /// Brave's first so its 429s reach the caller.
+    pub async fn suggest(&self, query: &str, context: SuggestContext) -> Result<Vec<Suggestion>> {
+        let query: Arc<str> = Arc::from(query);
+        let context = Arc::new(context);
+
+        let tasks: Vec<_> = self
+            .providers
+            .iter()
+            .map(|provider| {
+                let provider = provider.clone();
+                let query = query.clone();
+                let context = context.clone();
+                tokio::spawn(async move {
+                    let suggestions = tokio::time::timeout(
+                        provider.timeout(),
+                        provider.suggest(&query, &context),
+                    )
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out")));
+                    if let Err(e) = &suggestions {
+                        tracing::warn!("{} suggestions failed: {:?}", provider.source(), e);
+                    }
+                    (provider.source().to_string(), suggestions)
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        let mut failures = Vec::new();
+        for task in tasks {
+            match task.await {
+                Ok((_, Ok(suggestions))) => results.push(suggestions),
+                Ok((source, Err(e))) => failures.push((source, e)),
+                Err(e) => failures.push((String::new(), e.into())),
+            }
+        }
+
+        let suggestions = merge(results, MAX_SUGGESTIONS);
+        if suggestions.is_empty() && !failures.is_empty() {
+            let index = failures
+                .iter()
+                .position(|(source, _)| source == "brave")
+                .unwrap_or(0);
+            return Err(failures.swap_remove(index).1);
+        }
+        Ok(suggestions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(query: &str, source: &str, url: Option<&str>) -> Suggestion {
+        Suggestion {
+            query: query.to_string(),
+            source: source.to_string(),
+            url: url.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let merged = merge(
+            vec![
+                vec![suggestion("Rust Book", "history", None)],
+                vec![suggestion("Rust", "links", Some("https://rust-lang.org"))],
+                vec![
+                    suggestion("rust  book", "brave", None),
+                    suggestion("rust", "brave", None),
+                    suggestion("rustup", "brave", None),
+                ],
+            ],
+            3,
+        );
+        assert_eq!(
+            merged,
+            vec![
+                suggestion("Rust Book", "history", None),
+                suggestion("Rust", "links", Some("https://rust-lang.org")),
+                suggestion("rust", "brave", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locale() {
+        assert_eq!(
+            parse_locale("pt_br"),
+            Some(("pt".to_string(), Some("BR".to_string())))
+        );
+        assert_eq!(parse_locale("english"), None);
+
+        let settings = LocaleSettings {
+            locale: None,
+            region: Some("ca".to_string()),
+        };
+        let context = SuggestContext::new("u", &settings, Some("fr-FR,fr;q=0.9"));
+        assert_eq!(context.language, "fr");
+        assert_eq!(context.region.as_deref(), Some("CA"));
+
+        let context = SuggestContext::new("u", &LocaleSettings::default(), None);
+        assert_eq!((context.language.as_str(), context.region), ("en", None));
+
+        assert_eq!(
+            parse_opensearch(&serde_json::json!(["ru", ["rust", "ruby"]])),
+            Some(vec!["rust".to_string(), "ruby".to_string()])
+        );
+    }
+
+    /// Answers with fixed suggestions, or fails with `error`
+    struct Fixed {
+        source: &'static str,
+        queries: Vec<&'static str>,
+        error: Option<&'static str>,
+    }
+
+    impl SuggestionProvider for Fixed {
+        fn source(&self) -> &str {
+            self.source
+        }
+
+        fn timeout(&self) -> Duration {
+            LOCAL_TIMEOUT
+        }
+
+        fn suggest<'a>(&'a self, _: &'a str, _: &'a SuggestContext) -> SuggestFuture<'a> {
+            Box::pin(async move {
+                if let Some(error) = self.error {
+                    return Err(anyhow::anyhow!(error));
+                }
+                Ok(self
+                    .queries
+                    .iter()
+                    .map(|query| suggestion(query, self.source, None))
+                    .collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    // a rate limited Brave isn't hidden behind local providers with nothing to say
+    async fn test_suggest_failures() {
+        let provider = |source, queries, error| -> Arc<dyn SuggestionProvider> {
+            Arc::new(Fixed {
+                source,
+                queries,
+                error,
+            })
+        };
+        let context = SuggestContext::new("u", &LocaleSettings::default(), None);
+
+        let suggestions = Suggestions {
+            providers: vec![
+                provider("links", vec![], Some("database is locked")),
+                provider("history", vec![], None),
+                provider("brave", vec![], Some("429")),
+            ],
+        };
+        let error = suggestions
+            .suggest("ru", context.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "429");
+
+        let suggestions = Suggestions {
+            providers: vec![
+                provider("history", vec!["rust"], None),
+                provider("brave", vec![], Some("429")),
+            ],
+        };
+        let found = suggestions.suggest("ru", context).await.unwrap();
+        assert_eq!(found, vec![suggestion("rust", "history", None)]);
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+
+/// Longest tag name we accept, in characters
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// Upper bound on tags per link, keeps the link cards readable
+pub const MAX_TAGS_PER_LINK: usize = 32;
+
+/// Trim and collapse whitespace in a tag name. Returns None for names that
+/// are empty or too long. Case is kept, names compare case-insensitively.
+pub fn normalize_tag_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() || name.chars().count() > MAX_TAG_LENGTH {
+        return None;
+    }
+    Some(name)
+}
+
+/// Normalize a list of tag names, dropping invalid ones and case-insensitive duplicates
+pub fn normalize_tags(names: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    names
+        .iter()
+        .filter_map(|name| normalize_tag_name(name))
+        .filter(|name| seen.insert(name.to_lowercase()))
+        .collect()
+}
+
+/// Parse the comma separated `tags` query parameter
+pub fn parse_tag_filter(filter: Option<&str>) -> Vec<String> {
+    filter
+        .map(|f| normalize_tags(f.split(',').map(str::to_string).collect()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_tags() {
+        assert_eq!(
+            normalize_tags(vec![
+                "  on  call ".to_string(),
+                "Docs".to_string(),
+                "docs".to_string(),
+                "".to_string(),
+                "x".repeat(MAX_TAG_LENGTH + 1),
+            ]),
+            vec!["on call", "Docs"]
+        );
+        assert_eq!(
+            parse_tag_filter(Some("prod,, oncall")),
+            vec!["prod", "oncall"]
+        );
+    }
+}
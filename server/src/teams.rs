@@ -0,0 +1,68 @@
+/// Seeded in 001_initial_schema.sql, shared workspaces start on it unless
+/// their creator has a subscription of their own
+pub const FREE_PLAN_ID: &str = "a0b1c2d3-e4f5-6789-abcd-ef0123456789";
+
+/// Longest team or organization name we accept, in characters
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// Owner types for things members share, next to "user"
+pub const SHARED_OWNER_TYPES: [&str; 2] = ["team", "organization"];
+
+/// What a member can do in a team or organization, weakest first so roles
+/// compare with `>=`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Read the shared links
+    Viewer,
+    /// Also add, change and remove links
+    Editor,
+    /// Also manage members, rename and delete
+    Admin,
+}
+
+impl Role {
+    pub fn parse(role: &str) -> Option<Role> {
+        match role.trim().to_lowercase().as_str() {
+            "viewer" => Some(Role::Viewer),
+            "editor" => Some(Role::Editor),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+pub fn is_shared_owner_type(owner_type: &str) -> bool {
+    SHARED_OWNER_TYPES.contains(&owner_type)
+}
+
+/// Trim and collapse whitespace in a team or organization name. Returns
+/// None for names that are empty or too long.
+pub fn normalize_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return None;
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles() {
+        assert_eq!(Role::parse(" Editor "), Some(Role::Editor));
+        assert_eq!(Role::parse("owner"), None);
+        assert!(Role::Admin >= Role::Editor);
+        assert!(Role::Viewer < Role::Editor);
+        assert_eq!(Role::parse(Role::Admin.as_str()), Some(Role::Admin));
+    }
+}
@@ -0,0 +1,31 @@
+use sha2::{Digest, Sha256};
+
+/// Random secret for links sent by email and similar one-off credentials.
+/// Two v4 UUIDs give 244 random bits from the OS generator.
+pub fn generate() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Only hashes are stored, so a leaked database can't be used to redeem
+/// tokens. Tokens are long and random, a plain SHA-256 is enough.
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let token = generate();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate());
+        assert_eq!(hash(&token), hash(&format!(" {}\n", token)));
+        assert_ne!(hash(&token), token);
+    }
+}
@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use std::env;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::sessions;
+
 // Define the JWT claims structure
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserClaims {
@@ -11,6 +13,12 @@ pub struct UserClaims {
     pub email: String,
     pub exp: usize, // Expiration time (as UTC timestamp)
     pub iat: usize, // Issued at (as UTC timestamp)
+    // Unique id of this token, so it can be revoked on its own
+    #[serde(default)]
+    pub jti: String,
+    // Session the token was issued for, see sessions.rs
+    #[serde(default)]
+    pub sid: String,
 }
 
 // Helper function to get current timestamp
@@ -21,19 +29,21 @@ fn get_current_timestamp() -> usize {
         .as_secs() as usize
 }
 
-pub fn generate_jwt(user_id: &str, email: &str) -> Result<String> {
+pub fn generate_jwt(user_id: &str, email: &str, session_id: &str) -> Result<(String, UserClaims)> {
     // Get JWT secret from environment variable
     let secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
 
-    // Set expiration time to 100 years from now (effectively forever for a local app)
-    let exp = get_current_timestamp() + (100 * 365 * 24 * 60 * 60); // ~100 years in seconds
+    // Access tokens are short-lived, sessions are kept alive with refresh tokens
     let iat = get_current_timestamp();
+    let exp = iat + (sessions::ACCESS_TOKEN_TTL_MINS * 60) as usize;
 
     let claims = UserClaims {
         user_id: user_id.to_string(),
         email: email.to_string(),
         exp,
         iat,
+        jti: uuid::Uuid::new_v4().to_string(),
+        sid: session_id.to_string(),
     };
 
     // Create header
@@ -46,7 +56,7 @@ pub fn generate_jwt(user_id: &str, email: &str) -> Result<String> {
         &EncodingKey::from_secret(secret.as_bytes()),
     )?;
 
-    Ok(token)
+    Ok((token, claims))
 }
 
 pub fn validate_jwt(token: &str) -> Result<UserClaims> {
@@ -64,14 +74,3 @@ pub fn validate_jwt(token: &str) -> Result<UserClaims> {
 
     Ok(token_data.claims)
 }
-
-// Function to check if token needs refresh (if it's close to expiring)
-pub fn needs_refresh(token: &str) -> Result<bool> {
-    let claims = validate_jwt(token)?;
-
-    // Get current time
-    let now = get_current_timestamp();
-
-    // If token is more than 45 minutes old (75% of its lifetime), it needs refresh
-    Ok(claims.exp - now < 900) // 900 = 15 minutes left before expiration
-}
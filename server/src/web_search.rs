@@ -0,0 +1,296 @@
+use crate::database::Database;
+use crate::golinks;
+use crate::invitations::escape_html;
+use crate::suggestions::Suggestion;
+use anyhow::Result;
+use serde_json::json;
+
+/// Engine used when the user hasn't picked one, `%s` is the query
+pub const DEFAULT_ENGINE: &str = "https://www.google.com/search?q=%s";
+
+/// Longest keyword a search engine can have, without the "!"
+pub const MAX_KEYWORD_LENGTH: usize = 32;
+
+/// A `!keyword` at the start or end of a query and the rest of it
+#[derive(Debug, PartialEq, Eq)]
+pub struct Bang {
+    pub keyword: String,
+    pub terms: String,
+}
+
+/// Where a search from the address bar should go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// One of the user's links, or something typed as an address
+    Url(String),
+    /// A search with the user's engine
+    Search(String),
+}
+
+impl Target {
+    pub fn url(&self) -> &str {
+        match self {
+            Target::Url(url) | Target::Search(url) => url,
+        }
+    }
+}
+
+/// An address typed into the search box, with https:// added when it has
+/// no scheme. None for anything that reads like a search.
+pub fn typed_url(query: &str) -> Option<String> {
+    let query = query.trim();
+    if query.is_empty() || query.chars().any(char::is_whitespace) {
+        return None;
+    }
+    if query.starts_with("http://") || query.starts_with("https://") {
+        return url::Url::parse(query).ok().map(|url| url.to_string());
+    }
+
+    // A host with a dot and a top level domain of letters, like
+    // example.com or docs.rs/serde
+    let host = query.split(['/', '?', '#']).next()?;
+    let host = host.split(':').next()?;
+    let (_, tld) = host.rsplit_once('.')?;
+    if tld.len() < 2 || !tld.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    url::Url::parse(&format!("https://{}", query))
+        .ok()
+        .filter(|url| url.host_str().is_some())
+        .map(|url| url.to_string())
+}
+
+/// Fill an engine's URL template with a query. Templates without `%s`, like
+/// `https://www.google.com/search?q=`, get the query appended.
+pub fn engine_url(template: &str, query: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+    if template.contains("%s") {
+        template.replace("%s", &encoded)
+    } else {
+        format!("{}{}", template, encoded)
+    }
+}
+
+/// Where a bang goes, the engine's home page when there's nothing to search
+pub fn bang_url(template: &str, terms: &str) -> String {
+    if !terms.is_empty() {
+        return engine_url(template, terms);
+    }
+    url::Url::parse(&template.replace("%s", ""))
+        .map(|url| format!("{}/", url.origin().ascii_serialization()))
+        .unwrap_or_else(|_| engine_url(template, terms))
+}
+
+/// Lowercase a keyword and drop a leading "!". None for keywords that are
+/// empty, too long or have anything but letters, digits, `-`, `_` and `.`
+pub fn normalize_keyword(keyword: &str) -> Option<String> {
+    let keyword = keyword.trim();
+    let keyword = keyword.strip_prefix('!').unwrap_or(keyword).to_lowercase();
+    let valid = !keyword.is_empty()
+        && keyword.chars().count() <= MAX_KEYWORD_LENGTH
+        && keyword
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    valid.then_some(keyword)
+}
+
+/// Find a bang in a query, `!gh serde` or `serde !gh`. The first word wins
+/// when both ends have one.
+pub fn parse_bang(query: &str) -> Option<Bang> {
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let (first, rest) = words.split_first()?;
+    let (last, init) = words.split_last()?;
+    let (keyword, terms) = if first.len() > 1 && first.starts_with('!') {
+        (first, rest)
+    } else if last.len() > 1 && last.starts_with('!') {
+        (last, init)
+    } else {
+        return None;
+    };
+    Some(Bang {
+        keyword: normalize_keyword(keyword)?,
+        terms: terms.join(" "),
+    })
+}
+
+/// A search engine URL template the user may pick, http(s) only
+pub fn is_valid_engine(template: &str) -> bool {
+    url::Url::parse(&template.replace("%s", "q"))
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+}
+
+/// Resolve a query: a link the user named, then an address, then a search
+pub fn resolve(query: &str, shortcut: Option<String>, engine: Option<&str>) -> Target {
+    if let Some(url) = shortcut {
+        return Target::Url(url);
+    }
+    if let Some(url) = typed_url(query) {
+        return Target::Url(url);
+    }
+    Target::Search(engine_url(engine.unwrap_or(DEFAULT_ENGINE), query.trim()))
+}
+
+/// Where a query from someone without a token goes: always a search with
+/// the default engine. Following typed addresses would make /search an
+/// open redirect on the app's origin.
+pub fn resolve_anonymous(query: &str) -> Target {
+    Target::Search(engine_url(DEFAULT_ENGINE, query.trim()))
+}
+
+/// Resolve a query the way the search bar does for `user_id`: a bang, then
+/// a go link, a link title, an address and finally the default engine.
+/// Anonymous queries only get the built-in bangs and the default engine.
+pub async fn resolve_query(
+    database: &Database,
+    user_id: Option<&str>,
+    query: &str,
+) -> Result<Target> {
+    let query = query.trim();
+    if let Some(bang) = parse_bang(query)
+        && let Some(template) = database.find_bang(user_id, &bang.keyword).await?
+    {
+        return Ok(Target::Search(bang_url(&template, &bang.terms)));
+    }
+
+    let Some(user_id) = user_id else {
+        return Ok(resolve_anonymous(query));
+    };
+    // go/alias/rest follows the go link when there is one
+    if let Some(path) = query.strip_prefix("go/")
+        && !path.contains(char::is_whitespace)
+    {
+        let (alias, rest) = path.split_once('/').unwrap_or((path, ""));
+        if let Some(alias) = golinks::normalize_alias(alias)
+            && let Some(link) = database.resolve_alias(user_id, &alias).await?
+        {
+            return Ok(Target::Url(golinks::target_url(&link.url, rest, None)));
+        }
+    }
+    // A link title typed in full goes straight to the link
+    let shortcut = database.find_link_url_by_title(user_id, query).await?;
+    let engine = database
+        .get_default_search_engine(user_id)
+        .await?
+        .map(|engine| engine.url);
+    Ok(resolve(query, shortcut, engine.as_deref()))
+}
+
+/// OpenSearch description that registers Omega Tab as a search engine.
+/// With a `token` the browser searches as that user.
+pub fn description_xml(app_url: &str, token: Option<&str>) -> String {
+    let token_param = token
+        .map(|token| {
+            let encoded: String = url::form_urlencoded::byte_serialize(token.as_bytes()).collect();
+            format!("&token={}", encoded)
+        })
+        .unwrap_or_default();
+    let search = escape_html(&format!(
+        "{}/api/search?q={{searchTerms}}{}",
+        app_url, token_param
+    ));
+    let suggest = escape_html(&format!(
+        "{}/api/search/suggest?q={{searchTerms}}{}",
+        app_url, token_param
+    ));
+    let app_url = escape_html(app_url);
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/" xmlns:moz="http://www.mozilla.org/2006/browser/search/">
+  <ShortName>Omega Tab</ShortName>
+  <Description>Search your Omega Tab links and your search engine</Description>
+  <InputEncoding>UTF-8</InputEncoding>
+  <Image type="image/svg+xml">{app_url}/favicon.svg</Image>
+  <Url type="text/html" method="get" template="{search}"/>
+  <Url type="application/x-suggestions+json" method="get" template="{suggest}"/>
+  <moz:SearchForm>{app_url}/</moz:SearchForm>
+</OpenSearchDescription>
+"#
+    )
+}
+
+/// Suggestions in the OpenSearch suggestions format: the query,
+/// completions, descriptions (the source) and URLs for links
+pub fn suggestions_json(query: &str, suggestions: &[Suggestion]) -> serde_json::Value {
+    let completions: Vec<&str> = suggestions.iter().map(|s| s.query.as_str()).collect();
+    let descriptions: Vec<&str> = suggestions.iter().map(|s| s.source.as_str()).collect();
+    let urls: Vec<&str> = suggestions
+        .iter()
+        .map(|s| s.url.as_deref().unwrap_or(""))
+        .collect();
+    json!([query, completions, descriptions, urls])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(
+            typed_url("docs.rs/serde").as_deref(),
+            Some("https://docs.rs/serde")
+        );
+        assert_eq!(typed_url("localhost:3000"), None);
+        assert_eq!(typed_url("node.js tutorial"), None);
+        assert_eq!(typed_url("v1.2"), None);
+        assert_eq!(
+            typed_url("http://example.com/a?b=c").as_deref(),
+            Some("http://example.com/a?b=c")
+        );
+
+        assert_eq!(
+            resolve("rust & go", None, None),
+            Target::Search("https://www.google.com/search?q=rust+%26+go".to_string())
+        );
+        assert_eq!(
+            resolve("x", None, Some("https://www.bing.com/search?q=")).url(),
+            "https://www.bing.com/search?q=x"
+        );
+        assert_eq!(
+            resolve("mail", Some("https://mail.example.com".to_string()), None),
+            Target::Url("https://mail.example.com".to_string())
+        );
+        assert_eq!(
+            resolve_anonymous("https://evil.example"),
+            Target::Search(
+                "https://www.google.com/search?q=https%3A%2F%2Fevil.example".to_string()
+            )
+        );
+        assert!(!is_valid_engine("javascript:alert(%s)"));
+        assert!(is_valid_engine("https://duckduckgo.com/?q=%s"));
+    }
+
+    #[test]
+    fn test_parse_bang() {
+        let bang = |keyword: &str, terms: &str| {
+            Some(Bang {
+                keyword: keyword.to_string(),
+                terms: terms.to_string(),
+            })
+        };
+        assert_eq!(parse_bang("!GH  serde json"), bang("gh", "serde json"));
+        assert_eq!(parse_bang("serde json !gh"), bang("gh", "serde json"));
+        assert_eq!(parse_bang("!w"), bang("w", ""));
+        assert_eq!(parse_bang("hello! world"), None);
+        assert_eq!(parse_bang("! spaced"), None);
+        assert_eq!(parse_bang("!a?b c"), None);
+
+        assert_eq!(
+            bang_url("https://github.com/search?q=%s", "a b"),
+            "https://github.com/search?q=a+b"
+        );
+        assert_eq!(
+            bang_url("https://github.com/search?q=%s", ""),
+            "https://github.com/"
+        );
+    }
+
+    #[test]
+    fn test_description_xml() {
+        let xml = description_xml("https://tab.example", Some("omt_a&b"));
+        assert!(xml.contains(
+            r#"template="https://tab.example/api/search?q={searchTerms}&amp;token=omt_a%26b""#
+        ));
+    }
+}